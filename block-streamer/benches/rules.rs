@@ -0,0 +1,173 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use near_lake_framework::near_indexer_primitives::{IndexerShard, StreamerMessage};
+use registry_types::{
+    AffectedAccountRole, ComparisonOperator, DataPredicate, Rule, StateChangeKind, Status,
+};
+
+use block_streamer::receipt_resolver::ReceiptResolver;
+use block_streamer::rules::compiled_rule::CompiledRule;
+use block_streamer::rules::reduce_indexer_rule_matches;
+use block_streamer::rules::types::ChainId;
+
+/// Reads every block within `data/`, in height order
+fn read_streamer_messages() -> Vec<StreamerMessage> {
+    let data_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("data");
+
+    let mut block_dirs: Vec<_> = std::fs::read_dir(data_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        // Skips `data/invalid`, which only exists to stop `near-lake-framework` within tests
+        .filter(|path| {
+            path.file_name()
+                .and_then(|file_name| file_name.to_str())
+                .is_some_and(|file_name| file_name.parse::<u64>().is_ok())
+        })
+        .collect();
+    block_dirs.sort();
+
+    block_dirs
+        .into_iter()
+        .map(|block_dir| {
+            let block =
+                serde_json::from_slice(&std::fs::read(block_dir.join("block.json")).unwrap())
+                    .unwrap();
+
+            let mut shard_paths: Vec<_> = std::fs::read_dir(&block_dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|file_name| file_name.to_str())
+                        .is_some_and(|file_name| file_name.starts_with("shard_"))
+                })
+                .collect();
+            shard_paths.sort();
+
+            let shards: Vec<IndexerShard> = shard_paths
+                .into_iter()
+                .map(|shard_path| {
+                    serde_json::from_slice(&std::fs::read(shard_path).unwrap()).unwrap()
+                })
+                .collect();
+
+            StreamerMessage { block, shards }
+        })
+        .collect()
+}
+
+fn rules() -> Vec<(&'static str, Rule)> {
+    vec![
+        (
+            "action_any_exact",
+            Rule::ActionAny {
+                affected_account_id: "app.nearcrowd.near".to_string(),
+                status: Status::Success,
+                affected_account_role: AffectedAccountRole::Any,
+            },
+        ),
+        (
+            "action_any_account_list",
+            Rule::ActionAny {
+                affected_account_id: (0..100)
+                    .map(|index| format!("account-{index}.near"))
+                    .chain(["*.kaiching".to_string(), "token.sweat".to_string()])
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
+            },
+        ),
+        (
+            "action_function_call_args",
+            Rule::ActionFunctionCall {
+                affected_account_id: "app.nearcrowd.near".to_string(),
+                status: Status::Success,
+                affected_account_role: AffectedAccountRole::Any,
+                function: "claim_*".to_string(),
+                args_predicates: vec![DataPredicate {
+                    path: "bid".to_string(),
+                    operator: ComparisonOperator::GreaterThanOrEqual,
+                    value: "50000000000000000000000".to_string(),
+                }],
+                min_deposit: None,
+                min_gas: None,
+            },
+        ),
+        (
+            "event_data_predicate",
+            Rule::Event {
+                contract_account_id: "token.sweat".to_string(),
+                standard: "nep141".to_string(),
+                version: "*".to_string(),
+                event: "ft_transfer".to_string(),
+                data_predicate: Some(DataPredicate {
+                    path: "data[].new_owner_id".to_string(),
+                    operator: ComparisonOperator::Matches,
+                    value: "*.sweat".to_string(),
+                }),
+            },
+        ),
+        (
+            "composite",
+            Rule::All {
+                rules: vec![
+                    Rule::ActionAny {
+                        affected_account_id: "*.near".to_string(),
+                        status: Status::Any,
+                        affected_account_role: AffectedAccountRole::Any,
+                    },
+                    Rule::Not {
+                        rule: Box::new(Rule::ActionAny {
+                            affected_account_id: "*.sweat".to_string(),
+                            status: Status::Any,
+                            affected_account_role: AffectedAccountRole::Any,
+                        }),
+                    },
+                ],
+            },
+        ),
+        (
+            "state_change",
+            Rule::StateChange {
+                affected_account_id: "*".to_string(),
+                change_kinds: vec![
+                    StateChangeKind::AccountUpdate,
+                    StateChangeKind::ContractDataUpdate,
+                ],
+            },
+        ),
+    ]
+}
+
+fn reduce_indexer_rule_matches_benchmark(c: &mut Criterion) {
+    let streamer_messages = read_streamer_messages();
+
+    let mut receipt_resolver = ReceiptResolver::default();
+    for streamer_message in &streamer_messages {
+        receipt_resolver.track(streamer_message);
+    }
+
+    let mut group = c.benchmark_group("reduce_indexer_rule_matches");
+
+    for (name, rule) in rules() {
+        let compiled_rule = CompiledRule::from(&rule);
+
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for streamer_message in &streamer_messages {
+                    black_box(reduce_indexer_rule_matches(
+                        &compiled_rule,
+                        streamer_message,
+                        &receipt_resolver,
+                        ChainId::Mainnet,
+                    ));
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, reduce_indexer_rule_matches_benchmark);
+criterion_main!(benches);
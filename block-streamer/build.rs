@@ -1,5 +1,9 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("proto/block_streamer.proto")?;
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
+
+    tonic_build::configure()
+        .file_descriptor_set_path(out_dir.join("blockstreamer_descriptor.bin"))
+        .compile(&["proto/block_streamer.proto"], &["proto"])?;
 
     Ok(())
 }
@@ -0,0 +1,31 @@
+use tonic::Request;
+
+use block_streamer::block_streamer_client::BlockStreamerClient;
+use block_streamer::{
+    nested_rule::Rule, preview_rule_request::Range, ActionAnyRule, AffectedAccountRole, Chain,
+    NestedRule, PreviewRuleRequest, Status,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = BlockStreamerClient::connect("http://[::1]:10000").await?;
+
+    let response = client
+        .preview_rule(Request::new(PreviewRuleRequest {
+            rule: Some(NestedRule {
+                rule: Some(Rule::ActionAnyRule(ActionAnyRule {
+                    affected_account_id: "social.near".to_string(),
+                    status: Status::Success.into(),
+                    affected_account_role: AffectedAccountRole::Receiver.into(),
+                })),
+            }),
+            chain: Chain::Mainnet.into(),
+            range: Some(Range::LastBlocks(1000)),
+            limit: 10,
+        }))
+        .await?;
+
+    println!("{:#?}", response.into_inner());
+
+    Ok(())
+}
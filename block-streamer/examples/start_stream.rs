@@ -1,7 +1,10 @@
 use tonic::Request;
 
 use block_streamer::block_streamer_client::BlockStreamerClient;
-use block_streamer::{start_stream_request::Rule, ActionAnyRule, StartStreamRequest, Status};
+use block_streamer::{
+    start_stream_request::Rule, ActionAnyRule, AffectedAccountRole, Chain, StartStreamRequest,
+    Status,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -14,9 +17,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             function_name: "test".to_string(),
             version: 0,
             redis_stream: "morgs.near/test:block_stream".to_string(),
+            chain: Chain::Mainnet.into(),
+            block_delivery: None,
+            sink: None,
             rule: Some(Rule::ActionAnyRule(ActionAnyRule {
                 affected_account_id: "social.near".to_string(),
                 status: Status::Success.into(),
+                affected_account_role: AffectedAccountRole::Receiver.into(),
             })),
         }))
         .await?;
@@ -0,0 +1,23 @@
+use tonic::Request;
+
+use block_streamer::block_streamer_client::BlockStreamerClient;
+use block_streamer::TailStreamRequest;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = BlockStreamerClient::connect("http://[::1]:10000").await?;
+
+    let mut stream = client
+        .tail_stream(Request::new(TailStreamRequest {
+            // ID for indexer morgs.near/test
+            stream_id: "16210176318434468568".to_string(),
+        }))
+        .await?
+        .into_inner();
+
+    while let Some(response) = stream.message().await? {
+        println!("{:#?}", response);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,318 @@
+use std::collections::HashSet;
+use std::io::Write;
+
+use anyhow::Context;
+use base64::Engine;
+use near_lake_framework::near_indexer_primitives;
+use serde_json::Value;
+
+use crate::rules::state_changes_reducer::classify_state_change;
+use crate::rules::types::{IndexerRuleMatch, IndexerRuleMatchPayload};
+
+/// Matches the expiry of blocks cached by the legacy coordinator
+pub const DEFAULT_CACHE_TTL_SECONDS: u64 = 60;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum BlockDeliveryMode {
+    #[default]
+    None,
+    Inline,
+    Cache {
+        ttl_seconds: u64,
+    },
+}
+
+/// How matching blocks are made available to executors alongside their stream entries, saving
+/// them from fetching the same block from S3 again.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockDelivery {
+    pub mode: BlockDeliveryMode,
+    pub compress: bool,
+    pub matching_shards_only: bool,
+}
+
+impl BlockDelivery {
+    /// Builds the stream entry fields which deliver the block, caching it first if required.
+    pub async fn deliver(
+        &self,
+        redis_client: &crate::redis::RedisClient,
+        redis_stream: &str,
+        streamer_message: &near_indexer_primitives::StreamerMessage,
+        matches: &[IndexerRuleMatch],
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        let ttl_seconds = match self.mode {
+            BlockDeliveryMode::None => return Ok(vec![]),
+            BlockDeliveryMode::Inline => None,
+            BlockDeliveryMode::Cache { ttl_seconds } => Some(ttl_seconds),
+        };
+
+        let block = self.encode(streamer_message, matches)?;
+
+        let mut fields = vec![(
+            "block_encoding".to_string(),
+            self.get_encoding().to_string(),
+        )];
+
+        match ttl_seconds {
+            None => fields.push(("block".to_string(), block)),
+            Some(ttl_seconds) => {
+                let block_key =
+                    self.generate_block_key(redis_stream, streamer_message.block.header.height);
+
+                redis_client
+                    .set_ex(block_key.clone(), block, ttl_seconds)
+                    .await
+                    .context("Failed to cache block")?;
+
+                fields.push(("block_key".to_string(), block_key));
+            }
+        }
+
+        Ok(fields)
+    }
+
+    fn get_encoding(&self) -> &'static str {
+        if self.compress {
+            "gzip+base64"
+        } else {
+            "json"
+        }
+    }
+
+    /// Complete, uncompressed blocks are cached under the same key as the legacy coordinator so
+    /// they can be shared across streams and read by existing executors. All others are specific
+    /// to the stream.
+    fn generate_block_key(&self, redis_stream: &str, block_height: u64) -> String {
+        if self.compress || self.matching_shards_only {
+            format!("{}:streamer:message:{}", redis_stream, block_height)
+        } else {
+            generate_real_time_streamer_message_key(block_height)
+        }
+    }
+
+    fn encode(
+        &self,
+        streamer_message: &near_indexer_primitives::StreamerMessage,
+        matches: &[IndexerRuleMatch],
+    ) -> anyhow::Result<String> {
+        let mut message_value =
+            serde_json::to_value(streamer_message).context("Failed to serialize block")?;
+
+        if self.matching_shards_only {
+            let mut is_matching_shard = find_matching_shards(streamer_message, matches).into_iter();
+            if let Some(Value::Array(shards)) = message_value.get_mut("shards") {
+                shards.retain(|_| is_matching_shard.next().unwrap_or(false));
+            }
+        }
+
+        // Executors expect blocks in the same format as the legacy coordinator cached them
+        to_camel_case_keys(&mut message_value);
+
+        let json = serde_json::to_string(&message_value).context("Failed to serialize block")?;
+
+        if !self.compress {
+            return Ok(json);
+        }
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder
+            .write_all(json.as_bytes())
+            .context("Failed to compress block")?;
+        let compressed = encoder.finish().context("Failed to compress block")?;
+
+        Ok(base64::engine::general_purpose::STANDARD.encode(compressed))
+    }
+}
+
+pub fn generate_real_time_streamer_message_key(block_height: u64) -> String {
+    format!("streamer:message:{}", block_height)
+}
+
+/// Flags which shards, in order, contain the receipts or state changes matched by the rule.
+fn find_matching_shards(
+    streamer_message: &near_indexer_primitives::StreamerMessage,
+    matches: &[IndexerRuleMatch],
+) -> Vec<bool> {
+    let receipt_ids: HashSet<&str> = matches
+        .iter()
+        .filter_map(|indexer_rule_match| indexer_rule_match.payload.receipt_id())
+        .map(String::as_str)
+        .collect();
+    let state_change_account_ids: HashSet<&str> = matches
+        .iter()
+        .filter_map(|indexer_rule_match| match &indexer_rule_match.payload {
+            IndexerRuleMatchPayload::StateChanges { account_id, .. } => Some(account_id.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    streamer_message
+        .shards
+        .iter()
+        .map(|shard| {
+            shard.receipt_execution_outcomes.iter().any(|outcome| {
+                receipt_ids.contains(outcome.receipt.receipt_id.to_string().as_str())
+            }) || shard.state_changes.iter().any(|state_change| {
+                classify_state_change(state_change)
+                    .is_some_and(|(account_id, _)| state_change_account_ids.contains(account_id))
+            })
+        })
+        .collect()
+}
+
+fn to_camel_case_keys(message_value: &mut Value) {
+    match message_value {
+        Value::Object(map) => {
+            for key in map.keys().cloned().collect::<Vec<String>>() {
+                let new_key = key
+                    .split('_')
+                    .enumerate()
+                    .map(|(index, word)| {
+                        if index > 0 {
+                            let mut characters = word.chars();
+                            match characters.next() {
+                                Some(first) => first.to_uppercase().chain(characters).collect(),
+                                None => String::new(),
+                            }
+                        } else {
+                            word.to_string()
+                        }
+                    })
+                    .collect::<String>();
+
+                if let Some(mut value) = map.remove(&key) {
+                    to_camel_case_keys(&mut value);
+                    map.insert(new_key, value);
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                to_camel_case_keys(value);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Read;
+
+    use mockall::predicate;
+
+    use crate::receipt_resolver::ReceiptResolver;
+    use crate::rules::compiled_rule::CompiledRule;
+    use crate::rules::types::ChainId;
+
+    fn get_matches(
+        streamer_message: &near_indexer_primitives::StreamerMessage,
+    ) -> Vec<IndexerRuleMatch> {
+        let mut receipt_resolver = ReceiptResolver::default();
+        receipt_resolver.track(streamer_message);
+
+        crate::rules::reduce_indexer_rule_matches(
+            &CompiledRule::from(&registry_types::Rule::ActionFunctionCall {
+                affected_account_id: "app.nearcrowd.near".to_string(),
+                status: registry_types::Status::Any,
+                affected_account_role: registry_types::AffectedAccountRole::Any,
+                function: "approve_solution".to_string(),
+                args_predicates: vec![],
+                min_deposit: None,
+                min_gas: None,
+            }),
+            streamer_message,
+            &receipt_resolver,
+            ChainId::Mainnet,
+        )
+    }
+
+    #[tokio::test]
+    async fn delivers_compressed_matching_shards_inline() {
+        let streamer_message = crate::test_utils::get_streamer_message(93085141);
+        let matches = get_matches(&streamer_message);
+
+        let block_delivery = BlockDelivery {
+            mode: BlockDeliveryMode::Inline,
+            compress: true,
+            matching_shards_only: true,
+        };
+
+        let fields = block_delivery
+            .deliver(
+                &crate::redis::RedisClient::default(),
+                "stream key",
+                &streamer_message,
+                &matches,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fields[0],
+            ("block_encoding".to_string(), "gzip+base64".to_string())
+        );
+        assert_eq!(fields[1].0, "block");
+
+        let compressed = base64::engine::general_purpose::STANDARD
+            .decode(&fields[1].1)
+            .unwrap();
+        let mut json = String::new();
+        flate2::read::GzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut json)
+            .unwrap();
+        let block: Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(block["block"]["header"]["height"], 93085141);
+        assert_eq!(block["shards"].as_array().unwrap().len(), 1);
+        assert!(block["shards"][0]["receiptExecutionOutcomes"].is_array());
+    }
+
+    #[tokio::test]
+    async fn caches_complete_blocks_under_legacy_key() {
+        let streamer_message = crate::test_utils::get_streamer_message(93085141);
+        let matches = get_matches(&streamer_message);
+
+        let mut mock_redis_client = crate::redis::RedisClient::default();
+        mock_redis_client
+            .expect_set_ex::<String, String>()
+            .with(
+                predicate::eq("streamer:message:93085141".to_string()),
+                predicate::always(),
+                predicate::eq(60),
+            )
+            .returning(|_, _, _| Ok(()))
+            .times(1);
+
+        let block_delivery = BlockDelivery {
+            mode: BlockDeliveryMode::Cache {
+                ttl_seconds: DEFAULT_CACHE_TTL_SECONDS,
+            },
+            compress: false,
+            matching_shards_only: false,
+        };
+
+        let fields = block_delivery
+            .deliver(
+                &mock_redis_client,
+                "stream key",
+                &streamer_message,
+                &matches,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fields,
+            vec![
+                ("block_encoding".to_string(), "json".to_string()),
+                (
+                    "block_key".to_string(),
+                    "streamer:message:93085141".to_string()
+                ),
+            ]
+        );
+    }
+}
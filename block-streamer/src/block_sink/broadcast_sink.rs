@@ -0,0 +1,105 @@
+use super::BlockSink;
+
+/// The number of published blocks buffered for each subscriber, beyond which slow subscribers
+/// miss blocks rather than slowing down the block stream
+pub const BROADCAST_CAPACITY: usize = 100;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublishedBlock {
+    pub block_height: u64,
+    pub fields: Vec<(String, String)>,
+}
+
+/// Forwards entries to the wrapped sink, then broadcasts those written to any subscribers, e.g.
+/// clients tailing the block stream. Entries the sink skipped as already published are not
+/// broadcast again.
+pub struct BroadcastSink {
+    block_sink: std::sync::Arc<dyn BlockSink>,
+    sender: tokio::sync::broadcast::Sender<PublishedBlock>,
+}
+
+impl BroadcastSink {
+    pub fn new(
+        block_sink: std::sync::Arc<dyn BlockSink>,
+        sender: tokio::sync::broadcast::Sender<PublishedBlock>,
+    ) -> Self {
+        Self { block_sink, sender }
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockSink for BroadcastSink {
+    async fn publish(
+        &self,
+        block_height: u64,
+        fields: &[(String, String)],
+    ) -> anyhow::Result<bool> {
+        let written = self.block_sink.publish(block_height, fields).await?;
+
+        // Fields are only copied while there are subscribers. Sending still fails should the last
+        // subscriber unsubscribe in the meantime, which is ignored.
+        if written && self.sender.receiver_count() > 0 {
+            let _ = self.sender.send(PublishedBlock {
+                block_height,
+                fields: fields.to_vec(),
+            });
+        }
+
+        Ok(written)
+    }
+
+    fn records_last_published_block(&self) -> bool {
+        self.block_sink.records_last_published_block()
+    }
+
+    async fn pending_entries(&self) -> anyhow::Result<Option<u64>> {
+        self.block_sink.pending_entries().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn broadcasts_published_blocks() {
+        let mut mock_block_sink = crate::block_sink::MockBlockSink::new();
+        mock_block_sink
+            .expect_publish()
+            .returning(|_, _| Ok(true))
+            .once();
+
+        let (sender, mut receiver) = tokio::sync::broadcast::channel(BROADCAST_CAPACITY);
+        let broadcast_sink = BroadcastSink::new(std::sync::Arc::new(mock_block_sink), sender);
+
+        broadcast_sink
+            .publish(1, &[("block_height".to_string(), "1".to_string())])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            PublishedBlock {
+                block_height: 1,
+                fields: vec![("block_height".to_string(), "1".to_string())],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn does_not_broadcast_skipped_blocks() {
+        let mut mock_block_sink = crate::block_sink::MockBlockSink::new();
+        mock_block_sink
+            .expect_publish()
+            .returning(|block_height, _| Ok(block_height != 1))
+            .times(2);
+
+        let (sender, mut receiver) = tokio::sync::broadcast::channel(BROADCAST_CAPACITY);
+        let broadcast_sink = BroadcastSink::new(std::sync::Arc::new(mock_block_sink), sender);
+
+        assert!(!broadcast_sink.publish(1, &[]).await.unwrap());
+        assert!(broadcast_sink.publish(2, &[]).await.unwrap());
+
+        assert_eq!(receiver.recv().await.unwrap().block_height, 2);
+    }
+}
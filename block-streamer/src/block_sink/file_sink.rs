@@ -0,0 +1,84 @@
+use anyhow::Context;
+use tokio::io::AsyncWriteExt;
+
+use super::BlockSink;
+
+/// Appends entries to a file as newline delimited JSON, for local debugging or ingestion by
+/// other tools.
+pub struct FileSink {
+    file: tokio::sync::Mutex<tokio::fs::File>,
+}
+
+impl FileSink {
+    pub async fn create(path: &std::path::Path) -> anyhow::Result<Self> {
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("Failed to open block sink file {}", path.display()))?;
+
+        Ok(Self {
+            file: tokio::sync::Mutex::new(file),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockSink for FileSink {
+    async fn publish(
+        &self,
+        _block_height: u64,
+        fields: &[(String, String)],
+    ) -> anyhow::Result<bool> {
+        let mut line = super::fields_to_json(fields)?;
+        line.push('\n');
+
+        let mut file = self.file.lock().await;
+        file.write_all(line.as_bytes())
+            .await
+            .context("Failed to write block to file")?;
+        file.flush()
+            .await
+            .context("Failed to write block to file")?;
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn appends_entries_as_json_lines() {
+        let path = std::env::temp_dir().join(format!(
+            "block-streamer-file-sink-{}.ndjson",
+            std::process::id()
+        ));
+
+        let file_sink = FileSink::create(&path).await.unwrap();
+        file_sink
+            .publish(1, &[("block_height".to_string(), "1".to_string())])
+            .await
+            .unwrap();
+        file_sink
+            .publish(
+                2,
+                &[
+                    ("block_height".to_string(), "2".to_string()),
+                    ("block_hash".to_string(), "hash".to_string()),
+                ],
+            )
+            .await
+            .unwrap();
+
+        let contents = tokio::fs::read_to_string(&path).await.unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        assert_eq!(
+            contents,
+            "{\"block_height\":\"1\"}\n{\"block_hash\":\"hash\",\"block_height\":\"2\"}\n"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::Context;
+
+use super::BlockSink;
+
+/// Publishes entries as JSON messages to a NATS JetStream subject, waiting for each to be
+/// acknowledged so that entries are persisted in order. Messages are identified by block height,
+/// so JetStream discards blocks re-published within its duplicate window.
+pub struct JetStreamSink {
+    context: async_nats::jetstream::Context,
+    subject: String,
+}
+
+impl JetStreamSink {
+    pub async fn connect(url: &str, subject: String) -> anyhow::Result<Self> {
+        let client = async_nats::connect(url)
+            .await
+            .with_context(|| format!("Failed to connect to NATS at {}", url))?;
+
+        Ok(Self {
+            context: async_nats::jetstream::new(client),
+            subject,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockSink for JetStreamSink {
+    async fn publish(
+        &self,
+        block_height: u64,
+        fields: &[(String, String)],
+    ) -> anyhow::Result<bool> {
+        let payload = super::fields_to_json(fields)?;
+
+        let mut headers = async_nats::HeaderMap::new();
+        headers.insert(
+            "Nats-Msg-Id",
+            format!("{}:{}", self.subject, block_height).as_str(),
+        );
+
+        let ack = self
+            .context
+            .publish_with_headers(self.subject.clone(), headers, payload.into())
+            .await
+            .context("Failed to publish block to JetStream")?
+            .await
+            .context("Block was not acknowledged by JetStream")?;
+
+        if ack.duplicate {
+            tracing::debug!(
+                block_height,
+                "Skipping block already published to JetStream"
+            );
+        }
+
+        Ok(!ack.duplicate)
+    }
+}
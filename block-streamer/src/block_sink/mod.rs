@@ -0,0 +1,175 @@
+mod broadcast_sink;
+mod file_sink;
+mod jet_stream_sink;
+mod redis_stream_sink;
+
+pub use broadcast_sink::{BroadcastSink, PublishedBlock, BROADCAST_CAPACITY};
+pub use file_sink::FileSink;
+pub use jet_stream_sink::JetStreamSink;
+pub use redis_stream_sink::RedisStreamSink;
+
+/// Destination of the entries published by a block stream, one per matching block.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait::async_trait]
+pub trait BlockSink: Send + Sync {
+    /// Publishes the entry for a block, returning whether it was written. Sinks which can do so
+    /// skip blocks which have already been published, so that restarted streams do not deliver
+    /// them twice.
+    async fn publish(&self, block_height: u64, fields: &[(String, String)])
+        -> anyhow::Result<bool>;
+
+    /// Whether `publish` also records the block as the last published block, atomically with
+    /// publishing it. Otherwise the block stream records it after publishing.
+    fn records_last_published_block(&self) -> bool {
+        false
+    }
+
+    /// The number of published entries not yet consumed, or `None` when this is unknown, in
+    /// which case back-pressure is not applied.
+    async fn pending_entries(&self) -> anyhow::Result<Option<u64>> {
+        Ok(None)
+    }
+}
+
+/// Which sink a block stream publishes to. Streams publish to their Redis Stream by default.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum BlockSinkConfig {
+    #[default]
+    RedisStream,
+    File {
+        path: std::path::PathBuf,
+    },
+    JetStream {
+        url: String,
+        subject: String,
+    },
+}
+
+/// Restricts the sinks which clients may request, as sinks are opened by the block streamer on
+/// their behalf.
+#[derive(Clone, Debug, Default)]
+pub struct BlockSinkPolicy {
+    /// Directory which file sinks are created within. File sinks are rejected when unset.
+    pub file_directory: Option<std::path::PathBuf>,
+    /// NATS servers which JetStream sinks may connect to.
+    pub allowed_nats_urls: Vec<String>,
+}
+
+impl BlockSinkPolicy {
+    /// Resolves a file sink path within the file sink directory. Absolute paths and paths with
+    /// `..` components are rejected, as they could escape it.
+    pub fn resolve_file_path(&self, path: &str) -> anyhow::Result<std::path::PathBuf> {
+        let Some(file_directory) = &self.file_directory else {
+            anyhow::bail!("File sinks are not enabled");
+        };
+
+        let path = std::path::Path::new(path);
+
+        if !path
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_)))
+        {
+            anyhow::bail!(
+                "File sink path must be relative to the file sink directory: {}",
+                path.display()
+            );
+        }
+
+        Ok(file_directory.join(path))
+    }
+
+    pub fn check_nats_url(&self, url: &str) -> anyhow::Result<()> {
+        if !self
+            .allowed_nats_urls
+            .iter()
+            .any(|allowed_url| allowed_url == url)
+        {
+            anyhow::bail!("NATS server is not allowed: {}", url);
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn build(
+    block_sink_config: &BlockSinkConfig,
+    redis_client: std::sync::Arc<crate::redis::RedisClient>,
+    redis_stream: &str,
+    last_published_block_key: &str,
+) -> anyhow::Result<std::sync::Arc<dyn BlockSink>> {
+    let block_sink: std::sync::Arc<dyn BlockSink> = match block_sink_config {
+        BlockSinkConfig::RedisStream => std::sync::Arc::new(RedisStreamSink::new(
+            redis_client,
+            redis_stream.to_string(),
+            last_published_block_key.to_string(),
+        )),
+        BlockSinkConfig::File { path } => std::sync::Arc::new(FileSink::create(path).await?),
+        BlockSinkConfig::JetStream { url, subject } => {
+            std::sync::Arc::new(JetStreamSink::connect(url, subject.clone()).await?)
+        }
+    };
+
+    Ok(block_sink)
+}
+
+/// Serializes stream entry fields as a single JSON object, for sinks without a notion of fields.
+fn fields_to_json(fields: &[(String, String)]) -> anyhow::Result<String> {
+    let object: serde_json::Map<String, serde_json::Value> = fields
+        .iter()
+        .map(|(field, value)| (field.clone(), serde_json::Value::String(value.clone())))
+        .collect();
+
+    Ok(serde_json::to_string(&object)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_block_sink_policy() -> BlockSinkPolicy {
+        BlockSinkPolicy {
+            file_directory: Some(std::path::PathBuf::from("/var/lib/block-streamer")),
+            allowed_nats_urls: vec!["nats://nats:4222".to_string()],
+        }
+    }
+
+    #[test]
+    fn resolves_file_paths_within_file_directory() {
+        assert_eq!(
+            create_block_sink_policy()
+                .resolve_file_path("morgs.near/test.ndjson")
+                .unwrap(),
+            std::path::PathBuf::from("/var/lib/block-streamer/morgs.near/test.ndjson")
+        );
+    }
+
+    #[test]
+    fn rejects_file_paths_which_escape_file_directory() {
+        let block_sink_policy = create_block_sink_policy();
+
+        assert!(block_sink_policy.resolve_file_path("/etc/passwd").is_err());
+        assert!(block_sink_policy
+            .resolve_file_path("../../etc/passwd")
+            .is_err());
+        assert!(block_sink_policy
+            .resolve_file_path("morgs.near/../../test.ndjson")
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_file_paths_when_file_sinks_are_not_enabled() {
+        assert!(BlockSinkPolicy::default()
+            .resolve_file_path("test.ndjson")
+            .is_err());
+    }
+
+    #[test]
+    fn only_allows_listed_nats_urls() {
+        let block_sink_policy = create_block_sink_policy();
+
+        assert!(block_sink_policy.check_nats_url("nats://nats:4222").is_ok());
+        assert!(block_sink_policy
+            .check_nats_url("nats://internal:4222")
+            .is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use anyhow::Context;
+
+use super::BlockSink;
+
+/// Adds entries to the Redis Stream of the block stream, the default sink. Entry IDs are derived
+/// from block heights, so blocks which have already been added are skipped.
+pub struct RedisStreamSink {
+    redis_client: std::sync::Arc<crate::redis::RedisClient>,
+    redis_stream: String,
+    last_published_block_key: String,
+}
+
+impl RedisStreamSink {
+    pub fn new(
+        redis_client: std::sync::Arc<crate::redis::RedisClient>,
+        redis_stream: String,
+        last_published_block_key: String,
+    ) -> Self {
+        Self {
+            redis_client,
+            redis_stream,
+            last_published_block_key,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockSink for RedisStreamSink {
+    async fn publish(
+        &self,
+        block_height: u64,
+        fields: &[(String, String)],
+    ) -> anyhow::Result<bool> {
+        let added = self
+            .redis_client
+            .xadd_block(
+                self.redis_stream.clone(),
+                self.last_published_block_key.clone(),
+                block_height,
+                fields,
+            )
+            .await
+            .context("Failed to add block to Redis Stream")?;
+
+        if !added {
+            tracing::debug!(block_height, "Skipping block already added to Redis Stream");
+        }
+
+        Ok(added)
+    }
+
+    fn records_last_published_block(&self) -> bool {
+        true
+    }
+
+    async fn pending_entries(&self) -> anyhow::Result<Option<u64>> {
+        let stream_length = self
+            .redis_client
+            .xlen(self.redis_stream.clone())
+            .await
+            .context("Failed to get Redis Stream length")?;
+
+        Ok(Some(stream_length))
+    }
+}
@@ -1,22 +1,240 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
 use anyhow::Context;
+use futures::{StreamExt, TryStreamExt};
 use near_lake_framework::near_indexer_primitives;
 use tokio::task::JoinHandle;
 
+use crate::block_delivery::BlockDelivery;
+use crate::block_sink::{BlockSink, BroadcastSink, PublishedBlock, BROADCAST_CAPACITY};
 use crate::indexer_config::IndexerConfig;
-use crate::rules::types::ChainId;
+use crate::receipt_resolver::ReceiptResolver;
+use crate::rules::compiled_rule::CompiledRule;
+use crate::rules::types::{ChainId, IndexerRuleMatch, IndexerRuleMatchPayload};
 use registry_types::Rule;
 
 /// The number of blocks to prefetch within `near-lake-framework`. The internal default is 100, but
 /// we need this configurable for testing purposes.
 const LAKE_PREFETCH_SIZE: usize = 100;
+/// The number of candidate blocks fetched from Delta Lake ahead of the block being verified
+pub(crate) const MAX_PREFETCHED_CANDIDATE_BLOCKS: usize = 10;
+/// How often, in blocks, the receipts tracked while streaming from NEAR Lake are persisted
+const RECEIPT_RESOLVER_SNAPSHOT_INTERVAL_BLOCKS: u64 = 100;
+/// The most blocks re-streamed from NEAR Lake to resume tracking receipts from a persisted
+/// snapshot, beyond which receipts created before the start block are left unresolved
+const MAX_RECEIPT_RESOLVER_REPLAY_BLOCKS: u64 = 1_000;
+/// How often the length of a paused block stream's Redis Stream is checked
+const BACK_PRESSURE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
 
 pub struct Task {
     handle: JoinHandle<anyhow::Result<()>>,
     cancellation_token: tokio_util::sync::CancellationToken,
 }
 
+/// Progress of the historical backfill from Delta Lake index files, updated by the block stream
+/// task as each date is published.
+#[derive(Debug, Default)]
+pub struct BackfillProgress {
+    pub index_files_total: AtomicU64,
+    pub index_files_processed: AtomicU64,
+    pub blocks_published: AtomicU64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StreamState {
+    #[default]
+    Starting,
+    BackfillingFromDeltaLake,
+    StreamingFromLake,
+    Restarting,
+    Failed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamError {
+    pub message: String,
+    pub occurred_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Status of a block stream task, updated by the task as blocks are processed so that failed
+/// streams can be identified via `ListStreams`.
+#[derive(Debug, Default)]
+pub struct StreamStatus {
+    state: Mutex<StreamState>,
+    last_error: Mutex<Option<StreamError>>,
+    pub last_published_block: AtomicU64,
+    pub blocks_scanned: AtomicU64,
+    pub matches_published: AtomicU64,
+    pub restarts: AtomicU64,
+    /// Held while a block is being published, so that cancellation can wait for it to finish
+    publishing: tokio::sync::Mutex<()>,
+}
+
+impl StreamStatus {
+    pub fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    pub fn last_error(&self) -> Option<StreamError> {
+        self.last_error.lock().unwrap().clone()
+    }
+
+    fn set_state(&self, state: StreamState) {
+        *self.state.lock().unwrap() = state;
+    }
+
+    fn record_error(&self, err: &anyhow::Error) {
+        *self.last_error.lock().unwrap() = Some(StreamError {
+            message: format!("{:?}", err),
+            occurred_at: chrono::Utc::now(),
+        });
+    }
+
+    fn record_published(&self, block_height: near_indexer_primitives::types::BlockHeight) {
+        self.last_published_block
+            .store(block_height, Ordering::Relaxed);
+        self.matches_published.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Limits on restarting a failed block stream task. Restarts back off exponentially from
+/// `initial_backoff` up to `max_backoff`, and the task is left `Failed`, for the coordinator to
+/// restart, once it has been restarted `max_restarts` times within `window`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RestartPolicy {
+    pub initial_backoff: std::time::Duration,
+    pub max_backoff: std::time::Duration,
+    pub max_restarts: usize,
+    pub window: std::time::Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: std::time::Duration::from_secs(1),
+            max_backoff: std::time::Duration::from_secs(60),
+            max_restarts: 5,
+            window: std::time::Duration::from_secs(600),
+        }
+    }
+}
+
+struct Supervisor {
+    restart_policy: RestartPolicy,
+    restarted_at: std::collections::VecDeque<tokio::time::Instant>,
+}
+
+impl Supervisor {
+    fn new(restart_policy: RestartPolicy) -> Self {
+        Self {
+            restart_policy,
+            restarted_at: std::collections::VecDeque::new(),
+        }
+    }
+
+    /// Records a restart, returning how long to wait before restarting, or `None` once the
+    /// restart limit within the window has been reached.
+    fn next_backoff(&mut self) -> Option<std::time::Duration> {
+        let now = tokio::time::Instant::now();
+
+        while self.restarted_at.front().is_some_and(|restarted_at| {
+            now.duration_since(*restarted_at) > self.restart_policy.window
+        }) {
+            self.restarted_at.pop_front();
+        }
+
+        if self.restarted_at.len() >= self.restart_policy.max_restarts {
+            return None;
+        }
+
+        let backoff = self
+            .restart_policy
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(self.restarted_at.len() as u32))
+            .min(self.restart_policy.max_backoff);
+
+        self.restarted_at.push_back(now);
+
+        Some(backoff)
+    }
+}
+
+/// Bounds on the length of a block stream's Redis Stream. Publishing pauses once the stream
+/// reaches `high` entries, and resumes once executors have drained it to `low` entries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RedisStreamWatermarks {
+    pub high: u64,
+    pub low: u64,
+}
+
+impl Default for RedisStreamWatermarks {
+    fn default() -> Self {
+        Self {
+            high: 10_000,
+            low: 5_000,
+        }
+    }
+}
+
+/// Stops a block stream from consuming blocks while its Redis Stream is above the high watermark,
+/// so that a slow or stopped executor does not cause entries to accumulate indefinitely.
+#[derive(Debug)]
+pub struct BackPressure {
+    watermarks: RedisStreamWatermarks,
+    paused: AtomicBool,
+}
+
+impl BackPressure {
+    pub fn new(watermarks: RedisStreamWatermarks) -> Self {
+        Self {
+            watermarks,
+            paused: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    async fn wait_for_capacity(&self, block_sink: &dyn BlockSink) -> anyhow::Result<()> {
+        let Some(mut pending_entries) = block_sink.pending_entries().await? else {
+            return Ok(());
+        };
+
+        if pending_entries < self.watermarks.high {
+            return Ok(());
+        }
+
+        self.paused.store(true, Ordering::Relaxed);
+        tracing::warn!(
+            pending_entries,
+            high_watermark = self.watermarks.high,
+            "Pausing block stream until Redis Stream is drained",
+        );
+
+        while pending_entries > self.watermarks.low {
+            tokio::time::sleep(BACK_PRESSURE_POLL_INTERVAL).await;
+
+            pending_entries = block_sink.pending_entries().await?.unwrap_or_default();
+        }
+
+        self.paused.store(false, Ordering::Relaxed);
+        tracing::info!(pending_entries, "Resuming block stream");
+
+        Ok(())
+    }
+}
+
 pub struct BlockStream {
     task: Option<Task>,
+    restart_policy: RestartPolicy,
+    published_blocks: tokio::sync::broadcast::Sender<PublishedBlock>,
+    compiled_rule: std::sync::Arc<CompiledRule>,
+    pub backfill_progress: std::sync::Arc<BackfillProgress>,
+    pub status: std::sync::Arc<StreamStatus>,
+    pub back_pressure: std::sync::Arc<BackPressure>,
+    pub block_delivery: BlockDelivery,
     pub indexer_config: IndexerConfig,
     pub chain_id: ChainId,
     pub version: u64,
@@ -29,9 +247,18 @@ impl BlockStream {
         chain_id: ChainId,
         version: u64,
         redis_stream: String,
+        redis_stream_watermarks: RedisStreamWatermarks,
+        block_delivery: BlockDelivery,
     ) -> Self {
         Self {
             task: None,
+            restart_policy: RestartPolicy::default(),
+            published_blocks: tokio::sync::broadcast::channel(BROADCAST_CAPACITY).0,
+            compiled_rule: std::sync::Arc::new(CompiledRule::from(&indexer_config.rule)),
+            backfill_progress: std::sync::Arc::new(BackfillProgress::default()),
+            status: std::sync::Arc::new(StreamStatus::default()),
+            back_pressure: std::sync::Arc::new(BackPressure::new(redis_stream_watermarks)),
+            block_delivery,
             indexer_config,
             chain_id,
             version,
@@ -45,6 +272,7 @@ impl BlockStream {
         redis_client: std::sync::Arc<crate::redis::RedisClient>,
         delta_lake_client: std::sync::Arc<crate::delta_lake_client::DeltaLakeClient>,
         lake_s3_config: aws_sdk_s3::Config,
+        block_sink: std::sync::Arc<dyn BlockSink>,
     ) -> anyhow::Result<()> {
         if self.task.is_some() {
             return Err(anyhow::anyhow!("BlockStreamer has already been started",));
@@ -54,40 +282,108 @@ impl BlockStream {
         let cancellation_token_clone = cancellation_token.clone();
 
         let indexer_config = self.indexer_config.clone();
+        let compiled_rule = self.compiled_rule.clone();
+        let backfill_progress = self.backfill_progress.clone();
+        let status = self.status.clone();
+        let back_pressure = self.back_pressure.clone();
+        let block_delivery = self.block_delivery.clone();
         let chain_id = self.chain_id.clone();
         let redis_stream = self.redis_stream.clone();
 
+        let restart_policy = self.restart_policy;
+        let block_sink = BroadcastSink::new(block_sink, self.published_blocks.clone());
+
         let handle = tokio::spawn(async move {
-            tokio::select! {
-                _ = cancellation_token_clone.cancelled() => {
-                    tracing::info!(
-                        account_id = indexer_config.account_id.as_str(),
-                        function_name = indexer_config.function_name,
-                        "Cancelling block stream task",
-                    );
+            let mut supervisor = Supervisor::new(restart_policy);
+            let mut start_block_height = start_block_height;
+            let mut is_restarting = false;
 
-                    Ok(())
-                },
-                result = start_block_stream(
-                    start_block_height,
-                    &indexer_config,
-                    redis_client,
-                    delta_lake_client,
-                    lake_s3_config,
-                    &chain_id,
-                    LAKE_PREFETCH_SIZE,
-                    redis_stream
-                ) => {
-                    result.map_err(|err| {
-                        tracing::error!(
+            loop {
+                let result = tokio::select! {
+                    // Blocks which are part way through publishing are allowed to finish, so that
+                    // they are not left published without being recorded as such
+                    _publishing = async {
+                        cancellation_token_clone.cancelled().await;
+                        status.publishing.lock().await
+                    } => {
+                        tracing::info!(
                             account_id = indexer_config.account_id.as_str(),
                             function_name = indexer_config.function_name,
-                            "Block stream task stopped due to error: {:?}",
-                            err,
+                            "Cancelling block stream task",
                         );
-                        err
-                    })
+
+                        return Ok(());
+                    },
+                    result = async {
+                        if is_restarting {
+                            start_block_height = get_restart_block_height(
+                                &redis_client,
+                                &indexer_config,
+                                start_block_height,
+                            )
+                            .await?;
+                        }
+
+                        crate::metrics::with_s3_request_block_stream(
+                            indexer_config.account_id.as_str(),
+                            &indexer_config.function_name,
+                            start_block_stream(
+                                start_block_height,
+                                &indexer_config,
+                                &compiled_rule,
+                                redis_client.clone(),
+                                delta_lake_client.clone(),
+                                lake_s3_config.clone(),
+                                &chain_id,
+                                LAKE_PREFETCH_SIZE,
+                                redis_stream.clone(),
+                                &block_sink,
+                                &backfill_progress,
+                                &status,
+                                &back_pressure,
+                                &block_delivery,
+                            ),
+                        )
+                        .await
+                    } => result,
+                };
+
+                let Err(err) = result else {
+                    return Ok(());
+                };
+
+                status.record_error(&err);
+
+                let Some(backoff) = supervisor.next_backoff() else {
+                    status.set_state(StreamState::Failed);
+
+                    tracing::error!(
+                        account_id = indexer_config.account_id.as_str(),
+                        function_name = indexer_config.function_name,
+                        "Block stream task stopped due to error: {:?}",
+                        err,
+                    );
+
+                    return Err(err);
+                };
+
+                status.set_state(StreamState::Restarting);
+
+                tracing::warn!(
+                    account_id = indexer_config.account_id.as_str(),
+                    function_name = indexer_config.function_name,
+                    backoff_ms = backoff.as_millis() as u64,
+                    "Restarting block stream task after error: {:?}",
+                    err,
+                );
+
+                tokio::select! {
+                    _ = cancellation_token_clone.cancelled() => return Ok(()),
+                    _ = tokio::time::sleep(backoff) => {},
                 }
+
+                status.restarts.fetch_add(1, Ordering::Relaxed);
+                is_restarting = true;
             }
         });
 
@@ -99,11 +395,28 @@ impl BlockStream {
         Ok(())
     }
 
+    /// Receives each block as it is published, until the block stream is stopped.
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<PublishedBlock> {
+        self.published_blocks.subscribe()
+    }
+
     pub async fn cancel(&mut self) -> anyhow::Result<()> {
         if let Some(task) = self.task.take() {
             task.cancellation_token.cancel();
             let _ = task.handle.await?;
 
+            tracing::info!(
+                account_id = self.indexer_config.account_id.as_str(),
+                function_name = self.indexer_config.function_name,
+                last_published_block = self.status.last_published_block.load(Ordering::Relaxed),
+                "Stopped block stream",
+            );
+
+            crate::metrics::remove_block_stream_metrics(
+                self.indexer_config.account_id.as_str(),
+                &self.indexer_config.function_name,
+            );
+
             return Ok(());
         }
 
@@ -125,32 +438,54 @@ impl BlockStream {
 pub(crate) async fn start_block_stream(
     start_block_height: near_indexer_primitives::types::BlockHeight,
     indexer: &IndexerConfig,
+    compiled_rule: &CompiledRule,
     redis_client: std::sync::Arc<crate::redis::RedisClient>,
     delta_lake_client: std::sync::Arc<crate::delta_lake_client::DeltaLakeClient>,
     lake_s3_config: aws_sdk_s3::Config,
     chain_id: &ChainId,
     lake_prefetch_size: usize,
     redis_stream: String,
+    block_sink: &dyn BlockSink,
+    backfill_progress: &BackfillProgress,
+    status: &StreamStatus,
+    back_pressure: &BackPressure,
+    block_delivery: &BlockDelivery,
 ) -> anyhow::Result<()> {
     tracing::info!("Starting block stream",);
 
+    status.set_state(StreamState::BackfillingFromDeltaLake);
+
     let last_indexed_delta_lake_block = process_delta_lake_blocks(
         start_block_height,
         delta_lake_client,
         redis_client.clone(),
         indexer,
+        compiled_rule,
         redis_stream.clone(),
+        block_sink,
+        chain_id,
+        backfill_progress,
+        status,
+        back_pressure,
+        block_delivery,
     )
     .await?;
 
+    status.set_state(StreamState::StreamingFromLake);
+
     let last_indexed_near_lake_block = process_near_lake_blocks(
         last_indexed_delta_lake_block,
         lake_s3_config,
         lake_prefetch_size,
         redis_client,
         indexer,
+        compiled_rule,
         redis_stream,
+        block_sink,
         chain_id,
+        status,
+        back_pressure,
+        block_delivery,
     )
     .await?;
 
@@ -162,12 +497,20 @@ pub(crate) async fn start_block_stream(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn process_delta_lake_blocks(
     start_block_height: near_indexer_primitives::types::BlockHeight,
     delta_lake_client: std::sync::Arc<crate::delta_lake_client::DeltaLakeClient>,
     redis_client: std::sync::Arc<crate::redis::RedisClient>,
     indexer: &IndexerConfig,
+    compiled_rule: &CompiledRule,
     redis_stream: String,
+    block_sink: &dyn BlockSink,
+    chain_id: &ChainId,
+    backfill_progress: &BackfillProgress,
+    status: &StreamStatus,
+    back_pressure: &BackPressure,
+    block_delivery: &BlockDelivery,
 ) -> anyhow::Result<u64> {
     let latest_block_metadata = delta_lake_client.get_latest_block_metadata().await?;
     let last_indexed_block_from_metadata = latest_block_metadata
@@ -179,126 +522,518 @@ async fn process_delta_lake_blocks(
         return Ok(start_block_height);
     }
 
-    let blocks_from_index = match &indexer.rule {
-        Rule::ActionAny {
-            affected_account_id,
-            ..
-        } => {
-            tracing::debug!(
-                "Fetching block heights starting from {} from delta lake",
-                start_block_height,
-            );
-
-            delta_lake_client
-                .list_matching_block_heights(start_block_height, affected_account_id)
-                .await
-        }
-        Rule::ActionFunctionCall { .. } => {
-            tracing::error!("ActionFunctionCall matching rule not yet supported for delta lake processing, function: {:?} {:?}", indexer.account_id, indexer.function_name);
-            Ok(vec![])
-        }
-        Rule::Event { .. } => {
-            tracing::error!("Event matching rule not yet supported for delta lake processing, function {:?} {:?}", indexer.account_id, indexer.function_name);
-            Ok(vec![])
-        }
-    }?;
+    // Every block from the start must be scanned for rules which cannot be narrowed via the
+    // index, which NEAR Lake does without Delta Lake
+    let Some(index_queries) = build_index_queries(&indexer.rule) else {
+        tracing::warn!(
+            "Rule cannot be narrowed via the index, scanning NEAR Lake from the start block"
+        );
+        return Ok(start_block_height);
+    };
 
     tracing::debug!(
-        "Flushing {} block heights from index files to Redis Stream",
-        blocks_from_index.len(),
+        "Fetching block heights starting from {} from delta lake",
+        start_block_height,
     );
 
-    for block in &blocks_from_index {
-        let block = block.to_owned();
-        redis_client
-            .xadd(redis_stream.clone(), &[("block_height".to_string(), block)])
-            .await
-            .context("Failed to add block to Redis Stream")?;
-        redis_client
-            .set(
-                format!("{}:last_published_block", indexer.get_full_name()),
-                block,
+    let index_files =
+        list_index_files(&delta_lake_client, start_block_height, index_queries).await?;
+
+    backfill_progress
+        .index_files_total
+        .store(index_files.len() as u64, Ordering::Relaxed);
+
+    let mut block_height_batches =
+        delta_lake_client.stream_matching_block_heights(start_block_height, index_files);
+    let mut last_block_in_index = None;
+
+    while let Some(block_height_batch) = block_height_batches.try_next().await? {
+        // The index only narrows the candidate blocks, as it does not record the status of
+        // receipts or the role of the account, and fetching the block also provides the same entry
+        // fields as those published from NEAR Lake. Candidates are fetched ahead of verification,
+        // and yielded in order so that blocks are still published in order.
+        let mut streamer_messages = futures::stream::iter(&block_height_batch.block_heights)
+            .map(|block_height| {
+                let delta_lake_client = &delta_lake_client;
+
+                async move {
+                    delta_lake_client
+                        .get_streamer_message(*block_height)
+                        .await
+                        .context("Failed to fetch candidate block")
+                }
+            })
+            .buffered(MAX_PREFETCHED_CANDIDATE_BLOCKS);
+
+        while let Some(streamer_message) = streamer_messages.try_next().await? {
+            let block_height = streamer_message.block.header.height;
+
+            back_pressure.wait_for_capacity(block_sink).await?;
+
+            status.blocks_scanned.fetch_add(1, Ordering::Relaxed);
+            crate::metrics::BLOCKS_SCANNED
+                .with_label_values(&metric_labels(indexer))
+                .inc();
+
+            // Candidate blocks are not contiguous, so only receipts created within the same block
+            // can be resolved to their transaction
+            let mut receipt_resolver = ReceiptResolver::default();
+            receipt_resolver.track(&streamer_message);
+
+            let matches = crate::rules::reduce_indexer_rule_matches(
+                compiled_rule,
+                &streamer_message,
+                &receipt_resolver,
+                chain_id.clone(),
+            );
+
+            if matches.is_empty() {
+                continue;
+            }
+
+            let mut fields = build_stream_entry_fields(&streamer_message, &matches)?;
+            fields.extend(
+                block_delivery
+                    .deliver(&redis_client, &redis_stream, &streamer_message, &matches)
+                    .await?,
+            );
+
+            publish_block(
+                block_sink,
+                &redis_client,
+                indexer,
+                status,
+                block_height,
+                &fields,
             )
-            .await
-            .context("Failed to set last_published_block")?;
+            .await?;
+
+            backfill_progress
+                .blocks_published
+                .fetch_add(1, Ordering::Relaxed);
+        }
+
+        last_block_in_index = block_height_batch
+            .block_heights
+            .last()
+            .copied()
+            .or(last_block_in_index);
+
+        let index_files_processed = backfill_progress.index_files_processed.fetch_add(
+            block_height_batch.index_file_count as u64,
+            Ordering::Relaxed,
+        ) + block_height_batch.index_file_count as u64;
+        crate::metrics::DELTA_LAKE_INDEX_FILES_FETCHED
+            .with_label_values(&metric_labels(indexer))
+            .inc_by(block_height_batch.index_file_count as u64);
+
+        tracing::debug!(
+            date = %block_height_batch.date,
+            "Processed {} of {} index files from delta lake",
+            index_files_processed,
+            backfill_progress.index_files_total.load(Ordering::Relaxed),
+        );
     }
 
     let last_indexed_block =
-        blocks_from_index
-            .last()
-            .map_or(last_indexed_block_from_metadata, |&last_block_in_index| {
-                // Check for the case where index files are written right after we fetch the last_indexed_block metadata
-                std::cmp::max(last_block_in_index, last_indexed_block_from_metadata)
-            });
+        last_block_in_index.map_or(last_indexed_block_from_metadata, |last_block_in_index| {
+            // Check for the case where index files are written right after we fetch the last_indexed_block metadata
+            std::cmp::max(last_block_in_index, last_indexed_block_from_metadata)
+        });
 
     Ok(last_indexed_block)
 }
 
+/// Lists the index files of all queries from `start_block_height` onwards, ordered by date.
+pub(crate) async fn list_index_files(
+    delta_lake_client: &crate::delta_lake_client::DeltaLakeClient,
+    start_block_height: near_indexer_primitives::types::BlockHeight,
+    index_queries: Vec<IndexQuery>,
+) -> anyhow::Result<Vec<crate::delta_lake_client::IndexFileKey>> {
+    let mut index_files = vec![];
+    for index_query in index_queries {
+        index_files.extend(
+            delta_lake_client
+                .list_index_files(
+                    start_block_height,
+                    &index_query.contract_pattern,
+                    index_query.action_kinds,
+                )
+                .await?,
+        );
+    }
+    index_files.sort_by_key(|index_file| index_file.date);
+
+    Ok(index_files)
+}
+
+/// An account pattern to look up within the Delta Lake index, optionally narrowed to blocks
+/// containing specific kinds of actions.
+#[derive(Debug, PartialEq)]
+pub(crate) struct IndexQuery {
+    contract_pattern: String,
+    action_kinds: Option<Vec<String>>,
+}
+
+impl IndexQuery {
+    fn new(contract_pattern: &str, action_kinds: Option<Vec<&str>>) -> Self {
+        Self {
+            contract_pattern: contract_pattern.to_string(),
+            action_kinds: action_kinds.map(|action_kinds| {
+                action_kinds
+                    .into_iter()
+                    .map(|action_kind| action_kind.to_string())
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// Builds the index query for the accounts matching `contract_pattern`, or `None` when they cannot
+/// be listed from the index, i.e. for wildcards which are not beneath a sub-account.
+fn build_index_query(
+    contract_pattern: &str,
+    action_kinds: Option<Vec<&str>>,
+) -> Option<Vec<IndexQuery>> {
+    crate::delta_lake_client::can_list_index_files(contract_pattern)
+        .then(|| vec![IndexQuery::new(contract_pattern, action_kinds)])
+}
+
+/// Builds the index queries whose combined heights include every block the rule can match, or
+/// `None` when no such queries exist, i.e. for rules which match in the absence of an account's
+/// actions, or match accounts which cannot be listed from the index. Candidate blocks are expected
+/// to be verified against the rule.
+///
+/// The index does not record the signers of transactions, so rules matching signers cannot be
+/// narrowed, and neither can state change rules, as state also changes without any actions on the
+/// account, e.g. when validator rewards are distributed.
+pub(crate) fn build_index_queries(rule: &Rule) -> Option<Vec<IndexQuery>> {
+    match rule {
+        Rule::ActionAny {
+            affected_account_role: registry_types::AffectedAccountRole::Signer,
+            ..
+        }
+        | Rule::ActionFunctionCall {
+            affected_account_role: registry_types::AffectedAccountRole::Signer,
+            ..
+        }
+        | Rule::ActionKind {
+            affected_account_role: registry_types::AffectedAccountRole::Signer,
+            ..
+        }
+        | Rule::StateChange { .. } => None,
+        Rule::ActionAny {
+            affected_account_id,
+            ..
+        } => build_index_query(affected_account_id, None),
+        // Events can only be emitted while executing function calls
+        Rule::ActionFunctionCall {
+            affected_account_id: account_id,
+            ..
+        }
+        | Rule::Event {
+            contract_account_id: account_id,
+            ..
+        } => build_index_query(account_id, Some(vec!["FUNCTION_CALL"])),
+        Rule::ActionKind {
+            affected_account_id,
+            action_kinds,
+            ..
+        } => build_index_query(
+            affected_account_id,
+            Some(action_kinds.iter().map(index_action_kind).collect()),
+        ),
+        Rule::Any { rules } => rules
+            .iter()
+            .map(build_index_queries)
+            .collect::<Option<Vec<_>>>()
+            .map(|index_queries| index_queries.into_iter().flatten().collect()),
+        // Blocks matching every nested rule must match the first which can be narrowed
+        Rule::All { rules } => rules.iter().find_map(build_index_queries),
+        Rule::Not { .. } => None,
+    }
+}
+
+fn index_action_kind(action_kind: &registry_types::ActionKind) -> &'static str {
+    match action_kind {
+        registry_types::ActionKind::Transfer => "TRANSFER",
+        registry_types::ActionKind::AddKey => "ADD_KEY",
+        registry_types::ActionKind::DeleteKey => "DELETE_KEY",
+        registry_types::ActionKind::DeployContract => "DEPLOY_CONTRACT",
+        registry_types::ActionKind::Stake => "STAKE",
+        registry_types::ActionKind::CreateAccount => "CREATE_ACCOUNT",
+        registry_types::ActionKind::DeleteAccount => "DELETE_ACCOUNT",
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn process_near_lake_blocks(
     start_block_height: near_indexer_primitives::types::BlockHeight,
     lake_s3_config: aws_sdk_s3::Config,
     lake_prefetch_size: usize,
     redis_client: std::sync::Arc<crate::redis::RedisClient>,
     indexer: &IndexerConfig,
+    compiled_rule: &CompiledRule,
     redis_stream: String,
+    block_sink: &dyn BlockSink,
     chain_id: &ChainId,
+    status: &StreamStatus,
+    back_pressure: &BackPressure,
+    block_delivery: &BlockDelivery,
 ) -> anyhow::Result<u64> {
     tracing::debug!(start_block_height, "Starting near-lake-framework",);
 
+    let (mut receipt_resolver, lake_start_block_height) =
+        restore_receipt_resolver(&redis_client, indexer, start_block_height).await?;
+
     let lake_config = match &chain_id {
         ChainId::Mainnet => near_lake_framework::LakeConfigBuilder::default().mainnet(),
         ChainId::Testnet => near_lake_framework::LakeConfigBuilder::default().testnet(),
     }
-    .s3_config(lake_s3_config)
-    .start_block_height(start_block_height)
+    .s3_config(
+        lake_s3_config
+            .to_builder()
+            .interceptor(crate::s3_client::LakeS3RequestMetrics::new(
+                indexer.account_id.as_str(),
+                &indexer.function_name,
+            ))
+            .build(),
+    )
+    .start_block_height(lake_start_block_height)
     .blocks_preload_pool_size(lake_prefetch_size)
     .build()
     .context("Failed to build lake config")?;
 
     let mut last_indexed_block = start_block_height;
 
-    let (sender, mut stream) = near_lake_framework::streamer(lake_config);
+    let (lake_handle, mut stream) = near_lake_framework::streamer(lake_config);
 
     while let Some(streamer_message) = stream.recv().await {
         let block_height = streamer_message.block.header.height;
+
+        receipt_resolver.track(&streamer_message);
+
+        // Blocks since the persisted receipts are only streamed to track the receipts created
+        // within them
+        if block_height < start_block_height {
+            continue;
+        }
+
+        // Blocks are not received while waiting, so `near-lake-framework` stops prefetching once
+        // its buffer is full
+        back_pressure.wait_for_capacity(block_sink).await?;
+
         last_indexed_block = block_height;
 
-        redis_client
+        status.blocks_scanned.fetch_add(1, Ordering::Relaxed);
+        crate::metrics::BLOCKS_SCANNED
+            .with_label_values(&metric_labels(indexer))
+            .inc();
+        crate::metrics::LAKE_LAG_SECONDS
+            .with_label_values(&metric_labels(indexer))
             .set(
-                format!("{}:last_published_block", indexer.get_full_name()),
-                last_indexed_block,
-            )
-            .await
-            .context("Failed to set last_published_block")?;
+                chrono::Utc::now().timestamp()
+                    - (streamer_message.block.header.timestamp / 1_000_000_000) as i64,
+            );
 
         let matches = crate::rules::reduce_indexer_rule_matches(
-            &indexer.rule,
+            compiled_rule,
             &streamer_message,
+            &receipt_resolver,
             chain_id.clone(),
         );
 
-        if !matches.is_empty() {
-            redis_client
-                .xadd(
-                    redis_stream.clone(),
-                    &[("block_height".to_string(), block_height.to_owned())],
-                )
-                .await
-                .context("Failed to add block to Redis Stream")?;
+        if matches.is_empty() {
+            set_last_published_block(&redis_client, indexer, block_height).await?;
+        } else {
+            let mut fields = build_stream_entry_fields(&streamer_message, &matches)?;
+            fields.extend(
+                block_delivery
+                    .deliver(&redis_client, &redis_stream, &streamer_message, &matches)
+                    .await?,
+            );
+
+            publish_block(
+                block_sink,
+                &redis_client,
+                indexer,
+                status,
+                block_height,
+                &fields,
+            )
+            .await?;
+        }
+
+        if block_height % RECEIPT_RESOLVER_SNAPSHOT_INTERVAL_BLOCKS == 0 {
+            persist_receipt_resolver(&redis_client, indexer, &receipt_resolver).await?;
         }
     }
 
-    drop(sender);
+    // The stream only ends once the lake task has stopped, which it does not do without failing
+    lake_handle
+        .await
+        .context("NEAR Lake task panicked")?
+        .context("Failed to stream blocks from NEAR Lake")?;
 
     Ok(last_indexed_block)
 }
 
+/// Restores the receipts tracked before the block stream was last stopped, along with the block to
+/// start NEAR Lake from so that receipts created since are tracked before `start_block_height`.
+/// Receipts are tracked afresh from `start_block_height` when there is no usable snapshot.
+async fn restore_receipt_resolver(
+    redis_client: &crate::redis::RedisClient,
+    indexer: &IndexerConfig,
+    start_block_height: near_indexer_primitives::types::BlockHeight,
+) -> anyhow::Result<(ReceiptResolver, near_indexer_primitives::types::BlockHeight)> {
+    let snapshot: Option<String> = redis_client
+        .get(indexer.get_receipt_resolver_key())
+        .await
+        .context("Failed to get persisted receipts")?;
+
+    let receipt_resolver = snapshot.and_then(|snapshot| {
+        ReceiptResolver::from_snapshot(&snapshot)
+            .map_err(|err| tracing::warn!("Ignoring invalid persisted receipts: {:?}", err))
+            .ok()
+    });
+
+    if let Some(receipt_resolver) = receipt_resolver {
+        if let Some(last_tracked_block) = receipt_resolver.last_tracked_block() {
+            if last_tracked_block < start_block_height
+                && start_block_height - last_tracked_block <= MAX_RECEIPT_RESOLVER_REPLAY_BLOCKS
+            {
+                return Ok((receipt_resolver, last_tracked_block + 1));
+            }
+        }
+    }
+
+    Ok((ReceiptResolver::default(), start_block_height))
+}
+
+async fn persist_receipt_resolver(
+    redis_client: &crate::redis::RedisClient,
+    indexer: &IndexerConfig,
+    receipt_resolver: &ReceiptResolver,
+) -> anyhow::Result<()> {
+    let snapshot = receipt_resolver
+        .to_snapshot()
+        .context("Failed to serialize receipts")?;
+
+    redis_client
+        .set(indexer.get_receipt_resolver_key(), snapshot)
+        .await
+        .context("Failed to persist receipts")
+}
+
+/// Publishes a matching block, recording it as the last published block only once it has been
+/// published so that restarted streams resume without skipping it.
+async fn publish_block(
+    block_sink: &dyn BlockSink,
+    redis_client: &crate::redis::RedisClient,
+    indexer: &IndexerConfig,
+    status: &StreamStatus,
+    block_height: near_indexer_primitives::types::BlockHeight,
+    fields: &[(String, String)],
+) -> anyhow::Result<()> {
+    let _publishing = status.publishing.lock().await;
+
+    block_sink.publish(block_height, fields).await?;
+
+    if !block_sink.records_last_published_block() {
+        set_last_published_block(redis_client, indexer, block_height).await?;
+    }
+
+    status.record_published(block_height);
+    crate::metrics::LAST_PUBLISHED_BLOCK
+        .with_label_values(&metric_labels(indexer))
+        .set(block_height as i64);
+    crate::metrics::MATCHES_PUBLISHED
+        .with_label_values(&metric_labels(indexer))
+        .inc();
+
+    Ok(())
+}
+
+fn metric_labels(indexer: &IndexerConfig) -> [&str; 2] {
+    [indexer.account_id.as_str(), &indexer.function_name]
+}
+
+/// Blocks after the persisted last published block are either unmatched or unpublished, so
+/// resuming from there neither skips nor duplicates entries. Unlike `StreamStatus`, the persisted
+/// block also advances past unmatched blocks streamed from NEAR Lake, so they are not re-scanned.
+async fn get_restart_block_height(
+    redis_client: &crate::redis::RedisClient,
+    indexer: &IndexerConfig,
+    start_block_height: near_indexer_primitives::types::BlockHeight,
+) -> anyhow::Result<near_indexer_primitives::types::BlockHeight> {
+    let last_published_block: Option<near_indexer_primitives::types::BlockHeight> = redis_client
+        .get(indexer.get_last_published_block_key())
+        .await
+        .context("Failed to get last_published_block")?;
+
+    Ok(
+        last_published_block.map_or(start_block_height, |last_published_block| {
+            start_block_height.max(last_published_block + 1)
+        }),
+    )
+}
+
+async fn set_last_published_block(
+    redis_client: &crate::redis::RedisClient,
+    indexer: &IndexerConfig,
+    block_height: near_indexer_primitives::types::BlockHeight,
+) -> anyhow::Result<()> {
+    redis_client
+        .set(indexer.get_last_published_block_key(), block_height)
+        .await
+        .context("Failed to set last_published_block")
+}
+
+/// Builds the Redis Stream entry for a matching block, including the matched receipts and their
+/// payloads so that executors do not need to re-scan the entire block.
+fn build_stream_entry_fields(
+    streamer_message: &near_indexer_primitives::StreamerMessage,
+    matches: &[IndexerRuleMatch],
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut receipt_ids: Vec<&str> = matches
+        .iter()
+        .filter_map(|indexer_rule_match| indexer_rule_match.payload.receipt_id())
+        .map(String::as_str)
+        .collect();
+    // Payloads for the same receipt are always adjacent
+    receipt_ids.dedup();
+
+    let payloads: Vec<&IndexerRuleMatchPayload> = matches
+        .iter()
+        .map(|indexer_rule_match| &indexer_rule_match.payload)
+        .collect();
+
+    let header = &streamer_message.block.header;
+
+    Ok(vec![
+        ("block_height".to_string(), header.height.to_string()),
+        ("block_hash".to_string(), header.hash.to_string()),
+        ("block_timestamp".to_string(), header.timestamp.to_string()),
+        (
+            "receipt_ids".to_string(),
+            serde_json::to_string(&receipt_ids).context("Failed to serialize receipt IDs")?,
+        ),
+        (
+            "matches".to_string(),
+            serde_json::to_string(&payloads).context("Failed to serialize matches")?,
+        ),
+    ])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use futures::StreamExt;
     use mockall::predicate;
 
+    use crate::block_sink::RedisStreamSink;
+
     #[tokio::test]
     async fn adds_matching_blocks_from_index_and_lake() {
         let mut mock_delta_lake_client = crate::delta_lake_client::DeltaLakeClient::default();
@@ -314,22 +1049,57 @@ mod tests {
                 })
             });
         mock_delta_lake_client
-            .expect_list_matching_block_heights()
-            .returning(|_, _| Ok(vec![107503702, 107503703]));
+            .expect_list_index_files()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_delta_lake_client
+            .expect_stream_matching_block_heights()
+            .returning(|_, _| {
+                futures::stream::iter(vec![Ok(crate::delta_lake_client::BlockHeightBatch {
+                    date: chrono::NaiveDate::from_ymd_opt(2023, 5, 16).unwrap(),
+                    index_file_count: 1,
+                    block_heights: vec![93085141],
+                })])
+                .boxed()
+            });
+        mock_delta_lake_client
+            .expect_get_streamer_message()
+            .with(predicate::eq(93085141))
+            .returning(|block_height| Ok(crate::test_utils::get_streamer_message(block_height)))
+            .times(1);
 
         let mut mock_redis_client = crate::redis::RedisClient::default();
         mock_redis_client
-            .expect_xadd::<String, u64>()
-            .with(predicate::eq("stream key".to_string()), predicate::always())
-            .returning(|_, fields| {
-                assert!(vec![107503702, 107503703, 107503705].contains(&fields[0].1));
-                Ok(())
-            })
-            .times(3);
+            .expect_get::<String, String>()
+            .returning(|_| Ok(None));
         mock_redis_client
-            .expect_set::<String, u64>()
-            .returning(|_, _| Ok(()))
-            .times(4);
+            .expect_xlen::<String>()
+            .returning(|_| Ok(0));
+        // Entries published from the index and from NEAR Lake contain the same fields
+        mock_redis_client
+            .expect_xadd_block()
+            .withf(|stream_key, last_published_block_key, _, _| {
+                stream_key == "stream key"
+                    && last_published_block_key == "morgs.near/test:last_published_block"
+            })
+            .returning(|_, _, block_height, fields| {
+                assert!([93085141, 107503704, 107503705].contains(&block_height));
+                assert_eq!(
+                    fields
+                        .iter()
+                        .map(|(field, _)| field.as_str())
+                        .collect::<Vec<_>>(),
+                    [
+                        "block_height",
+                        "block_hash",
+                        "block_timestamp",
+                        "receipt_ids",
+                        "matches"
+                    ]
+                );
+                assert_eq!(fields[0].1, block_height.to_string());
+                Ok(true)
+            })
+            .times(3);
 
         let indexer_config = crate::indexer_config::IndexerConfig {
             account_id: near_indexer_primitives::types::AccountId::try_from(
@@ -338,24 +1108,758 @@ mod tests {
             .unwrap(),
             function_name: "test".to_string(),
             rule: registry_types::Rule::ActionAny {
-                affected_account_id: "queryapi.dataplatform.near".to_string(),
-                status: registry_types::Status::Success,
+                affected_account_id: "token.sweat".to_string(),
+                status: registry_types::Status::Any,
+                affected_account_role: registry_types::AffectedAccountRole::Any,
             },
         };
 
         let lake_s3_config = crate::test_utils::create_mock_lake_s3_config(&[107503704, 107503705]);
 
+        let redis_client = std::sync::Arc::new(mock_redis_client);
+
+        start_block_stream(
+            93085000,
+            &indexer_config,
+            &CompiledRule::from(&indexer_config.rule),
+            redis_client.clone(),
+            std::sync::Arc::new(mock_delta_lake_client),
+            lake_s3_config,
+            &ChainId::Mainnet,
+            1,
+            "stream key".to_string(),
+            &RedisStreamSink::new(
+                redis_client,
+                "stream key".to_string(),
+                "morgs.near/test:last_published_block".to_string(),
+            ),
+            &BackfillProgress::default(),
+            &StreamStatus::default(),
+            &BackPressure::new(RedisStreamWatermarks::default()),
+            &BlockDelivery::default(),
+        )
+        .await
+        // The mock lake ends with an invalid block, failing the stream once the blocks before it
+        // have been published
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn publishes_verified_candidate_blocks_from_index() {
+        let mut mock_delta_lake_client = crate::delta_lake_client::DeltaLakeClient::default();
+        mock_delta_lake_client
+            .expect_get_latest_block_metadata()
+            .returning(|| {
+                Ok(crate::delta_lake_client::LatestBlockMetadata {
+                    last_indexed_block: "107503703".to_string(),
+                    processed_at_utc: "".to_string(),
+                    first_indexed_block: "".to_string(),
+                    last_indexed_block_date: "".to_string(),
+                    first_indexed_block_date: "".to_string(),
+                })
+            });
+        mock_delta_lake_client
+            .expect_list_index_files()
+            .with(
+                predicate::eq(93085000),
+                predicate::eq("app.nearcrowd.near"),
+                predicate::eq(Some(vec!["FUNCTION_CALL".to_string()])),
+            )
+            .returning(|_, _, _| Ok(vec![]));
+        mock_delta_lake_client
+            .expect_stream_matching_block_heights()
+            .returning(|_, _| {
+                futures::stream::iter(vec![
+                    Ok(crate::delta_lake_client::BlockHeightBatch {
+                        date: chrono::NaiveDate::from_ymd_opt(2023, 5, 16).unwrap(),
+                        index_file_count: 1,
+                        block_heights: vec![93085141],
+                    }),
+                    Ok(crate::delta_lake_client::BlockHeightBatch {
+                        date: chrono::NaiveDate::from_ymd_opt(2023, 12, 9).unwrap(),
+                        index_file_count: 1,
+                        block_heights: vec![107503705],
+                    }),
+                ])
+                .boxed()
+            });
+        mock_delta_lake_client
+            .expect_get_streamer_message()
+            .returning(|block_height| Ok(crate::test_utils::get_streamer_message(block_height)));
+
+        let mut mock_redis_client = crate::redis::RedisClient::default();
+        mock_redis_client
+            .expect_get::<String, String>()
+            .returning(|_| Ok(None));
+        mock_redis_client
+            .expect_xlen::<String>()
+            .returning(|_| Ok(0));
+        mock_redis_client
+            .expect_xadd_block()
+            .with(
+                predicate::eq("stream key".to_string()),
+                predicate::eq("morgs.near/test:last_published_block".to_string()),
+                predicate::eq(93085141),
+                predicate::always(),
+            )
+            .returning(|_, _, _, fields| {
+                assert_eq!(
+                    fields[0],
+                    ("block_height".to_string(), "93085141".to_string())
+                );
+                Ok(true)
+            })
+            .times(1);
+        mock_redis_client
+            .expect_set::<String, u64>()
+            .returning(|_, _| Ok(()));
+
+        let indexer_config = crate::indexer_config::IndexerConfig {
+            account_id: near_indexer_primitives::types::AccountId::try_from(
+                "morgs.near".to_string(),
+            )
+            .unwrap(),
+            function_name: "test".to_string(),
+            rule: registry_types::Rule::ActionFunctionCall {
+                affected_account_id: "app.nearcrowd.near".to_string(),
+                status: registry_types::Status::Any,
+                affected_account_role: registry_types::AffectedAccountRole::Any,
+                function: "approve_solution".to_string(),
+                args_predicates: vec![],
+                min_deposit: None,
+                min_gas: None,
+            },
+        };
+
+        let lake_s3_config = crate::test_utils::create_mock_lake_s3_config(&[107503704]);
+        let backfill_progress = BackfillProgress::default();
+        let status = StreamStatus::default();
+
+        let redis_client = std::sync::Arc::new(mock_redis_client);
+
         start_block_stream(
-            91940840,
+            93085000,
             &indexer_config,
-            std::sync::Arc::new(mock_redis_client),
+            &CompiledRule::from(&indexer_config.rule),
+            redis_client.clone(),
             std::sync::Arc::new(mock_delta_lake_client),
             lake_s3_config,
             &ChainId::Mainnet,
             1,
             "stream key".to_string(),
+            &RedisStreamSink::new(
+                redis_client,
+                "stream key".to_string(),
+                "morgs.near/test:last_published_block".to_string(),
+            ),
+            &backfill_progress,
+            &status,
+            &BackPressure::new(RedisStreamWatermarks::default()),
+            &BlockDelivery::default(),
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(
+            backfill_progress
+                .index_files_processed
+                .load(Ordering::Relaxed),
+            2
+        );
+        assert_eq!(
+            backfill_progress.blocks_published.load(Ordering::Relaxed),
+            1
+        );
+        assert_eq!(status.state(), StreamState::StreamingFromLake);
+        assert_eq!(status.matches_published.load(Ordering::Relaxed), 1);
+        assert_eq!(
+            status.last_published_block.load(Ordering::Relaxed),
+            93085141
+        );
+    }
+
+    #[tokio::test]
+    async fn scans_from_start_block_when_rule_cannot_be_narrowed_via_index() {
+        let mut mock_delta_lake_client = crate::delta_lake_client::DeltaLakeClient::default();
+        mock_delta_lake_client
+            .expect_get_latest_block_metadata()
+            .returning(|| {
+                Ok(crate::delta_lake_client::LatestBlockMetadata {
+                    last_indexed_block: "107503703".to_string(),
+                    processed_at_utc: "".to_string(),
+                    first_indexed_block: "".to_string(),
+                    last_indexed_block_date: "".to_string(),
+                    first_indexed_block_date: "".to_string(),
+                })
+            });
+        mock_delta_lake_client.expect_list_index_files().never();
+
+        let indexer_config = crate::indexer_config::IndexerConfig {
+            account_id: near_indexer_primitives::types::AccountId::try_from(
+                "morgs.near".to_string(),
+            )
+            .unwrap(),
+            function_name: "test".to_string(),
+            rule: registry_types::Rule::Not {
+                rule: Box::new(registry_types::Rule::ActionAny {
+                    affected_account_id: "token.sweat".to_string(),
+                    status: registry_types::Status::Any,
+                    affected_account_role: registry_types::AffectedAccountRole::Any,
+                }),
+            },
+        };
+
+        let redis_client = std::sync::Arc::new(crate::redis::RedisClient::default());
+
+        let last_indexed_block = process_delta_lake_blocks(
+            93085000,
+            std::sync::Arc::new(mock_delta_lake_client),
+            redis_client.clone(),
+            &indexer_config,
+            &CompiledRule::from(&indexer_config.rule),
+            "stream key".to_string(),
+            &RedisStreamSink::new(
+                redis_client,
+                "stream key".to_string(),
+                "morgs.near/test:last_published_block".to_string(),
+            ),
+            &ChainId::Mainnet,
+            &BackfillProgress::default(),
+            &StreamStatus::default(),
+            &BackPressure::new(RedisStreamWatermarks::default()),
+            &BlockDelivery::default(),
         )
         .await
         .unwrap();
+
+        assert_eq!(last_indexed_block, 93085000);
+    }
+
+    #[tokio::test]
+    async fn fails_when_near_lake_fails() {
+        let mut mock_delta_lake_client = crate::delta_lake_client::DeltaLakeClient::default();
+        mock_delta_lake_client
+            .expect_get_latest_block_metadata()
+            .returning(|| {
+                Ok(crate::delta_lake_client::LatestBlockMetadata {
+                    last_indexed_block: "107503703".to_string(),
+                    processed_at_utc: "".to_string(),
+                    first_indexed_block: "".to_string(),
+                    last_indexed_block_date: "".to_string(),
+                    first_indexed_block_date: "".to_string(),
+                })
+            });
+
+        let mut mock_redis_client = crate::redis::RedisClient::default();
+        mock_redis_client
+            .expect_get::<String, String>()
+            .returning(|_| Ok(None));
+        mock_redis_client.expect_xadd_block().never();
+
+        let indexer_config = crate::indexer_config::IndexerConfig {
+            account_id: near_indexer_primitives::types::AccountId::try_from(
+                "morgs.near".to_string(),
+            )
+            .unwrap(),
+            function_name: "test".to_string(),
+            rule: registry_types::Rule::ActionAny {
+                affected_account_id: "queryapi.dataplatform.near".to_string(),
+                status: registry_types::Status::Success,
+                affected_account_role: registry_types::AffectedAccountRole::Any,
+            },
+        };
+
+        let redis_client = std::sync::Arc::new(mock_redis_client);
+
+        let result = start_block_stream(
+            107503704,
+            &indexer_config,
+            &CompiledRule::from(&indexer_config.rule),
+            redis_client.clone(),
+            std::sync::Arc::new(mock_delta_lake_client),
+            crate::test_utils::create_failing_mock_lake_s3_config(107503704),
+            &ChainId::Mainnet,
+            1,
+            "stream key".to_string(),
+            &RedisStreamSink::new(
+                redis_client,
+                "stream key".to_string(),
+                "morgs.near/test:last_published_block".to_string(),
+            ),
+            &BackfillProgress::default(),
+            &StreamStatus::default(),
+            &BackPressure::new(RedisStreamWatermarks::default()),
+            &BlockDelivery::default(),
+        )
+        .await;
+
+        assert!(
+            format!("{:?}", result.unwrap_err()).contains("Failed to stream blocks from NEAR Lake")
+        );
+    }
+
+    #[tokio::test]
+    async fn resolves_receipts_created_before_restart() {
+        let mut mock_delta_lake_client = crate::delta_lake_client::DeltaLakeClient::default();
+        mock_delta_lake_client
+            .expect_get_latest_block_metadata()
+            .returning(|| {
+                Ok(crate::delta_lake_client::LatestBlockMetadata {
+                    last_indexed_block: "107503703".to_string(),
+                    processed_at_utc: "".to_string(),
+                    first_indexed_block: "".to_string(),
+                    last_indexed_block_date: "".to_string(),
+                    first_indexed_block_date: "".to_string(),
+                })
+            });
+
+        // The receipt matched in 107503705 was created in 107503704, before the restart
+        let mut receipt_resolver = ReceiptResolver::default();
+        receipt_resolver.track(&crate::test_utils::get_streamer_message(107503704));
+        let snapshot = receipt_resolver.to_snapshot().unwrap();
+
+        let mut mock_redis_client = crate::redis::RedisClient::default();
+        mock_redis_client
+            .expect_get::<String, String>()
+            .with(predicate::eq(
+                "morgs.near/test:receipt_resolver".to_string(),
+            ))
+            .returning(move |_| Ok(Some(snapshot.clone())));
+        mock_redis_client
+            .expect_xlen::<String>()
+            .returning(|_| Ok(0));
+        mock_redis_client
+            .expect_xadd_block()
+            .withf(|_, _, block_height, _| *block_height == 107503705)
+            .returning(|_, _, _, fields| {
+                assert!(fields[4]
+                    .1
+                    .contains("BpoRax4dyMgtS2yfNg8go71mnR72baEx2GxbGood7wgH"));
+                Ok(true)
+            })
+            .times(1);
+
+        let indexer_config = crate::indexer_config::IndexerConfig {
+            account_id: near_indexer_primitives::types::AccountId::try_from(
+                "morgs.near".to_string(),
+            )
+            .unwrap(),
+            function_name: "test".to_string(),
+            rule: registry_types::Rule::ActionAny {
+                affected_account_id: "queryapi.dataplatform.near".to_string(),
+                status: registry_types::Status::Success,
+                affected_account_role: registry_types::AffectedAccountRole::Any,
+            },
+        };
+
+        let redis_client = std::sync::Arc::new(mock_redis_client);
+
+        start_block_stream(
+            107503705,
+            &indexer_config,
+            &CompiledRule::from(&indexer_config.rule),
+            redis_client.clone(),
+            std::sync::Arc::new(mock_delta_lake_client),
+            crate::test_utils::create_mock_lake_s3_config(&[107503705]),
+            &ChainId::Mainnet,
+            1,
+            "stream key".to_string(),
+            &RedisStreamSink::new(
+                redis_client,
+                "stream key".to_string(),
+                "morgs.near/test:last_published_block".to_string(),
+            ),
+            &BackfillProgress::default(),
+            &StreamStatus::default(),
+            &BackPressure::new(RedisStreamWatermarks::default()),
+            &BlockDelivery::default(),
+        )
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn records_error_of_failed_block_stream() {
+        let mut mock_delta_lake_client = crate::delta_lake_client::DeltaLakeClient::default();
+        mock_delta_lake_client
+            .expect_get_latest_block_metadata()
+            .returning(|| Err(anyhow::anyhow!("Delta Lake unavailable")));
+
+        let redis_client = std::sync::Arc::new(crate::redis::RedisClient::default());
+
+        let indexer_config = crate::indexer_config::IndexerConfig {
+            account_id: near_indexer_primitives::types::AccountId::try_from(
+                "morgs.near".to_string(),
+            )
+            .unwrap(),
+            function_name: "test".to_string(),
+            rule: registry_types::Rule::ActionAny {
+                affected_account_id: "queryapi.dataplatform.near".to_string(),
+                status: registry_types::Status::Success,
+                affected_account_role: registry_types::AffectedAccountRole::Any,
+            },
+        };
+
+        let mut block_stream = BlockStream::new(
+            indexer_config,
+            ChainId::Mainnet,
+            1,
+            "stream key".to_string(),
+            RedisStreamWatermarks::default(),
+            BlockDelivery::default(),
+        );
+        block_stream.restart_policy = RestartPolicy {
+            max_restarts: 0,
+            ..RestartPolicy::default()
+        };
+
+        block_stream
+            .start(
+                0,
+                redis_client.clone(),
+                std::sync::Arc::new(mock_delta_lake_client),
+                crate::test_utils::create_mock_lake_s3_config(&[]),
+                std::sync::Arc::new(RedisStreamSink::new(
+                    redis_client,
+                    "stream key".to_string(),
+                    "morgs.near/test:last_published_block".to_string(),
+                )),
+            )
+            .unwrap();
+
+        let result = block_stream.task.take().unwrap().handle.await.unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(block_stream.status.state(), StreamState::Failed);
+        assert!(block_stream
+            .status
+            .last_error()
+            .unwrap()
+            .message
+            .contains("Delta Lake unavailable"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn restarts_failed_block_stream_until_limit() {
+        let mut mock_delta_lake_client = crate::delta_lake_client::DeltaLakeClient::default();
+        mock_delta_lake_client
+            .expect_get_latest_block_metadata()
+            .returning(|| Err(anyhow::anyhow!("Delta Lake unavailable")))
+            .times(3);
+
+        let mut mock_redis_client = crate::redis::RedisClient::default();
+        mock_redis_client
+            .expect_get::<String, u64>()
+            .returning(|_| Ok(None))
+            .times(2);
+        let redis_client = std::sync::Arc::new(mock_redis_client);
+
+        let indexer_config = crate::indexer_config::IndexerConfig {
+            account_id: near_indexer_primitives::types::AccountId::try_from(
+                "morgs.near".to_string(),
+            )
+            .unwrap(),
+            function_name: "test".to_string(),
+            rule: registry_types::Rule::ActionAny {
+                affected_account_id: "queryapi.dataplatform.near".to_string(),
+                status: registry_types::Status::Success,
+                affected_account_role: registry_types::AffectedAccountRole::Any,
+            },
+        };
+
+        let mut block_stream = BlockStream::new(
+            indexer_config,
+            ChainId::Mainnet,
+            1,
+            "stream key".to_string(),
+            RedisStreamWatermarks::default(),
+            BlockDelivery::default(),
+        );
+        block_stream.restart_policy = RestartPolicy {
+            max_restarts: 2,
+            ..RestartPolicy::default()
+        };
+
+        block_stream
+            .start(
+                0,
+                redis_client.clone(),
+                std::sync::Arc::new(mock_delta_lake_client),
+                crate::test_utils::create_mock_lake_s3_config(&[]),
+                std::sync::Arc::new(RedisStreamSink::new(
+                    redis_client,
+                    "stream key".to_string(),
+                    "morgs.near/test:last_published_block".to_string(),
+                )),
+            )
+            .unwrap();
+
+        let result = block_stream.task.take().unwrap().handle.await.unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(block_stream.status.state(), StreamState::Failed);
+        assert_eq!(block_stream.status.restarts.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn restarts_from_persisted_last_published_block() {
+        let attempts = std::sync::atomic::AtomicUsize::new(0);
+
+        let mut mock_delta_lake_client = crate::delta_lake_client::DeltaLakeClient::default();
+        mock_delta_lake_client
+            .expect_get_latest_block_metadata()
+            .returning(move || {
+                if attempts.fetch_add(1, Ordering::Relaxed) == 0 {
+                    return Err(anyhow::anyhow!("Delta Lake unavailable"));
+                }
+
+                Ok(crate::delta_lake_client::LatestBlockMetadata {
+                    last_indexed_block: "107503703".to_string(),
+                    processed_at_utc: "".to_string(),
+                    first_indexed_block: "".to_string(),
+                    last_indexed_block_date: "".to_string(),
+                    first_indexed_block_date: "".to_string(),
+                })
+            })
+            .times(2);
+        mock_delta_lake_client
+            .expect_list_index_files()
+            .with(
+                predicate::eq(93085141),
+                predicate::always(),
+                predicate::always(),
+            )
+            .returning(|_, _, _| Err(anyhow::anyhow!("Delta Lake unavailable")))
+            .once();
+
+        // Unmatched blocks streamed from NEAR Lake are persisted as published, but are not
+        // reflected in the in-memory status
+        let mut mock_redis_client = crate::redis::RedisClient::default();
+        mock_redis_client
+            .expect_get::<String, u64>()
+            .with(predicate::eq(
+                "morgs.near/test:last_published_block".to_string(),
+            ))
+            .returning(|_| Ok(Some(93085140)))
+            .once();
+        let redis_client = std::sync::Arc::new(mock_redis_client);
+
+        let indexer_config = crate::indexer_config::IndexerConfig {
+            account_id: near_indexer_primitives::types::AccountId::try_from(
+                "morgs.near".to_string(),
+            )
+            .unwrap(),
+            function_name: "test".to_string(),
+            rule: registry_types::Rule::ActionAny {
+                affected_account_id: "queryapi.dataplatform.near".to_string(),
+                status: registry_types::Status::Success,
+                affected_account_role: registry_types::AffectedAccountRole::Any,
+            },
+        };
+
+        let mut block_stream = BlockStream::new(
+            indexer_config,
+            ChainId::Mainnet,
+            1,
+            "stream key".to_string(),
+            RedisStreamWatermarks::default(),
+            BlockDelivery::default(),
+        );
+        block_stream.restart_policy = RestartPolicy {
+            max_restarts: 1,
+            ..RestartPolicy::default()
+        };
+
+        block_stream
+            .start(
+                93085000,
+                redis_client.clone(),
+                std::sync::Arc::new(mock_delta_lake_client),
+                crate::test_utils::create_mock_lake_s3_config(&[]),
+                std::sync::Arc::new(RedisStreamSink::new(
+                    redis_client,
+                    "stream key".to_string(),
+                    "morgs.near/test:last_published_block".to_string(),
+                )),
+            )
+            .unwrap();
+
+        let result = block_stream.task.take().unwrap().handle.await.unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(block_stream.status.restarts.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backs_off_exponentially_between_restarts() {
+        let mut supervisor = Supervisor::new(RestartPolicy {
+            initial_backoff: std::time::Duration::from_secs(1),
+            max_backoff: std::time::Duration::from_secs(3),
+            max_restarts: 3,
+            window: std::time::Duration::from_secs(60),
+        });
+
+        assert_eq!(
+            supervisor.next_backoff(),
+            Some(std::time::Duration::from_secs(1))
+        );
+        assert_eq!(
+            supervisor.next_backoff(),
+            Some(std::time::Duration::from_secs(2))
+        );
+        assert_eq!(
+            supervisor.next_backoff(),
+            Some(std::time::Duration::from_secs(3))
+        );
+        assert_eq!(supervisor.next_backoff(), None);
+
+        tokio::time::advance(std::time::Duration::from_secs(61)).await;
+
+        assert_eq!(
+            supervisor.next_backoff(),
+            Some(std::time::Duration::from_secs(1))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_redis_stream_to_drain_below_low_watermark() {
+        let stream_lengths = std::sync::Mutex::new(vec![10, 8, 5]);
+
+        let mut mock_redis_client = crate::redis::RedisClient::default();
+        mock_redis_client
+            .expect_xlen::<String>()
+            .with(predicate::eq("stream key".to_string()))
+            .returning(move |_| Ok(stream_lengths.lock().unwrap().remove(0)))
+            .times(3);
+
+        let back_pressure = BackPressure::new(RedisStreamWatermarks { high: 10, low: 5 });
+
+        back_pressure
+            .wait_for_capacity(&RedisStreamSink::new(
+                std::sync::Arc::new(mock_redis_client),
+                "stream key".to_string(),
+                "morgs.near/test:last_published_block".to_string(),
+            ))
+            .await
+            .unwrap();
+
+        assert!(!back_pressure.is_paused());
+    }
+
+    #[test]
+    fn does_not_narrow_unbounded_wildcards_via_index() {
+        for affected_account_id in ["*", "*.near", "?pp.*", "app.nea*owd.near"] {
+            assert_eq!(
+                build_index_queries(&registry_types::Rule::ActionAny {
+                    affected_account_id: affected_account_id.to_string(),
+                    status: registry_types::Status::Any,
+                    affected_account_role: registry_types::AffectedAccountRole::Any,
+                }),
+                None
+            );
+        }
+
+        assert_eq!(
+            build_index_queries(&registry_types::Rule::ActionAny {
+                affected_account_id: "token.sweat, *.near".to_string(),
+                status: registry_types::Status::Any,
+                affected_account_role: registry_types::AffectedAccountRole::Any,
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn does_not_narrow_signer_or_state_change_rules_via_index() {
+        assert_eq!(
+            build_index_queries(&registry_types::Rule::ActionAny {
+                affected_account_id: "token.sweat".to_string(),
+                status: registry_types::Status::Any,
+                affected_account_role: registry_types::AffectedAccountRole::Signer,
+            }),
+            None
+        );
+        assert_eq!(
+            build_index_queries(&registry_types::Rule::ActionFunctionCall {
+                affected_account_id: "app.nearcrowd.near".to_string(),
+                status: registry_types::Status::Any,
+                affected_account_role: registry_types::AffectedAccountRole::Signer,
+                function: "approve_solution".to_string(),
+                args_predicates: vec![],
+                min_deposit: None,
+                min_gas: None,
+            }),
+            None
+        );
+        assert_eq!(
+            build_index_queries(&registry_types::Rule::StateChange {
+                affected_account_id: "token.sweat".to_string(),
+                change_kinds: vec![],
+            }),
+            None
+        );
+
+        assert_eq!(
+            build_index_queries(&registry_types::Rule::ActionAny {
+                affected_account_id: "token.sweat".to_string(),
+                status: registry_types::Status::Any,
+                affected_account_role: registry_types::AffectedAccountRole::Receiver,
+            }),
+            Some(vec![IndexQuery::new("token.sweat", None)])
+        );
+    }
+
+    #[test]
+    fn narrows_composite_rules_via_index() {
+        let action_kind_rule = registry_types::Rule::ActionKind {
+            affected_account_id: "*.users.kaiching".to_string(),
+            status: registry_types::Status::Any,
+            affected_account_role: registry_types::AffectedAccountRole::Any,
+            action_kinds: vec![registry_types::ActionKind::CreateAccount],
+            min_amount: None,
+            max_amount: None,
+        };
+        let event_rule = registry_types::Rule::Event {
+            contract_account_id: "token.sweat".to_string(),
+            standard: "nep141".to_string(),
+            version: "*".to_string(),
+            event: "ft_transfer".to_string(),
+            data_predicate: None,
+        };
+
+        assert_eq!(
+            build_index_queries(&registry_types::Rule::Any {
+                rules: vec![action_kind_rule.clone(), event_rule.clone()]
+            }),
+            Some(vec![
+                IndexQuery::new("*.users.kaiching", Some(vec!["CREATE_ACCOUNT"])),
+                IndexQuery::new("token.sweat", Some(vec!["FUNCTION_CALL"])),
+            ])
+        );
+        assert_eq!(
+            build_index_queries(&registry_types::Rule::All {
+                rules: vec![
+                    registry_types::Rule::Not {
+                        rule: Box::new(action_kind_rule.clone())
+                    },
+                    event_rule.clone()
+                ]
+            }),
+            Some(vec![IndexQuery::new(
+                "token.sweat",
+                Some(vec!["FUNCTION_CALL"])
+            )])
+        );
+        assert_eq!(
+            build_index_queries(&registry_types::Rule::Any {
+                rules: vec![
+                    registry_types::Rule::Not {
+                        rule: Box::new(action_kind_rule)
+                    },
+                    event_rule
+                ]
+            }),
+            None
+        );
     }
 }
@@ -1,12 +1,21 @@
 use anyhow::Context;
 use chrono::TimeZone;
 use futures::future::try_join_all;
+use futures::{StreamExt, TryStreamExt};
 use near_lake_framework::near_indexer_primitives;
 
+use crate::rules::compiled_rule::{is_wildcard, Pattern};
 use crate::rules::types::ChainId;
 
-const DELTA_LAKE_BUCKET: &str = "near-delta-lake";
+const MAINNET_DELTA_LAKE_BUCKET: &str = "near-delta-lake";
+const TESTNET_DELTA_LAKE_BUCKET: &str = "near-delta-lake-testnet";
 const MAX_S3_RETRY_COUNT: u8 = 20;
+/// The number of dates to fetch index files for ahead of the date being published
+const MAX_PREFETCHED_INDEX_DATES: usize = 4;
+/// The number of index files to fetch concurrently for a single date, i.e. across contracts
+const MAX_CONCURRENT_INDEX_FILE_FETCHES: usize = 10;
+/// The number of index folders to list concurrently while walking the account hierarchy
+const MAX_CONCURRENT_INDEX_FOLDER_LISTS: usize = 10;
 const INDEXED_ACTIONS_PREFIX: &str = "silver/accounts/action_receipt_actions/metadata";
 const LATEST_BLOCK_METADATA_KEY: &str =
     "silver/accounts/action_receipt_actions/metadata/latest_block.json";
@@ -37,31 +46,76 @@ pub struct IndexFile {
     pub actions: Vec<IndexFileAction>,
 }
 
+/// An index file to read heights from, optionally only those of blocks containing the given
+/// action kinds
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IndexFileKey {
+    pub key: String,
+    pub date: chrono::NaiveDate,
+    pub action_kinds: Option<Vec<String>>,
+}
+
+impl IndexFileKey {
+    fn block_heights(&self, content: &str) -> Vec<near_indexer_primitives::types::BlockHeight> {
+        if content.is_empty() {
+            return vec![];
+        }
+
+        let Ok(index_file) = serde_json::from_str::<IndexFile>(content) else {
+            return vec![];
+        };
+
+        match &self.action_kinds {
+            Some(action_kinds) => index_file
+                .actions
+                .into_iter()
+                .filter(|action| action_kinds.contains(&action.action_kind))
+                .flat_map(|action| action.block_heights)
+                .collect(),
+            None => index_file.heights,
+        }
+    }
+}
+
+/// The heights within all index files of a single date, sorted and deduplicated
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlockHeightBatch {
+    pub date: chrono::NaiveDate,
+    pub index_file_count: usize,
+    pub block_heights: Vec<near_indexer_primitives::types::BlockHeight>,
+}
+
 pub struct DeltaLakeClientImpl {
-    s3_client: crate::s3_client::S3Client,
+    s3_client: std::sync::Arc<crate::s3_client::S3Client>,
     chain_id: ChainId,
 }
 
 #[cfg_attr(test, mockall::automock)]
 impl DeltaLakeClientImpl {
-    pub fn new(s3_client: crate::s3_client::S3Client) -> Self {
+    pub fn new(s3_client: crate::s3_client::S3Client, chain_id: ChainId) -> Self {
         Self {
-            s3_client,
-            // hardcode to mainnet for now
-            chain_id: ChainId::Mainnet,
+            s3_client: std::sync::Arc::new(s3_client),
+            chain_id,
         }
     }
 
     pub async fn get_latest_block_metadata(&self) -> anyhow::Result<LatestBlockMetadata> {
         let metadata_file_content = self
             .s3_client
-            .get_text_file(DELTA_LAKE_BUCKET, LATEST_BLOCK_METADATA_KEY)
+            .get_text_file(self.get_delta_lake_bucket(), LATEST_BLOCK_METADATA_KEY)
             .await?;
 
         serde_json::from_str::<LatestBlockMetadata>(&metadata_file_content)
             .context("Unable to parse Metadata")
     }
 
+    fn get_delta_lake_bucket(&self) -> &'static str {
+        match self.chain_id {
+            ChainId::Mainnet => MAINNET_DELTA_LAKE_BUCKET,
+            ChainId::Testnet => TESTNET_DELTA_LAKE_BUCKET,
+        }
+    }
+
     fn get_lake_bucket(&self) -> String {
         match self.chain_id {
             ChainId::Mainnet => "near-lake-data-mainnet".to_string(),
@@ -119,81 +173,97 @@ impl DeltaLakeClientImpl {
         format!("{}/{}/", INDEXED_ACTIONS_PREFIX, folders.join("/"))
     }
 
-    async fn list_objects_recursive(
+    async fn list_matching_index_files(
         &self,
-        prefix: &str,
-        depth: u32,
+        contract_pattern: &str,
     ) -> anyhow::Result<Vec<String>> {
-        if depth > 1 {
-            unimplemented!("Recursive list with depth > 1 not supported")
-        }
-
-        let objects = self
-            .s3_client
-            .list_all_objects(DELTA_LAKE_BUCKET, prefix)
-            .await?;
-
         let mut results = vec![];
-        // TODO do in parallel?
-        // TODO only list objects without .json extension
-        for object in objects {
-            results.extend(
-                self.s3_client
-                    .list_all_objects(DELTA_LAKE_BUCKET, &object)
-                    .await?,
-            );
+
+        for contract_id in contract_pattern.split(',').map(str::trim) {
+            match Pattern::new(contract_id) {
+                Pattern::Exact(contract_id) => results.extend(
+                    self.s3_client
+                        .list_all_objects(
+                            self.get_delta_lake_bucket(),
+                            &self.s3_prefix_from_contract_id(&contract_id),
+                        )
+                        .await?,
+                ),
+                pattern => results.extend(
+                    self.list_wildcard_index_files(contract_id, &pattern)
+                        .await?,
+                ),
+            }
         }
 
+        // Overlapping patterns, e.g. `*.keypom.near, beta.keypom.near`, list the same files
+        results.sort();
+        results.dedup();
+
         Ok(results)
     }
 
-    async fn list_matching_index_files(
+    /// Walks the index folders beneath the fixed suffix of `contract_id`, one level of the
+    /// account hierarchy at a time, only descending into folders which may contain matching
+    /// sub-accounts. Index files are then selected with the same `Pattern` used by the real-time
+    /// matcher, so both agree on which accounts match.
+    async fn list_wildcard_index_files(
         &self,
-        contract_pattern: &str,
+        contract_id: &str,
+        pattern: &Pattern,
     ) -> anyhow::Result<Vec<String>> {
-        match contract_pattern {
-            pattern if pattern.contains(',') => {
-                let contract_ids = pattern.split(',');
-
-                let mut results = vec![];
-
-                for contract_id in contract_ids {
-                    let contract_id = contract_id.trim();
-
-                    if contract_id.contains('*') {
-                        let pattern = contract_id.replace("*.", "");
-                        results.extend(
-                            self.list_objects_recursive(
-                                &self.s3_prefix_from_contract_id(&pattern),
-                                1,
-                            )
-                            .await?,
-                        );
-                    } else {
-                        results.extend(
-                            self.s3_client
-                                .list_all_objects(
-                                    DELTA_LAKE_BUCKET,
-                                    &self.s3_prefix_from_contract_id(contract_id),
-                                )
-                                .await?,
-                        );
+        let fixed_account_id = fixed_account_suffix(contract_id);
+
+        // Every account shares a top-level account, so the walk would list a significant part of
+        // the index
+        if !fixed_account_id.contains('.') {
+            anyhow::bail!(
+                "Cannot list index files for {}, wildcards must be beneath a sub-account",
+                contract_id
+            );
+        }
+
+        let root_folder = self.s3_prefix_from_contract_id(&fixed_account_id);
+
+        let mut index_files = vec![];
+        let mut folders = vec![(root_folder, fixed_account_id)];
+
+        while !folders.is_empty() {
+            let listings: Vec<(String, Vec<String>)> = futures::stream::iter(folders)
+                .map(|(folder, account_id)| async move {
+                    let objects = self
+                        .s3_client
+                        .list_all_objects(self.get_delta_lake_bucket(), &folder)
+                        .await?;
+
+                    Ok::<_, anyhow::Error>((account_id, objects))
+                })
+                .buffer_unordered(MAX_CONCURRENT_INDEX_FOLDER_LISTS)
+                .try_collect()
+                .await?;
+
+            folders = vec![];
+
+            for (account_id, objects) in listings {
+                for object in objects {
+                    let Some(folder) = object.strip_suffix('/') else {
+                        if pattern.matches(&account_id) {
+                            index_files.push(object);
+                        }
+                        continue;
                     };
-                }
 
-                Ok(results)
-            }
-            pattern if pattern.contains('*') => {
-                let contract_id = pattern.replace("*.", "");
-                self.list_objects_recursive(&self.s3_prefix_from_contract_id(&contract_id), 1)
-                    .await
-            }
-            pattern => {
-                self.s3_client
-                    .list_all_objects(DELTA_LAKE_BUCKET, &self.s3_prefix_from_contract_id(pattern))
-                    .await
+                    let segment = folder.rsplit('/').next().unwrap_or_default();
+                    let sub_account_id = format!("{}.{}", segment, account_id);
+
+                    if may_match_account_or_sub_accounts(contract_id, &sub_account_id) {
+                        folders.push((object, sub_account_id));
+                    }
+                }
             }
         }
+
+        Ok(index_files)
     }
 
     fn date_from_s3_path(&self, path: &str) -> Option<chrono::NaiveDate> {
@@ -202,11 +272,14 @@ impl DeltaLakeClientImpl {
         chrono::NaiveDate::parse_from_str(&file_name_date, "%Y-%m-%d").ok()
     }
 
-    pub async fn list_matching_block_heights(
+    /// Lists the index files of matching contracts which may contain heights from
+    /// `start_block_height` onwards, ordered by date.
+    pub async fn list_index_files(
         &self,
         start_block_height: near_indexer_primitives::types::BlockHeight,
         contract_pattern: &str,
-    ) -> anyhow::Result<Vec<near_indexer_primitives::types::BlockHeight>> {
+        action_kinds: Option<Vec<String>>,
+    ) -> anyhow::Result<Vec<IndexFileKey>> {
         let start_date = self.get_nearest_block_date(start_block_height).await?;
 
         let file_list = self.list_matching_index_files(contract_pattern).await?;
@@ -216,53 +289,225 @@ impl DeltaLakeClientImpl {
             contract_pattern,
         );
 
-        let futures = file_list
+        let mut index_files: Vec<_> = file_list
             .into_iter()
             // TODO use `start_after` in the request to S3 to avoid this filter
-            .filter(|file_path| {
-                self.date_from_s3_path(file_path)
+            .filter_map(|file_path| {
+                self.date_from_s3_path(&file_path)
                     // Ignore invalid paths, i.e. sub-folders, by default
-                    .map_or(false, |file_date| file_date >= start_date.date_naive())
+                    .filter(|file_date| *file_date >= start_date.date_naive())
+                    .map(|date| IndexFileKey {
+                        key: file_path,
+                        date,
+                        action_kinds: action_kinds.clone(),
+                    })
             })
-            .map(|key| async move { self.s3_client.get_text_file(DELTA_LAKE_BUCKET, &key).await })
-            .collect::<Vec<_>>();
+            .collect();
+        index_files.sort_by_key(|index_file| index_file.date);
 
         tracing::debug!(
             "Found {} index files matching {} after date {}",
-            futures.len(),
+            index_files.len(),
             contract_pattern,
             start_date
         );
 
-        let file_content_list = try_join_all(futures).await?;
+        Ok(index_files)
+    }
+
+    /// Streams the heights within the index files from `start_block_height` onwards, one batch
+    /// per date, in order. Index files for upcoming dates are fetched with bounded concurrency
+    /// while the current batch is being processed, so memory usage does not grow with the number
+    /// of index files.
+    pub fn stream_matching_block_heights(
+        &self,
+        start_block_height: near_indexer_primitives::types::BlockHeight,
+        index_files: Vec<IndexFileKey>,
+    ) -> futures::stream::BoxStream<'static, anyhow::Result<BlockHeightBatch>> {
+        let s3_client = self.s3_client.clone();
+        let bucket = self.get_delta_lake_bucket();
+
+        // Files for the same date belong to different contracts, so must be merged for heights
+        // to be published in order
+        let mut index_files_by_date: Vec<Vec<IndexFileKey>> = vec![];
+        for index_file in index_files {
+            match index_files_by_date.last_mut() {
+                Some(date_index_files) if date_index_files[0].date == index_file.date => {
+                    date_index_files.push(index_file)
+                }
+                _ => index_files_by_date.push(vec![index_file]),
+            }
+        }
 
-        let mut block_heights: Vec<_> = file_content_list
-            .into_iter()
-            .filter_map(|content| {
-                if content.is_empty() {
-                    None
-                } else {
-                    serde_json::from_str::<IndexFile>(&content).ok()
+        futures::stream::iter(index_files_by_date)
+            .map(move |date_index_files| {
+                let s3_client = s3_client.clone();
+
+                async move {
+                    let date = date_index_files[0].date;
+                    let index_file_count = date_index_files.len();
+
+                    let block_heights_by_file: Vec<Vec<_>> =
+                        futures::stream::iter(date_index_files)
+                            .map(|index_file| {
+                                let s3_client = s3_client.clone();
+
+                                async move {
+                                    let content =
+                                        s3_client.get_text_file(bucket, &index_file.key).await?;
+
+                                    Ok::<_, anyhow::Error>(index_file.block_heights(&content))
+                                }
+                            })
+                            .buffer_unordered(MAX_CONCURRENT_INDEX_FILE_FETCHES)
+                            .try_collect()
+                            .await?;
+
+                    let mut block_heights: Vec<_> = block_heights_by_file
+                        .into_iter()
+                        .flatten()
+                        .filter(|block_height| *block_height >= start_block_height)
+                        .collect();
+                    block_heights.sort();
+                    block_heights.dedup();
+
+                    Ok(BlockHeightBatch {
+                        date,
+                        index_file_count,
+                        block_heights,
+                    })
                 }
             })
-            .flat_map(|index_file| index_file.heights)
-            .filter(|block_height| *block_height >= start_block_height)
-            .collect();
+            .buffered(MAX_PREFETCHED_INDEX_DATES)
+            // Index files occasionally contain heights belonging to other dates, dropping those
+            // already yielded keeps heights strictly increasing across batches
+            .scan(None, |last_block_height, block_height_batch| {
+                let block_height_batch =
+                    block_height_batch.map(|mut block_height_batch: BlockHeightBatch| {
+                        if let Some(last_block_height) = *last_block_height {
+                            block_height_batch
+                                .block_heights
+                                .retain(|block_height| *block_height > last_block_height);
+                        }
+                        if let Some(block_height) = block_height_batch.block_heights.last() {
+                            *last_block_height = Some(*block_height);
+                        }
 
-        let pattern_has_multiple_contracts = contract_pattern.chars().any(|c| c == ',' || c == '*');
-        if pattern_has_multiple_contracts {
-            block_heights.sort();
-            block_heights.dedup();
-        }
+                        block_height_batch
+                    });
 
-        tracing::debug!(
-            "Found {} matching block heights matching {}",
-            block_heights.len(),
-            contract_pattern,
+                futures::future::ready(Some(block_height_batch))
+            })
+            .boxed()
+    }
+
+    pub async fn get_streamer_message(
+        &self,
+        block_height: near_indexer_primitives::types::BlockHeight,
+    ) -> anyhow::Result<near_indexer_primitives::StreamerMessage> {
+        let lake_bucket = self.get_lake_bucket();
+
+        let block: near_indexer_primitives::views::BlockView = serde_json::from_str(
+            &self
+                .s3_client
+                .get_text_file(&lake_bucket, &format!("{:0>12}/block.json", block_height))
+                .await?,
+        )
+        .context("Failed to parse block")?;
+
+        let futures = (0..block.chunks.len()).map(|shard_id| {
+            let lake_bucket = &lake_bucket;
+            async move {
+                let shard_file = self
+                    .s3_client
+                    .get_text_file(
+                        lake_bucket,
+                        &format!("{:0>12}/shard_{}.json", block_height, shard_id),
+                    )
+                    .await?;
+
+                serde_json::from_str::<near_indexer_primitives::IndexerShard>(&shard_file)
+                    .context("Failed to parse shard")
+            }
+        });
+
+        let shards = try_join_all(futures).await?;
+
+        Ok(near_indexer_primitives::StreamerMessage { block, shards })
+    }
+}
+
+/// The account which every account matching `contract_id` is, or is a sub-account of, i.e. the
+/// segments following the last wildcard.
+fn fixed_account_suffix(contract_id: &str) -> String {
+    let mut fixed_segments: Vec<_> = contract_id
+        .rsplit('.')
+        .take_while(|segment| !is_wildcard(segment))
+        .collect();
+    fixed_segments.reverse();
+
+    fixed_segments.join(".")
+}
+
+/// Whether the index files of every account matching the comma separated `contract_pattern` can
+/// be listed, which requires wildcards to be beneath a sub-account, e.g. `*.keypom.near` but not
+/// `*.near` or `*`.
+pub fn can_list_index_files(contract_pattern: &str) -> bool {
+    contract_pattern
+        .split(',')
+        .map(str::trim)
+        .all(|contract_id| {
+            !is_wildcard(contract_id) || fixed_account_suffix(contract_id).contains('.')
+        })
+}
+
+/// Whether `pattern` could match `account_id` or any of its sub-accounts. Accounts are compared
+/// from the end, as that is the order the index hierarchy is walked in, tracking every position
+/// of the reversed pattern which could have been reached so far.
+fn may_match_account_or_sub_accounts(pattern: &str, account_id: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().rev().collect();
+
+    let skip_stars = |mut positions: Vec<usize>| {
+        let mut index = 0;
+        while index < positions.len() {
+            let position = positions[index];
+            if pattern.get(position) == Some(&'*') {
+                positions.push(position + 1);
+            }
+            index += 1;
+        }
+        positions.sort();
+        positions.dedup();
+        positions
+    };
+
+    let mut positions = skip_stars(vec![0]);
+
+    for character in account_id.chars().rev() {
+        positions = skip_stars(
+            positions
+                .into_iter()
+                .filter_map(|position| match pattern.get(position) {
+                    Some('*') => Some(position),
+                    Some('?') => Some(position + 1),
+                    Some(pattern_character) if *pattern_character == character => {
+                        Some(position + 1)
+                    }
+                    _ => None,
+                })
+                .collect(),
         );
 
-        Ok(block_heights)
+        if positions.is_empty() {
+            return false;
+        }
     }
+
+    // Either the account itself matches, or the remaining pattern can continue with a `.` to
+    // match a sub-account
+    positions
+        .iter()
+        .any(|position| matches!(pattern.get(*position), None | Some('*' | '?' | '.')))
 }
 
 #[cfg(test)]
@@ -321,16 +566,35 @@ mod tests {
         )
     }
 
+    async fn collect_block_heights(
+        delta_lake_client: &DeltaLakeClientImpl,
+        start_block_height: near_indexer_primitives::types::BlockHeight,
+        contract_pattern: &str,
+        action_kinds: Option<Vec<String>>,
+    ) -> Vec<near_indexer_primitives::types::BlockHeight> {
+        let index_files = delta_lake_client
+            .list_index_files(start_block_height, contract_pattern, action_kinds)
+            .await
+            .unwrap();
+
+        delta_lake_client
+            .stream_matching_block_heights(start_block_height, index_files)
+            .map_ok(|block_height_batch| block_height_batch.block_heights)
+            .try_concat()
+            .await
+            .unwrap()
+    }
+
     #[tokio::test]
     async fn fetches_metadata_from_s3() {
         let mut mock_s3_client = crate::s3_client::S3Client::default();
 
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET), predicate::eq(LATEST_BLOCK_METADATA_KEY))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET), predicate::eq(LATEST_BLOCK_METADATA_KEY))
             .returning(|_bucket, _prefix| Ok("{ \"last_indexed_block\": \"106309326\", \"first_indexed_block\": \"106164983\", \"last_indexed_block_date\": \"2023-11-22\", \"first_indexed_block_date\": \"2023-11-21\", \"processed_at_utc\": \"2023-11-22 23:06:24.358000\" }".to_string()));
 
-        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client);
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Mainnet);
 
         let latest_block_metadata = delta_lake_client.get_latest_block_metadata().await.unwrap();
 
@@ -346,6 +610,22 @@ mod tests {
         )
     }
 
+    #[tokio::test]
+    async fn fetches_testnet_metadata_from_testnet_bucket() {
+        let mut mock_s3_client = crate::s3_client::S3Client::default();
+
+        mock_s3_client
+            .expect_get_text_file()
+            .with(predicate::eq(TESTNET_DELTA_LAKE_BUCKET), predicate::eq(LATEST_BLOCK_METADATA_KEY))
+            .returning(|_bucket, _prefix| Ok("{ \"last_indexed_block\": \"153092587\", \"first_indexed_block\": \"152956178\", \"last_indexed_block_date\": \"2024-01-10\", \"first_indexed_block_date\": \"2024-01-09\", \"processed_at_utc\": \"2024-01-10 23:06:24.358000\" }".to_string()));
+
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Testnet);
+
+        let latest_block_metadata = delta_lake_client.get_latest_block_metadata().await.unwrap();
+
+        assert_eq!(latest_block_metadata.last_indexed_block, "153092587");
+    }
+
     #[tokio::test]
     async fn lists_block_heights_for_single_contract() {
         let mut mock_s3_client = crate::s3_client::S3Client::default();
@@ -367,21 +647,87 @@ mod tests {
             });
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/dataplatform/queryapi/2023-05-15.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/dataplatform/queryapi/2023-05-15.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[91940840,91942989],\"actions\":[{\"action_kind\":\"FUNCTION_CALL\",\"block_heights\":[91942989,91940840]}]}".to_string()));
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/dataplatform/queryapi/2023-05-17.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/dataplatform/queryapi/2023-05-17.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[92080299,92080344],\"actions\":[{\"action_kind\":\"FUNCTION_CALL\",\"block_heights\":[92080344,92080299]}]}".to_string()));
 
-        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client);
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Mainnet);
+
+        let block_heights = collect_block_heights(
+            &delta_lake_client,
+            91940840,
+            "queryapi.dataplatform.near",
+            None,
+        )
+        .await;
+
+        assert_eq!(block_heights, vec![92080299, 92080344])
+    }
+
+    #[tokio::test]
+    async fn lists_block_heights_by_action_kind() {
+        let mut mock_s3_client = crate::s3_client::S3Client::default();
+
+        mock_s3_client
+            .expect_get_text_file()
+            .with(
+                predicate::eq("near-lake-data-mainnet"),
+                predicate::eq("000091940840/block.json"),
+            )
+            .returning(|_bucket, _prefix| Ok(generate_block_with_timestamp("2023-05-16")));
+        mock_s3_client
+            .expect_list_all_objects()
+            .returning(|_bucket, _prefix| {
+                Ok(vec![
+                    "silver/accounts/action_receipt_actions/metadata/near/dataplatform/queryapi/2023-05-17.json".to_string(),
+                ])
+            });
+        mock_s3_client
+            .expect_get_text_file()
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/dataplatform/queryapi/2023-05-17.json".to_string()))
+            .returning(|_bucket, _prefix| Ok("{\"heights\":[92080299,92080344,92080400],\"actions\":[{\"action_kind\":\"FUNCTION_CALL\",\"block_heights\":[92080344,92080299]},{\"action_kind\":\"TRANSFER\",\"block_heights\":[92080400,92080344]}]}".to_string()));
+
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Mainnet);
+
+        let block_heights = collect_block_heights(
+            &delta_lake_client,
+            91940840,
+            "queryapi.dataplatform.near",
+            Some(vec!["FUNCTION_CALL".to_string()]),
+        )
+        .await;
+
+        assert_eq!(block_heights, vec![92080299, 92080344])
+    }
+
+    #[tokio::test]
+    async fn gets_streamer_message_from_lake() {
+        let mut mock_s3_client = crate::s3_client::S3Client::default();
+
+        mock_s3_client
+            .expect_get_text_file()
+            .with(predicate::eq("near-lake-data-mainnet"), predicate::always())
+            .returning(|_bucket, prefix| {
+                Ok(std::fs::read_to_string(format!(
+                    "{}/data/{}",
+                    env!("CARGO_MANIFEST_DIR"),
+                    prefix
+                ))
+                .unwrap())
+            });
+
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Mainnet);
 
-        let block_heights = delta_lake_client
-            .list_matching_block_heights(91940840, "queryapi.dataplatform.near")
+        let streamer_message = delta_lake_client
+            .get_streamer_message(93085141)
             .await
             .unwrap();
 
-        assert_eq!(block_heights, vec![92080299, 92080344])
+        assert_eq!(streamer_message.block.header.height, 93085141);
+        assert_eq!(streamer_message.shards.len(), 4);
     }
 
     #[tokio::test]
@@ -416,30 +762,30 @@ mod tests {
             });
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/agency/hackathon/2021-08-22.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/agency/hackathon/2021-08-22.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[45894617,45894627,45894628,45894712,45898413,45898423,45898424],\"actions\":[{\"action_kind\":\"CREATE_ACCOUNT\",\"block_heights\":[45894617,45898413]},{\"action_kind\":\"FUNCTION_CALL\",\"block_heights\":[45898423,45894627]},{\"action_kind\":\"DELETE_ACCOUNT\",\"block_heights\":[45894712]},{\"action_kind\":\"ADD_KEY\",\"block_heights\":[45894617,45898413]},{\"action_kind\":\"TRANSFER\",\"block_heights\":[45894628,45894617,45898424,45898413]},{\"action_kind\":\"DEPLOY_CONTRACT\",\"block_heights\":[45898423,45894627]}]}".to_string()));
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/aurora-silo-dev/hackathon/2023-05-18.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/aurora-silo-dev/hackathon/2023-05-18.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[92167977,92168200,92168293,92168338,92168535,92168870,92168871,92168922,92168923,92168939,92168971,92169330],\"actions\":[{\"action_kind\":\"DEPLOY_CONTRACT\",\"block_heights\":[92168200,92168338]},{\"action_kind\":\"ADD_KEY\",\"block_heights\":[92168535,92167977]},{\"action_kind\":\"CREATE_ACCOUNT\",\"block_heights\":[92167977]},{\"action_kind\":\"FUNCTION_CALL\",\"block_heights\":[92168922,92168971,92168870]},{\"action_kind\":\"TRANSFER\",\"block_heights\":[92168871,92168923,92169330,92168293,92168939,92167977]}]}".to_string()));
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/aurora-silo-dev/hackathon/2023-05-30.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/aurora-silo-dev/hackathon/2023-05-30.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[92167977,93067570,93067619,93067631,93067726,93067737,93067770,93067889,93067920,93067926,93067936,93073935,93073944,93073954],\"actions\":[{\"action_kind\":\"FUNCTION_CALL\",\"block_heights\":[93073954,93067770,93067726,93065811,93067619,93073935,93067889,93067737,93067570,93067926,93073944,93067920,93067631,93067936]}]}".to_string()));
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/sputnik-dao/hackathon/2022-05-27.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/sputnik-dao/hackathon/2022-05-27.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[66494954],\"actions\":[{\"action_kind\":\"CREATE_ACCOUNT\",\"block_heights\":[66494954]},{\"action_kind\":\"DEPLOY_CONTRACT\",\"block_heights\":[66494954]},{\"action_kind\":\"FUNCTION_CALL\",\"block_heights\":[66494954]},{\"action_kind\":\"TRANSFER\",\"block_heights\":[66494954]}]}".to_string()));
 
-        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client);
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Mainnet);
 
-        let block_heights = delta_lake_client
-            .list_matching_block_heights(
-                45894617,
-                "hackathon.agency.near, hackathon.aurora-silo-dev.near, hackathon.sputnik-dao.near",
-            )
-            .await
-            .unwrap();
+        let block_heights = collect_block_heights(
+            &delta_lake_client,
+            45894617,
+            "hackathon.agency.near, hackathon.aurora-silo-dev.near, hackathon.sputnik-dao.near",
+            None,
+        )
+        .await;
 
         assert_eq!(
             block_heights,
@@ -479,19 +825,6 @@ mod tests {
                     "silver/accounts/action_receipt_actions/metadata/near/keypom/nft/" => vec![
                         "silver/accounts/action_receipt_actions/metadata/near/keypom/nft/2023-09-26.json".to_string(),
                     ],
-
-                    "silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-23.json" => vec![
-                        "silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-23.json".to_string()
-                    ],
-                    "silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-31.json" => vec![
-                        "silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-31.json".to_string()
-                    ],
-                    "silver/accounts/action_receipt_actions/metadata/near/keypom/beta/2022-11-15.json" => vec![
-                        "silver/accounts/action_receipt_actions/metadata/near/keypom/beta/2022-11-15json".to_string()
-                    ],
-                    "silver/accounts/action_receipt_actions/metadata/near/keypom/nft/2023-09-26.json" => vec![
-                        "silver/accounts/action_receipt_actions/metadata/near/keypom/nft/2023-09-26.json".to_string()
-                    ],
                     _ => panic!("Unexpected prefix: {}", prefix)
                 };
 
@@ -499,32 +832,28 @@ mod tests {
             });
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/beta/2022-11-15.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/beta/2022-11-15.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[78516467,78516476,78516489,78516511,78516512],\"actions\":[{\"action_kind\":\"DELETE_ACCOUNT\",\"block_heights\":[78516467]},{\"action_kind\":\"CREATE_ACCOUNT\",\"block_heights\":[78516476]},{\"action_kind\":\"TRANSFER\",\"block_heights\":[78516476,78516512]},{\"action_kind\":\"ADD_KEY\",\"block_heights\":[78516476]},{\"action_kind\":\"FUNCTION_CALL\",\"block_heights\":[78516511]},{\"action_kind\":\"DEPLOY_CONTRACT\",\"block_heights\":[78516489]}]}".to_string()));
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/nft/2023-09-26.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/nft/2023-09-26.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[102025554],\"actions\":[{\"action_kind\":\"FUNCTION_CALL\",\"block_heights\":[102025554]}]}".to_string()));
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-23.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-23.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[104045849,104047967,104047968],\"actions\":[{\"action_kind\":\"TRANSFER\",\"block_heights\":[104047968,104045849,104047967]}]}".to_string()));
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-31.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-31.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[104616819],\"actions\":[{\"action_kind\":\"ADD_KEY\",\"block_heights\":[104616819]}]}".to_string()));
 
-        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client);
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Mainnet);
 
-        let block_heights = delta_lake_client
-            .list_matching_block_heights(78516467, "*.keypom.near")
-            .await
-            .unwrap();
+        let block_heights =
+            collect_block_heights(&delta_lake_client, 78516467, "*.keypom.near", None).await;
 
-        assert_eq!(
-            block_heights,
-            vec![102025554, 104045849, 104047967, 104047968, 104616819]
-        )
+        // `keypom.near` itself does not match `*.keypom.near`, as with the real-time matcher
+        assert_eq!(block_heights, vec![102025554])
     }
 
     #[tokio::test]
@@ -552,12 +881,6 @@ mod tests {
                     "silver/accounts/action_receipt_actions/metadata/near/agency/hackathon/" => vec![
                         "silver/accounts/action_receipt_actions/metadata/near/agency/hackathon/2021-08-22.json".to_string()
                     ],
-                    "silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-31.json" => vec![
-                        "silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-31.json".to_string()
-                    ],
-                    "silver/accounts/action_receipt_actions/metadata/near/keypom/beta/2022-11-15.json" => vec![
-                        "silver/accounts/action_receipt_actions/metadata/near/keypom/beta/2022-11-15json".to_string()
-                    ],
                     _ => panic!("Unexpected prefix: {}", prefix)
                 };
 
@@ -565,33 +888,152 @@ mod tests {
             });
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/agency/hackathon/2021-08-22.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/agency/hackathon/2021-08-22.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[45894617,45894627,45894628,45894712,45898413,45898423,45898424],\"actions\":[{\"action_kind\":\"CREATE_ACCOUNT\",\"block_heights\":[45894617,45898413]},{\"action_kind\":\"FUNCTION_CALL\",\"block_heights\":[45898423,45894627]},{\"action_kind\":\"DELETE_ACCOUNT\",\"block_heights\":[45894712]},{\"action_kind\":\"ADD_KEY\",\"block_heights\":[45894617,45898413]},{\"action_kind\":\"TRANSFER\",\"block_heights\":[45894628,45894617,45898424,45898413]},{\"action_kind\":\"DEPLOY_CONTRACT\",\"block_heights\":[45898423,45894627]}]}".to_string()));
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/beta/2022-11-15.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/beta/2022-11-15.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[78516467,78516476,78516489,78516511,78516512],\"actions\":[{\"action_kind\":\"DELETE_ACCOUNT\",\"block_heights\":[78516467]},{\"action_kind\":\"CREATE_ACCOUNT\",\"block_heights\":[78516476]},{\"action_kind\":\"TRANSFER\",\"block_heights\":[78516476,78516512]},{\"action_kind\":\"ADD_KEY\",\"block_heights\":[78516476]},{\"action_kind\":\"FUNCTION_CALL\",\"block_heights\":[78516511]},{\"action_kind\":\"DEPLOY_CONTRACT\",\"block_heights\":[78516489]}]}".to_string()));
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-31.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-31.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[104616819],\"actions\":[{\"action_kind\":\"ADD_KEY\",\"block_heights\":[104616819]}]}".to_string()));
 
-        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client);
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Mainnet);
 
-        let block_heights = delta_lake_client
-            .list_matching_block_heights(45894617, "*.keypom.near, hackathon.agency.near")
-            .await
-            .unwrap();
+        let block_heights = collect_block_heights(
+            &delta_lake_client,
+            45894617,
+            "*.keypom.near, hackathon.agency.near",
+            None,
+        )
+        .await;
 
         assert_eq!(
             block_heights,
             vec![
                 45894617, 45894627, 45894628, 45894712, 45898413, 45898423, 45898424, 78516467,
-                78516476, 78516489, 78516511, 78516512, 104616819
+                78516476, 78516489, 78516511, 78516512
             ]
         )
     }
 
+    #[tokio::test]
+    async fn lists_block_heights_for_nested_and_mid_string_wildcards() {
+        let mut mock_s3_client = crate::s3_client::S3Client::default();
+
+        mock_s3_client
+            .expect_get_text_file()
+            .with(
+                predicate::eq("near-lake-data-mainnet"),
+                predicate::eq("000093085141/block.json"),
+            )
+            .returning(|_bucket, _prefix| Ok(generate_block_with_timestamp("2023-05-16")));
+        mock_s3_client
+            .expect_list_all_objects()
+            .returning(|_bucket, prefix| {
+                let objects = match prefix {
+                    "silver/accounts/action_receipt_actions/metadata/near/nearcrowd/" => vec![
+                        "silver/accounts/action_receipt_actions/metadata/near/nearcrowd/api/".to_string(),
+                        "silver/accounts/action_receipt_actions/metadata/near/nearcrowd/app/".to_string(),
+                        "silver/accounts/action_receipt_actions/metadata/near/nearcrowd/2023-05-16.json".to_string(),
+                    ],
+                    "silver/accounts/action_receipt_actions/metadata/near/nearcrowd/app/" => vec![
+                        "silver/accounts/action_receipt_actions/metadata/near/nearcrowd/app/v2/".to_string(),
+                        "silver/accounts/action_receipt_actions/metadata/near/nearcrowd/app/2023-05-16.json".to_string(),
+                    ],
+                    "silver/accounts/action_receipt_actions/metadata/near/nearcrowd/app/v2/" => vec![
+                        "silver/accounts/action_receipt_actions/metadata/near/nearcrowd/app/v2/2023-05-17.json".to_string(),
+                    ],
+                    _ => panic!("Unexpected prefix: {}", prefix)
+                };
+
+                Ok(objects)
+            });
+        mock_s3_client
+            .expect_get_text_file()
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/nearcrowd/app/2023-05-16.json".to_string()))
+            .returning(|_bucket, _prefix| Ok("{\"heights\":[93085141],\"actions\":[]}".to_string()));
+        mock_s3_client
+            .expect_get_text_file()
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/nearcrowd/app/v2/2023-05-17.json".to_string()))
+            .returning(|_bucket, _prefix| Ok("{\"heights\":[93150000],\"actions\":[]}".to_string()));
+
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Mainnet);
+
+        let block_heights = collect_block_heights(
+            &delta_lake_client,
+            93085141,
+            "a*p.nearcrowd.near, *.app.nearcrowd.near",
+            None,
+        )
+        .await;
+
+        assert_eq!(block_heights, vec![93085141, 93150000])
+    }
+
+    #[tokio::test]
+    async fn rejects_wildcards_which_are_not_beneath_a_sub_account() {
+        let mut mock_s3_client = crate::s3_client::S3Client::default();
+
+        mock_s3_client
+            .expect_get_text_file()
+            .with(
+                predicate::eq("near-lake-data-mainnet"),
+                predicate::eq("000093085141/block.json"),
+            )
+            .returning(|_bucket, _prefix| Ok(generate_block_with_timestamp("2023-05-16")));
+        mock_s3_client.expect_list_all_objects().never();
+
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Mainnet);
+
+        for contract_pattern in ["*", "*.near", "?pp.*", "app.nea*owd.near"] {
+            assert!(delta_lake_client
+                .list_index_files(93085141, contract_pattern, None)
+                .await
+                .is_err());
+        }
+
+        assert!(can_list_index_files(
+            "app.near, *.keypom.near, a?p.nearcrowd.near"
+        ));
+        assert!(!can_list_index_files("app.near, *.near"));
+    }
+
+    #[test]
+    fn prunes_accounts_which_cannot_contain_matches() {
+        assert!(may_match_account_or_sub_accounts(
+            "app.nea*owd.near",
+            "near"
+        ));
+        assert!(may_match_account_or_sub_accounts(
+            "app.nea*owd.near",
+            "nearcrowd.near"
+        ));
+        assert!(may_match_account_or_sub_accounts(
+            "app.nea*owd.near",
+            "app.nearcrowd.near"
+        ));
+        assert!(may_match_account_or_sub_accounts(
+            "app.nea*owd.near",
+            "x.owd.near"
+        ));
+        assert!(!may_match_account_or_sub_accounts(
+            "app.nea*owd.near",
+            "keypom.near"
+        ));
+        assert!(!may_match_account_or_sub_accounts(
+            "*.keypom.near",
+            "keypom.nearcrowd.near"
+        ));
+        assert!(may_match_account_or_sub_accounts(
+            "*.keypom.near",
+            "a.b.keypom.near"
+        ));
+        assert!(may_match_account_or_sub_accounts("?pp.near", "app.near"));
+        assert!(!may_match_account_or_sub_accounts("?pp.near", "x.app.near"));
+    }
+
     #[tokio::test]
     async fn sorts_and_removes_duplicates_for_multiple_contracts() {
         let mut mock_s3_client = crate::s3_client::S3Client::default();
@@ -621,19 +1063,22 @@ mod tests {
             });
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/agency/hackathon/2021-08-22.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/agency/hackathon/2021-08-22.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[45894628,45894617,45898413,45894627,45894712,45898423,45898424],\"actions\":[{\"action_kind\":\"CREATE_ACCOUNT\",\"block_heights\":[45894617,45898413]},{\"action_kind\":\"FUNCTION_CALL\",\"block_heights\":[45898423,45894627]},{\"action_kind\":\"DELETE_ACCOUNT\",\"block_heights\":[45894712]},{\"action_kind\":\"ADD_KEY\",\"block_heights\":[45894617,45898413]},{\"action_kind\":\"TRANSFER\",\"block_heights\":[45894628,45894617,45898424,45898413]},{\"action_kind\":\"DEPLOY_CONTRACT\",\"block_heights\":[45898423,45894627]}]}".to_string()));
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-31.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-31.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[45898424,45898423,45898413,45894712],\"actions\":[{\"action_kind\":\"ADD_KEY\",\"block_heights\":[104616819]}]}".to_string()));
 
-        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client);
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Mainnet);
 
-        let block_heights = delta_lake_client
-            .list_matching_block_heights(45894628, "keypom.near, hackathon.agency.near")
-            .await
-            .unwrap();
+        let block_heights = collect_block_heights(
+            &delta_lake_client,
+            45894628,
+            "keypom.near, hackathon.agency.near",
+            None,
+        )
+        .await;
 
         assert_eq!(
             block_heights,
@@ -660,15 +1105,18 @@ mod tests {
         });
         mock_s3_client
             .expect_get_text_file()
-            .with(predicate::eq(DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-31.json".to_string()))
+            .with(predicate::eq(MAINNET_DELTA_LAKE_BUCKET.to_string()), predicate::eq("silver/accounts/action_receipt_actions/metadata/near/keypom/2023-10-31.json".to_string()))
             .returning(|_bucket, _prefix| Ok("{\"heights\":[45898424,45898423,45898413,45894712],\"actions\":[{\"action_kind\":\"ADD_KEY\",\"block_heights\":[104616819]}]}".to_string()));
 
-        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client);
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Mainnet);
 
-        let block_heights = delta_lake_client
-            .list_matching_block_heights(45898423, "keypom.near, hackathon.agency.near")
-            .await
-            .unwrap();
+        let block_heights = collect_block_heights(
+            &delta_lake_client,
+            45898423,
+            "keypom.near, hackathon.agency.near",
+            None,
+        )
+        .await;
 
         assert_eq!(block_heights, vec![45898423, 45898424])
     }
@@ -686,7 +1134,7 @@ mod tests {
             .times(1)
             .returning(|_bucket, _prefix| Ok(generate_block_with_timestamp("2021-05-26")));
 
-        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client);
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Mainnet);
 
         let block_date = delta_lake_client
             .get_nearest_block_date(106397175)
@@ -721,7 +1169,7 @@ mod tests {
             .times(1)
             .returning(|_bucket, _prefix| Ok(generate_block_with_timestamp("2021-05-26")));
 
-        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client);
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Mainnet);
 
         let block_date = delta_lake_client
             .get_nearest_block_date(106397175)
@@ -744,7 +1192,7 @@ mod tests {
                 ))
             });
 
-        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client);
+        let delta_lake_client = DeltaLakeClientImpl::new(mock_s3_client, ChainId::Mainnet);
 
         let result = delta_lake_client.get_nearest_block_date(106397175).await;
 
@@ -16,6 +16,14 @@ impl IndexerConfig {
         format!("{}/{}", self.account_id, self.function_name)
     }
 
+    pub fn get_last_published_block_key(&self) -> String {
+        format!("{}:last_published_block", self.get_full_name())
+    }
+
+    pub fn get_receipt_resolver_key(&self) -> String {
+        format!("{}:receipt_resolver", self.get_full_name())
+    }
+
     pub fn get_hash_id(&self) -> String {
         let mut hasher = DefaultHasher::new();
         self.get_full_name().hash(&mut hasher);
@@ -1,5 +1,12 @@
+#[allow(clippy::enum_variant_names)]
 mod blockstreamer {
     tonic::include_proto!("blockstreamer");
 }
 
 pub use blockstreamer::*;
+
+pub mod receipt_resolver;
+pub mod rules;
+
+#[cfg(test)]
+mod test_utils;
@@ -1,10 +1,15 @@
+use block_streamer::{receipt_resolver, rules};
+use rules::types::ChainId;
 use tracing_subscriber::prelude::*;
 
+mod block_delivery;
+mod block_sink;
 mod block_stream;
 mod delta_lake_client;
 mod indexer_config;
+mod metrics;
 mod redis;
-mod rules;
+mod rule_preview;
 mod s3_client;
 mod server;
 
@@ -20,21 +25,90 @@ async fn main() -> anyhow::Result<()> {
 
     let redis_url = std::env::var("REDIS_URL").expect("REDIS_URL is not set");
     let server_port = std::env::var("SERVER_PORT").expect("SERVER_PORT is not set");
+    let metrics_port = std::env::var("METRICS_PORT")
+        .map_or(Ok(9181), |value| value.parse())
+        .map_err(|err| anyhow::anyhow!("METRICS_PORT is not a valid port number: {}", err))?;
+    let redis_stream_watermarks = get_redis_stream_watermarks()?;
+    let block_sink_policy = get_block_sink_policy();
+    let restore_block_streams = std::env::var("RESTORE_BLOCK_STREAMS")
+        .map_or(Ok(true), |value| value.parse())
+        .map_err(|err| anyhow::anyhow!("RESTORE_BLOCK_STREAMS is not a valid boolean: {}", err))?;
 
     tracing::info!("Starting Block Streamer Service...");
 
+    tokio::spawn(metrics::init_server(metrics_port).expect("Failed to start metrics server"));
+
     tracing::info!("Connecting to Redis...");
     let redis_client = std::sync::Arc::new(redis::RedisClient::connect(&redis_url).await?);
 
     let aws_config = aws_config::from_env().load().await;
     let s3_config = aws_sdk_s3::Config::from(&aws_config);
-    let s3_client = crate::s3_client::S3Client::new(s3_config.clone());
 
     tracing::info!("Connecting to Delta Lake...");
-    let delta_lake_client =
-        std::sync::Arc::new(crate::delta_lake_client::DeltaLakeClient::new(s3_client));
+    let mainnet_delta_lake_client =
+        std::sync::Arc::new(crate::delta_lake_client::DeltaLakeClient::new(
+            crate::s3_client::S3Client::new(s3_config.clone()),
+            ChainId::Mainnet,
+        ));
+    let testnet_delta_lake_client =
+        std::sync::Arc::new(crate::delta_lake_client::DeltaLakeClient::new(
+            crate::s3_client::S3Client::new(s3_config.clone()),
+            ChainId::Testnet,
+        ));
 
-    server::init(&server_port, redis_client, delta_lake_client, s3_config).await?;
+    server::init(
+        &server_port,
+        redis_client,
+        mainnet_delta_lake_client,
+        testnet_delta_lake_client,
+        s3_config,
+        redis_stream_watermarks,
+        block_sink_policy,
+        restore_block_streams,
+    )
+    .await?;
 
     Ok(())
 }
+
+fn get_redis_stream_watermarks() -> anyhow::Result<block_stream::RedisStreamWatermarks> {
+    let default_watermarks = block_stream::RedisStreamWatermarks::default();
+
+    let parse_watermark = |name: &str, default: u64| -> anyhow::Result<u64> {
+        match std::env::var(name) {
+            Ok(value) => value
+                .parse()
+                .map_err(|err| anyhow::anyhow!("{} is not a valid number: {}", name, err)),
+            Err(_) => Ok(default),
+        }
+    };
+
+    let watermarks = block_stream::RedisStreamWatermarks {
+        high: parse_watermark("REDIS_STREAM_HIGH_WATERMARK", default_watermarks.high)?,
+        low: parse_watermark("REDIS_STREAM_LOW_WATERMARK", default_watermarks.low)?,
+    };
+
+    if watermarks.low >= watermarks.high {
+        anyhow::bail!("REDIS_STREAM_LOW_WATERMARK must be less than REDIS_STREAM_HIGH_WATERMARK");
+    }
+
+    Ok(watermarks)
+}
+
+/// File sinks are only enabled when BLOCK_SINK_FILE_DIRECTORY is set, and JetStream sinks may
+/// only connect to the comma separated servers in BLOCK_SINK_NATS_URLS.
+fn get_block_sink_policy() -> block_sink::BlockSinkPolicy {
+    block_sink::BlockSinkPolicy {
+        file_directory: std::env::var("BLOCK_SINK_FILE_DIRECTORY")
+            .ok()
+            .map(std::path::PathBuf::from),
+        allowed_nats_urls: std::env::var("BLOCK_SINK_NATS_URLS")
+            .map(|urls| {
+                urls.split(',')
+                    .map(|url| url.trim().to_string())
+                    .filter(|url| !url.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
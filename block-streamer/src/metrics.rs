@@ -0,0 +1,158 @@
+use actix_web::{get, App, HttpServer, Responder};
+use lazy_static::lazy_static;
+use prometheus::{Encoder, IntCounterVec, IntGaugeVec, Opts};
+
+const BLOCK_STREAM_LABELS: &[&str] = &["account_id", "function_name"];
+const S3_REQUEST_LABELS: &[&str] = &["account_id", "function_name", "operation"];
+
+pub const S3_GET_OBJECT: &str = "get_object";
+pub const S3_LIST_OBJECTS: &str = "list_objects";
+
+tokio::task_local! {
+    /// The account and function name of the block stream running within the current task.
+    static S3_REQUEST_BLOCK_STREAM: (String, String);
+}
+
+lazy_static! {
+    pub static ref LAST_PUBLISHED_BLOCK: IntGaugeVec = try_create_int_gauge_vec(
+        "queryapi_block_streamer_last_published_block",
+        "Height of the last block published to the sink",
+        BLOCK_STREAM_LABELS
+    )
+    .unwrap();
+    pub static ref LAKE_LAG_SECONDS: IntGaugeVec = try_create_int_gauge_vec(
+        "queryapi_block_streamer_lake_lag_seconds",
+        "Seconds between the timestamp of the last block received from NEAR Lake and now, i.e. how far behind the lake head the stream is",
+        BLOCK_STREAM_LABELS
+    )
+    .unwrap();
+    pub static ref BLOCKS_SCANNED: IntCounterVec = try_create_int_counter_vec(
+        "queryapi_block_streamer_blocks_scanned_total",
+        "Number of blocks checked against the rule",
+        BLOCK_STREAM_LABELS
+    )
+    .unwrap();
+    pub static ref MATCHES_PUBLISHED: IntCounterVec = try_create_int_counter_vec(
+        "queryapi_block_streamer_matches_published_total",
+        "Number of matching blocks published to the sink",
+        BLOCK_STREAM_LABELS
+    )
+    .unwrap();
+    pub static ref DELTA_LAKE_INDEX_FILES_FETCHED: IntCounterVec = try_create_int_counter_vec(
+        "queryapi_block_streamer_delta_lake_index_files_fetched_total",
+        "Number of Delta Lake index files fetched",
+        BLOCK_STREAM_LABELS
+    )
+    .unwrap();
+    pub static ref S3_REQUESTS: IntCounterVec = try_create_int_counter_vec(
+        "queryapi_block_streamer_s3_requests_total",
+        "Number of requests made to S3, labelled empty when not made by a block stream",
+        S3_REQUEST_LABELS
+    )
+    .unwrap();
+    pub static ref S3_REQUEST_ERRORS: IntCounterVec = try_create_int_counter_vec(
+        "queryapi_block_streamer_s3_request_errors_total",
+        "Number of failed requests made to S3, labelled empty when not made by a block stream",
+        S3_REQUEST_LABELS
+    )
+    .unwrap();
+}
+
+fn try_create_int_gauge_vec(
+    name: &str,
+    help: &str,
+    labels: &[&str],
+) -> prometheus::Result<IntGaugeVec> {
+    let opts = Opts::new(name, help);
+    let gauge = IntGaugeVec::new(opts, labels)?;
+    prometheus::register(Box::new(gauge.clone()))?;
+    Ok(gauge)
+}
+
+fn try_create_int_counter_vec(
+    name: &str,
+    help: &str,
+    labels: &[&str],
+) -> prometheus::Result<IntCounterVec> {
+    let opts = Opts::new(name, help);
+    let counter = IntCounterVec::new(opts, labels)?;
+    prometheus::register(Box::new(counter.clone()))?;
+    Ok(counter)
+}
+
+/// Removes the metrics of a stopped block stream, so that it is no longer reported.
+pub fn remove_block_stream_metrics(account_id: &str, function_name: &str) {
+    let labels = &[account_id, function_name];
+
+    let _ = LAST_PUBLISHED_BLOCK.remove_label_values(labels);
+    let _ = LAKE_LAG_SECONDS.remove_label_values(labels);
+    let _ = BLOCKS_SCANNED.remove_label_values(labels);
+    let _ = MATCHES_PUBLISHED.remove_label_values(labels);
+    let _ = DELTA_LAKE_INDEX_FILES_FETCHED.remove_label_values(labels);
+
+    for operation in [S3_GET_OBJECT, S3_LIST_OBJECTS] {
+        let labels = &[account_id, function_name, operation];
+
+        let _ = S3_REQUESTS.remove_label_values(labels);
+        let _ = S3_REQUEST_ERRORS.remove_label_values(labels);
+    }
+}
+
+/// Attributes the S3 requests made via the shared `S3Client` while running `future` to the block
+/// stream. The client is shared by every block stream, so the stream is tracked per task instead.
+pub async fn with_s3_request_block_stream<F: std::future::Future>(
+    account_id: &str,
+    function_name: &str,
+    future: F,
+) -> F::Output {
+    S3_REQUEST_BLOCK_STREAM
+        .scope((account_id.to_string(), function_name.to_string()), future)
+        .await
+}
+
+/// Records an S3 request against the block stream running within the current task, if any.
+pub fn record_s3_request(operation: &str, is_error: bool) {
+    let recorded = S3_REQUEST_BLOCK_STREAM.try_with(|(account_id, function_name)| {
+        record_block_stream_s3_request(account_id, function_name, operation, is_error)
+    });
+
+    if recorded.is_err() {
+        record_block_stream_s3_request("", "", operation, is_error);
+    }
+}
+
+pub fn record_block_stream_s3_request(
+    account_id: &str,
+    function_name: &str,
+    operation: &str,
+    is_error: bool,
+) {
+    let labels = &[account_id, function_name, operation];
+
+    S3_REQUESTS.with_label_values(labels).inc();
+
+    if is_error {
+        S3_REQUEST_ERRORS.with_label_values(labels).inc();
+    }
+}
+
+#[get("/metrics")]
+async fn get_metrics() -> impl Responder {
+    let mut buffer = Vec::<u8>::new();
+    let encoder = prometheus::TextEncoder::new();
+
+    if let Err(err) = encoder.encode(&prometheus::gather(), &mut buffer) {
+        tracing::error!("Failed to encode metrics: {:?}", err);
+    }
+
+    String::from_utf8(buffer).unwrap()
+}
+
+pub fn init_server(port: u16) -> anyhow::Result<actix_web::dev::Server> {
+    tracing::info!("Starting metrics server on http://0.0.0.0:{port}");
+
+    Ok(HttpServer::new(|| App::new().service(get_metrics))
+        .bind(("0.0.0.0", port))?
+        .disable_signals()
+        .run())
+}
@@ -0,0 +1,310 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use near_lake_framework::near_indexer_primitives::{
+    types::BlockHeight, CryptoHash, StreamerMessage,
+};
+
+/// The number of blocks after which receipts which have not been executed are forgotten. Receipts
+/// are usually executed within a few blocks of being created, so this only guards against
+/// unbounded growth.
+const DEFAULT_MAX_RECEIPT_AGE_BLOCKS: u64 = 1000;
+
+/// Resolves the originating transaction of receipts, by following receipts from the
+/// transaction they were converted from, through each receipt they spawn, across blocks.
+///
+/// Receipts created before the first tracked block cannot be resolved. Block streams persist the
+/// tracked receipts via [`ReceiptResolver::to_snapshot`] so that this only applies to the first
+/// block streamed from NEAR Lake, but candidate blocks backfilled from Delta Lake are not
+/// contiguous, so only receipts created within the same candidate block are resolved there.
+pub struct ReceiptResolver {
+    max_receipt_age_blocks: u64,
+    transaction_hashes: HashMap<CryptoHash, CryptoHash>,
+    receipts_by_block: VecDeque<(BlockHeight, Vec<CryptoHash>)>,
+    executed_receipts: Vec<CryptoHash>,
+}
+
+/// The receipts tracked by a [`ReceiptResolver`] which have not yet been executed, grouped by the
+/// block they were created in.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Snapshot {
+    receipts_by_block: Vec<(BlockHeight, Vec<(CryptoHash, CryptoHash)>)>,
+}
+
+impl Default for ReceiptResolver {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_RECEIPT_AGE_BLOCKS)
+    }
+}
+
+impl ReceiptResolver {
+    pub fn new(max_receipt_age_blocks: u64) -> Self {
+        Self {
+            max_receipt_age_blocks,
+            transaction_hashes: HashMap::new(),
+            receipts_by_block: VecDeque::new(),
+            executed_receipts: vec![],
+        }
+    }
+
+    /// Tracks the receipts created within the block. This must be called before resolving any
+    /// receipts executed in this block, as local receipts are executed in the same block that
+    /// they are created.
+    pub fn track(&mut self, streamer_message: &StreamerMessage) {
+        let block_height = streamer_message.block.header.height;
+
+        // Receipts are only executed once, so those executed in the previous block are no longer
+        // needed
+        for receipt_id in self.executed_receipts.drain(..) {
+            self.transaction_hashes.remove(&receipt_id);
+        }
+
+        while let Some((oldest_block_height, _)) = self.receipts_by_block.front() {
+            if oldest_block_height.saturating_add(self.max_receipt_age_blocks) > block_height {
+                break;
+            }
+
+            if let Some((_, receipt_ids)) = self.receipts_by_block.pop_front() {
+                for receipt_id in receipt_ids {
+                    self.transaction_hashes.remove(&receipt_id);
+                }
+            }
+        }
+
+        let mut created_receipts = vec![];
+
+        for shard in &streamer_message.shards {
+            let Some(chunk) = &shard.chunk else {
+                continue;
+            };
+
+            for transaction in &chunk.transactions {
+                for receipt_id in &transaction.outcome.execution_outcome.outcome.receipt_ids {
+                    self.transaction_hashes
+                        .insert(*receipt_id, transaction.transaction.hash);
+                    created_receipts.push(*receipt_id);
+                }
+            }
+        }
+
+        for shard in &streamer_message.shards {
+            for outcome in &shard.receipt_execution_outcomes {
+                let receipt_id = outcome.receipt.receipt_id;
+                self.executed_receipts.push(receipt_id);
+
+                let Some(transaction_hash) = self.transaction_hashes.get(&receipt_id).copied()
+                else {
+                    continue;
+                };
+
+                for child_receipt_id in &outcome.execution_outcome.outcome.receipt_ids {
+                    self.transaction_hashes
+                        .insert(*child_receipt_id, transaction_hash);
+                    created_receipts.push(*child_receipt_id);
+                }
+            }
+        }
+
+        self.receipts_by_block
+            .push_back((block_height, created_receipts));
+    }
+
+    pub fn resolve(&self, receipt_id: &CryptoHash) -> Option<CryptoHash> {
+        self.transaction_hashes.get(receipt_id).copied()
+    }
+
+    pub fn last_tracked_block(&self) -> Option<BlockHeight> {
+        self.receipts_by_block
+            .back()
+            .map(|(block_height, _)| *block_height)
+    }
+
+    /// Serializes the receipts which can still be executed after the last tracked block, so that
+    /// tracking can be resumed from the following block via [`ReceiptResolver::from_snapshot`].
+    pub fn to_snapshot(&self) -> serde_json::Result<String> {
+        let executed_receipts: HashSet<&CryptoHash> = self.executed_receipts.iter().collect();
+
+        let receipts_by_block = self
+            .receipts_by_block
+            .iter()
+            .map(|(block_height, receipt_ids)| {
+                let receipts = receipt_ids
+                    .iter()
+                    .filter(|receipt_id| !executed_receipts.contains(receipt_id))
+                    .filter_map(|receipt_id| {
+                        self.transaction_hashes
+                            .get(receipt_id)
+                            .map(|transaction_hash| (*receipt_id, *transaction_hash))
+                    })
+                    .collect();
+
+                (*block_height, receipts)
+            })
+            .collect();
+
+        serde_json::to_string(&Snapshot { receipts_by_block })
+    }
+
+    pub fn from_snapshot(snapshot: &str) -> serde_json::Result<Self> {
+        let snapshot: Snapshot = serde_json::from_str(snapshot)?;
+        let mut receipt_resolver = Self::default();
+
+        for (block_height, receipts) in snapshot.receipts_by_block {
+            let mut receipt_ids = Vec::with_capacity(receipts.len());
+
+            for (receipt_id, transaction_hash) in receipts {
+                receipt_resolver
+                    .transaction_hashes
+                    .insert(receipt_id, transaction_hash);
+                receipt_ids.push(receipt_id);
+            }
+
+            receipt_resolver
+                .receipts_by_block
+                .push_back((block_height, receipt_ids));
+        }
+
+        Ok(receipt_resolver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_receipts_created_within_the_same_block() {
+        let mut receipt_resolver = ReceiptResolver::default();
+
+        receipt_resolver.track(&crate::test_utils::get_streamer_message(93085141));
+
+        assert_eq!(
+            receipt_resolver
+                .resolve(
+                    &"DwVQmRqaeHiMwRESakJhp2jdYbq5ATLwa5BdKQtaek8n"
+                        .parse()
+                        .unwrap()
+                )
+                .unwrap()
+                .to_string(),
+            "HwDUCUs3zts1s13UJaSH8Qbzj8fyvgURAYkaPH2KKq2o"
+        );
+    }
+
+    #[test]
+    fn resolves_receipts_across_blocks() {
+        let mut receipt_resolver = ReceiptResolver::default();
+
+        receipt_resolver.track(&crate::test_utils::get_streamer_message(107503704));
+        receipt_resolver.track(&crate::test_utils::get_streamer_message(107503705));
+
+        assert_eq!(
+            receipt_resolver
+                .resolve(
+                    &"7a4CaSeGDwoXFBVxarqK2Y4ZFDtaboEV6M3Y3XuaYW5M"
+                        .parse()
+                        .unwrap()
+                )
+                .unwrap()
+                .to_string(),
+            "BpoRax4dyMgtS2yfNg8go71mnR72baEx2GxbGood7wgH"
+        );
+    }
+
+    #[test]
+    fn forgets_executed_receipts() {
+        // Receipts are never too old, so only those executed are forgotten
+        let mut receipt_resolver = ReceiptResolver::new(u64::MAX);
+        let executed_receipt_id = "DwVQmRqaeHiMwRESakJhp2jdYbq5ATLwa5BdKQtaek8n"
+            .parse()
+            .unwrap();
+        let pending_receipt_id = "BcG3MXNSMFBdaKuwc8enhXBXTK4P9WohdKQTa1Myu8jR"
+            .parse()
+            .unwrap();
+
+        receipt_resolver.track(&crate::test_utils::get_streamer_message(93085141));
+        assert!(receipt_resolver.resolve(&executed_receipt_id).is_some());
+        assert!(receipt_resolver.resolve(&pending_receipt_id).is_some());
+
+        receipt_resolver.track(&crate::test_utils::get_streamer_message(107503704));
+        assert!(receipt_resolver.resolve(&executed_receipt_id).is_none());
+        assert!(receipt_resolver.resolve(&pending_receipt_id).is_some());
+    }
+
+    #[test]
+    fn forgets_receipts_older_than_max_age() {
+        let mut receipt_resolver = ReceiptResolver::default();
+        let pending_receipt_id = "BcG3MXNSMFBdaKuwc8enhXBXTK4P9WohdKQTa1Myu8jR"
+            .parse()
+            .unwrap();
+
+        receipt_resolver.track(&crate::test_utils::get_streamer_message(93085141));
+        assert!(receipt_resolver.resolve(&pending_receipt_id).is_some());
+
+        receipt_resolver.track(&crate::test_utils::get_streamer_message(107503704));
+        assert!(receipt_resolver.resolve(&pending_receipt_id).is_none());
+    }
+
+    #[test]
+    fn resolves_receipts_tracked_before_snapshot() {
+        let mut receipt_resolver = ReceiptResolver::default();
+        receipt_resolver.track(&crate::test_utils::get_streamer_message(107503704));
+
+        let mut receipt_resolver =
+            ReceiptResolver::from_snapshot(&receipt_resolver.to_snapshot().unwrap()).unwrap();
+
+        assert_eq!(receipt_resolver.last_tracked_block(), Some(107503704));
+
+        receipt_resolver.track(&crate::test_utils::get_streamer_message(107503705));
+
+        assert_eq!(
+            receipt_resolver
+                .resolve(
+                    &"7a4CaSeGDwoXFBVxarqK2Y4ZFDtaboEV6M3Y3XuaYW5M"
+                        .parse()
+                        .unwrap()
+                )
+                .unwrap()
+                .to_string(),
+            "BpoRax4dyMgtS2yfNg8go71mnR72baEx2GxbGood7wgH"
+        );
+    }
+
+    #[test]
+    fn omits_executed_receipts_from_snapshot() {
+        let mut receipt_resolver = ReceiptResolver::default();
+        receipt_resolver.track(&crate::test_utils::get_streamer_message(93085141));
+
+        let receipt_resolver =
+            ReceiptResolver::from_snapshot(&receipt_resolver.to_snapshot().unwrap()).unwrap();
+
+        assert!(receipt_resolver
+            .resolve(
+                &"DwVQmRqaeHiMwRESakJhp2jdYbq5ATLwa5BdKQtaek8n"
+                    .parse()
+                    .unwrap()
+            )
+            .is_none());
+        assert!(receipt_resolver
+            .resolve(
+                &"BcG3MXNSMFBdaKuwc8enhXBXTK4P9WohdKQTa1Myu8jR"
+                    .parse()
+                    .unwrap()
+            )
+            .is_some());
+    }
+
+    #[test]
+    fn does_not_resolve_unknown_receipts() {
+        let mut receipt_resolver = ReceiptResolver::default();
+
+        receipt_resolver.track(&crate::test_utils::get_streamer_message(107503705));
+
+        assert!(receipt_resolver
+            .resolve(
+                &"7a4CaSeGDwoXFBVxarqK2Y4ZFDtaboEV6M3Y3XuaYW5M"
+                    .parse()
+                    .unwrap()
+            )
+            .is_none());
+    }
+}
@@ -1,16 +1,47 @@
 #![cfg_attr(test, allow(dead_code))]
 
+use std::collections::HashMap;
 use std::fmt::Debug;
 
-use redis::{aio::ConnectionManager, RedisError, ToRedisArgs};
+use redis::{aio::ConnectionManager, FromRedisValue, RedisError, ToRedisArgs};
 
 #[cfg(test)]
 pub use MockRedisClientImpl as RedisClient;
 #[cfg(not(test))]
 pub use RedisClientImpl as RedisClient;
 
+/// Adds a block to a stream under a deterministic ID, skipping blocks which have already been
+/// added, and records it as the last published block. Streams migrated from the legacy
+/// coordinator contain timestamp IDs, which are far greater than any block height, so blocks are
+/// added to those under generated IDs instead.
+const XADD_BLOCK_SCRIPT: &str = r#"
+local result = redis.pcall('XADD', KEYS[1], ARGV[1] .. '-0', unpack(ARGV, 2))
+if type(result) == 'table' and result.err then
+    if not string.find(result.err, 'equal or smaller', 1, true) then
+        return redis.error_reply(result.err)
+    end
+
+    local info = redis.call('XINFO', 'STREAM', KEYS[1])
+    local last_generated_id = '0'
+    for i = 1, #info, 2 do
+        if info[i] == 'last-generated-id' then
+            last_generated_id = info[i + 1]
+        end
+    end
+
+    if tonumber(string.match(last_generated_id, '^%d+')) < 1000000000000 then
+        return 0
+    end
+
+    redis.call('XADD', KEYS[1], '*', unpack(ARGV, 2))
+end
+redis.call('SET', KEYS[2], ARGV[1])
+return 1
+"#;
+
 pub struct RedisClientImpl {
     connection: ConnectionManager,
+    xadd_block_script: redis::Script,
 }
 
 #[cfg_attr(test, mockall::automock)]
@@ -20,26 +51,45 @@ impl RedisClientImpl {
             .get_tokio_connection_manager()
             .await?;
 
-        Ok(Self { connection })
+        Ok(Self {
+            connection,
+            xadd_block_script: redis::Script::new(XADD_BLOCK_SCRIPT),
+        })
     }
 
-    pub async fn xadd<T, U>(&self, stream_key: T, fields: &[(String, U)]) -> Result<(), RedisError>
-    where
-        T: ToRedisArgs + Debug + Send + Sync + 'static,
-        U: ToRedisArgs + Debug + Send + Sync + 'static,
-    {
-        tracing::debug!("XADD: {:?}, {:?}", stream_key, fields);
+    /// Adds an entry with the ID `<block_height>-0` and sets `last_published_block_key` to the
+    /// block height, atomically. Blocks at or below the last entry ID of the stream are skipped,
+    /// so that re-publishing a block after a restart does not duplicate it. Returns whether the
+    /// entry was added.
+    pub async fn xadd_block(
+        &self,
+        stream_key: String,
+        last_published_block_key: String,
+        block_height: u64,
+        fields: &[(String, String)],
+    ) -> Result<bool, RedisError> {
+        tracing::debug!("XADD: {:?}, {}-0, {:?}", stream_key, block_height, fields);
 
-        let mut cmd = redis::cmd("XADD");
-        cmd.arg(stream_key).arg("*");
+        let mut invocation = self.xadd_block_script.key(stream_key);
+        invocation.key(last_published_block_key).arg(block_height);
 
         for (field, value) in fields {
-            cmd.arg(field).arg(value);
+            invocation.arg(field).arg(value);
         }
 
-        cmd.query_async(&mut self.connection.clone()).await?;
+        invocation.invoke_async(&mut self.connection.clone()).await
+    }
 
-        Ok(())
+    pub async fn xlen<T>(&self, stream_key: T) -> Result<u64, RedisError>
+    where
+        T: ToRedisArgs + Debug + Send + Sync + 'static,
+    {
+        tracing::debug!("XLEN: {:?}", stream_key);
+
+        let mut cmd = redis::cmd("XLEN");
+        cmd.arg(stream_key);
+
+        cmd.query_async(&mut self.connection.clone()).await
     }
 
     pub async fn set<T, U>(&self, key: T, value: U) -> Result<(), RedisError>
@@ -56,4 +106,74 @@ impl RedisClientImpl {
 
         Ok(())
     }
+
+    pub async fn set_ex<T, U>(&self, key: T, value: U, seconds: u64) -> Result<(), RedisError>
+    where
+        T: ToRedisArgs + Debug + Send + Sync + 'static,
+        U: ToRedisArgs + Debug + Send + Sync + 'static,
+    {
+        tracing::debug!("SET EX: {:?}, {}", key, seconds);
+
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(key).arg(value).arg("EX").arg(seconds);
+
+        cmd.query_async(&mut self.connection.clone()).await?;
+
+        Ok(())
+    }
+
+    pub async fn get<T, U>(&self, key: T) -> Result<Option<U>, RedisError>
+    where
+        T: ToRedisArgs + Debug + Send + Sync + 'static,
+        U: FromRedisValue + Debug + Send + Sync + 'static,
+    {
+        tracing::debug!("GET: {:?}", key);
+
+        let mut cmd = redis::cmd("GET");
+        cmd.arg(key);
+
+        cmd.query_async(&mut self.connection.clone()).await
+    }
+
+    pub async fn hset<T, U>(&self, key: T, field: String, value: U) -> Result<(), RedisError>
+    where
+        T: ToRedisArgs + Debug + Send + Sync + 'static,
+        U: ToRedisArgs + Debug + Send + Sync + 'static,
+    {
+        tracing::debug!("HSET: {:?}, {}", key, field);
+
+        let mut cmd = redis::cmd("HSET");
+        cmd.arg(key).arg(field).arg(value);
+
+        cmd.query_async(&mut self.connection.clone()).await?;
+
+        Ok(())
+    }
+
+    /// Removes the field from the hash, returning whether it existed.
+    pub async fn hdel<T>(&self, key: T, field: String) -> Result<bool, RedisError>
+    where
+        T: ToRedisArgs + Debug + Send + Sync + 'static,
+    {
+        tracing::debug!("HDEL: {:?}, {}", key, field);
+
+        let mut cmd = redis::cmd("HDEL");
+        cmd.arg(key).arg(field);
+
+        let removed: u64 = cmd.query_async(&mut self.connection.clone()).await?;
+
+        Ok(removed > 0)
+    }
+
+    pub async fn hgetall<T>(&self, key: T) -> Result<HashMap<String, Vec<u8>>, RedisError>
+    where
+        T: ToRedisArgs + Debug + Send + Sync + 'static,
+    {
+        tracing::debug!("HGETALL: {:?}", key);
+
+        let mut cmd = redis::cmd("HGETALL");
+        cmd.arg(key);
+
+        cmd.query_async(&mut self.connection.clone()).await
+    }
 }
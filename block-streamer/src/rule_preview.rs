@@ -0,0 +1,423 @@
+use anyhow::Context;
+use futures::{StreamExt, TryStreamExt};
+use near_lake_framework::near_indexer_primitives;
+
+use crate::block_stream::{build_index_queries, list_index_files, MAX_PREFETCHED_CANDIDATE_BLOCKS};
+use crate::receipt_resolver::ReceiptResolver;
+use crate::rules::compiled_rule::CompiledRule;
+use crate::rules::types::{ChainId, IndexerRuleMatch};
+use registry_types::Rule;
+
+/// The maximum number of blocks fetched from NEAR Lake for a single preview. Rules which cannot be
+/// narrowed via the Delta Lake index must be checked against every block in the range.
+pub const MAX_LAKE_PREVIEW_BLOCKS: u64 = 1_000;
+/// The maximum number of candidate blocks fetched from Delta Lake for a single preview.
+pub const MAX_INDEX_PREVIEW_CANDIDATES: usize = 1_000;
+
+#[derive(Debug)]
+pub struct PreviewedBlock {
+    pub block_height: near_indexer_primitives::types::BlockHeight,
+    pub matches: Vec<IndexerRuleMatch>,
+}
+
+#[derive(Debug)]
+pub struct RulePreview {
+    pub blocks: Vec<PreviewedBlock>,
+    /// Height of the last block checked against the rule. This precedes the end of the range
+    /// when `limit` matching blocks were found first, or too many blocks needed to be fetched
+    /// from Delta Lake or NEAR Lake.
+    pub last_block_height: near_indexer_primitives::types::BlockHeight,
+}
+
+/// Finds up to `limit` blocks matching the rule between `start_block_height` and
+/// `end_block_height` inclusive. Candidate blocks are taken from the Delta Lake index where
+/// possible, and the remaining blocks are fetched from NEAR Lake.
+pub async fn preview_rule(
+    rule: &Rule,
+    chain_id: &ChainId,
+    start_block_height: near_indexer_primitives::types::BlockHeight,
+    end_block_height: near_indexer_primitives::types::BlockHeight,
+    limit: usize,
+    delta_lake_client: &crate::delta_lake_client::DeltaLakeClient,
+    lake_s3_config: aws_sdk_s3::Config,
+) -> anyhow::Result<RulePreview> {
+    let compiled_rule = CompiledRule::from(rule);
+    let mut rule_preview = RulePreview {
+        blocks: vec![],
+        last_block_height: start_block_height.saturating_sub(1),
+    };
+
+    let last_indexed_block = delta_lake_client
+        .get_latest_block_metadata()
+        .await?
+        .last_indexed_block
+        .parse::<near_indexer_primitives::types::BlockHeight>()
+        .context("Failed to parse Delta Lake metadata")?;
+
+    if let Some(index_queries) = build_index_queries(rule) {
+        if start_block_height <= last_indexed_block {
+            let index_end_block_height = end_block_height.min(last_indexed_block);
+
+            let checked_index_range = preview_delta_lake_blocks(
+                &compiled_rule,
+                chain_id,
+                start_block_height,
+                index_end_block_height,
+                limit,
+                index_queries,
+                delta_lake_client,
+                &mut rule_preview,
+            )
+            .await?;
+
+            if !checked_index_range {
+                return Ok(rule_preview);
+            }
+
+            rule_preview.last_block_height = index_end_block_height;
+        }
+    }
+
+    if rule_preview.last_block_height < end_block_height {
+        preview_near_lake_blocks(
+            &compiled_rule,
+            chain_id,
+            rule_preview.last_block_height + 1,
+            end_block_height,
+            limit,
+            lake_s3_config,
+            &mut rule_preview,
+        )
+        .await?;
+    }
+
+    Ok(rule_preview)
+}
+
+/// Checks the candidate blocks from the index against the rule, returning whether every candidate
+/// up to `end_block_height` was checked, rather than stopping at `limit` matching blocks or
+/// `MAX_INDEX_PREVIEW_CANDIDATES` candidates.
+#[allow(clippy::too_many_arguments)]
+async fn preview_delta_lake_blocks(
+    compiled_rule: &CompiledRule,
+    chain_id: &ChainId,
+    start_block_height: near_indexer_primitives::types::BlockHeight,
+    end_block_height: near_indexer_primitives::types::BlockHeight,
+    limit: usize,
+    index_queries: Vec<crate::block_stream::IndexQuery>,
+    delta_lake_client: &crate::delta_lake_client::DeltaLakeClient,
+    rule_preview: &mut RulePreview,
+) -> anyhow::Result<bool> {
+    let index_files =
+        list_index_files(delta_lake_client, start_block_height, index_queries).await?;
+
+    let mut block_height_batches =
+        delta_lake_client.stream_matching_block_heights(start_block_height, index_files);
+    let mut candidates_fetched = 0;
+
+    while let Some(block_height_batch) = block_height_batches.try_next().await? {
+        let batch_size = block_height_batch.block_heights.len();
+        let mut block_heights: Vec<_> = block_height_batch
+            .block_heights
+            .into_iter()
+            .filter(|block_height| *block_height <= end_block_height)
+            .collect();
+        let reached_end = block_heights.len() < batch_size;
+
+        let remaining_candidates = MAX_INDEX_PREVIEW_CANDIDATES - candidates_fetched;
+        let reached_candidate_limit = block_heights.len() > remaining_candidates;
+        block_heights.truncate(remaining_candidates);
+        candidates_fetched += block_heights.len();
+
+        let mut streamer_messages = futures::stream::iter(block_heights)
+            .map(|block_height| async move {
+                delta_lake_client
+                    .get_streamer_message(block_height)
+                    .await
+                    .map(|streamer_message| (block_height, streamer_message))
+                    .context("Failed to fetch candidate block")
+            })
+            .buffered(MAX_PREFETCHED_CANDIDATE_BLOCKS);
+
+        while let Some((block_height, streamer_message)) = streamer_messages.try_next().await? {
+            let mut receipt_resolver = ReceiptResolver::default();
+            receipt_resolver.track(&streamer_message);
+
+            rule_preview.last_block_height = block_height;
+
+            let matches = crate::rules::reduce_indexer_rule_matches(
+                compiled_rule,
+                &streamer_message,
+                &receipt_resolver,
+                chain_id.clone(),
+            );
+
+            if !matches.is_empty() {
+                rule_preview.blocks.push(PreviewedBlock {
+                    block_height,
+                    matches,
+                });
+
+                if rule_preview.blocks.len() >= limit {
+                    return Ok(false);
+                }
+            }
+        }
+
+        if reached_candidate_limit {
+            return Ok(false);
+        }
+
+        if reached_end {
+            return Ok(true);
+        }
+    }
+
+    Ok(true)
+}
+
+async fn preview_near_lake_blocks(
+    compiled_rule: &CompiledRule,
+    chain_id: &ChainId,
+    start_block_height: near_indexer_primitives::types::BlockHeight,
+    end_block_height: near_indexer_primitives::types::BlockHeight,
+    limit: usize,
+    lake_s3_config: aws_sdk_s3::Config,
+    rule_preview: &mut RulePreview,
+) -> anyhow::Result<()> {
+    let lake_config = match &chain_id {
+        ChainId::Mainnet => near_lake_framework::LakeConfigBuilder::default().mainnet(),
+        ChainId::Testnet => near_lake_framework::LakeConfigBuilder::default().testnet(),
+    }
+    .s3_config(lake_s3_config)
+    .start_block_height(start_block_height)
+    .build()
+    .context("Failed to build lake config")?;
+
+    let mut receipt_resolver = ReceiptResolver::default();
+    let mut blocks_fetched = 0;
+
+    let (lake_handle, mut stream) = near_lake_framework::streamer(lake_config);
+
+    while let Some(streamer_message) = stream.recv().await {
+        let block_height = streamer_message.block.header.height;
+
+        if block_height > end_block_height {
+            return Ok(());
+        }
+
+        blocks_fetched += 1;
+        rule_preview.last_block_height = block_height;
+
+        receipt_resolver.track(&streamer_message);
+
+        let matches = crate::rules::reduce_indexer_rule_matches(
+            compiled_rule,
+            &streamer_message,
+            &receipt_resolver,
+            chain_id.clone(),
+        );
+
+        if !matches.is_empty() {
+            rule_preview.blocks.push(PreviewedBlock {
+                block_height,
+                matches,
+            });
+
+            if rule_preview.blocks.len() >= limit {
+                return Ok(());
+            }
+        }
+
+        if block_height == end_block_height || blocks_fetched >= MAX_LAKE_PREVIEW_BLOCKS {
+            return Ok(());
+        }
+    }
+
+    // The stream only ends before the range does when the lake task fails
+    lake_handle
+        .await
+        .context("NEAR Lake task panicked")?
+        .context("Failed to stream blocks from NEAR Lake")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn previews_candidate_blocks_from_index_and_lake() {
+        let mut mock_delta_lake_client = crate::delta_lake_client::DeltaLakeClient::default();
+        mock_delta_lake_client
+            .expect_get_latest_block_metadata()
+            .returning(|| {
+                Ok(crate::delta_lake_client::LatestBlockMetadata {
+                    last_indexed_block: "107503703".to_string(),
+                    processed_at_utc: "".to_string(),
+                    first_indexed_block: "".to_string(),
+                    last_indexed_block_date: "".to_string(),
+                    first_indexed_block_date: "".to_string(),
+                })
+            });
+        mock_delta_lake_client
+            .expect_list_index_files()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_delta_lake_client
+            .expect_stream_matching_block_heights()
+            .returning(|_, _| {
+                futures::stream::iter(vec![Ok(crate::delta_lake_client::BlockHeightBatch {
+                    date: chrono::NaiveDate::from_ymd_opt(2023, 5, 16).unwrap(),
+                    index_file_count: 1,
+                    block_heights: vec![93085141],
+                })])
+                .boxed()
+            });
+        mock_delta_lake_client
+            .expect_get_streamer_message()
+            .returning(|block_height| Ok(crate::test_utils::get_streamer_message(block_height)));
+
+        let rule = Rule::ActionFunctionCall {
+            affected_account_id: "app.nearcrowd.near".to_string(),
+            status: registry_types::Status::Any,
+            affected_account_role: registry_types::AffectedAccountRole::Any,
+            function: "approve_solution".to_string(),
+            args_predicates: vec![],
+            min_deposit: None,
+            min_gas: None,
+        };
+
+        let rule_preview = preview_rule(
+            &rule,
+            &ChainId::Mainnet,
+            93085000,
+            107503704,
+            10,
+            &mock_delta_lake_client,
+            crate::test_utils::create_mock_lake_s3_config(&[107503704]),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            rule_preview
+                .blocks
+                .iter()
+                .map(|block| block.block_height)
+                .collect::<Vec<_>>(),
+            vec![93085141]
+        );
+        assert_eq!(rule_preview.last_block_height, 107503704);
+    }
+
+    #[tokio::test]
+    async fn stops_previewing_at_limit() {
+        let mut mock_delta_lake_client = crate::delta_lake_client::DeltaLakeClient::default();
+        mock_delta_lake_client
+            .expect_get_latest_block_metadata()
+            .returning(|| {
+                Ok(crate::delta_lake_client::LatestBlockMetadata {
+                    last_indexed_block: "107503705".to_string(),
+                    processed_at_utc: "".to_string(),
+                    first_indexed_block: "".to_string(),
+                    last_indexed_block_date: "".to_string(),
+                    first_indexed_block_date: "".to_string(),
+                })
+            });
+        mock_delta_lake_client
+            .expect_list_index_files()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_delta_lake_client
+            .expect_stream_matching_block_heights()
+            .returning(|_, _| {
+                futures::stream::iter(vec![Ok(crate::delta_lake_client::BlockHeightBatch {
+                    date: chrono::NaiveDate::from_ymd_opt(2023, 12, 9).unwrap(),
+                    index_file_count: 1,
+                    block_heights: vec![107503704, 107503705],
+                })])
+                .boxed()
+            });
+        mock_delta_lake_client
+            .expect_get_streamer_message()
+            .returning(|block_height| Ok(crate::test_utils::get_streamer_message(block_height)));
+
+        let rule = Rule::ActionAny {
+            affected_account_id: "token.sweat".to_string(),
+            status: registry_types::Status::Any,
+            affected_account_role: registry_types::AffectedAccountRole::Any,
+        };
+
+        let rule_preview = preview_rule(
+            &rule,
+            &ChainId::Mainnet,
+            107503704,
+            107503705,
+            1,
+            &mock_delta_lake_client,
+            crate::test_utils::create_mock_lake_s3_config(&[]),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(rule_preview.blocks.len(), 1);
+        assert_eq!(rule_preview.last_block_height, 107503704);
+    }
+
+    #[tokio::test]
+    async fn stops_previewing_at_candidate_limit() {
+        let mut mock_delta_lake_client = crate::delta_lake_client::DeltaLakeClient::default();
+        mock_delta_lake_client
+            .expect_get_latest_block_metadata()
+            .returning(|| {
+                Ok(crate::delta_lake_client::LatestBlockMetadata {
+                    last_indexed_block: "107503705".to_string(),
+                    processed_at_utc: "".to_string(),
+                    first_indexed_block: "".to_string(),
+                    last_indexed_block_date: "".to_string(),
+                    first_indexed_block_date: "".to_string(),
+                })
+            });
+        mock_delta_lake_client
+            .expect_list_index_files()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_delta_lake_client
+            .expect_stream_matching_block_heights()
+            .returning(|_, _| {
+                futures::stream::iter(vec![Ok(crate::delta_lake_client::BlockHeightBatch {
+                    date: chrono::NaiveDate::from_ymd_opt(2023, 12, 9).unwrap(),
+                    index_file_count: 1,
+                    block_heights: (107000000..107001500).collect(),
+                })])
+                .boxed()
+            });
+        // Candidates which do not match the rule, so that the preview only stops at the
+        // candidate limit
+        let streamer_message = crate::test_utils::get_streamer_message(107503704);
+        mock_delta_lake_client
+            .expect_get_streamer_message()
+            .returning(move |_| Ok(streamer_message.clone()))
+            .times(MAX_INDEX_PREVIEW_CANDIDATES);
+
+        let rule = Rule::ActionAny {
+            affected_account_id: "queryapi.dataplatform.near".to_string(),
+            status: registry_types::Status::Success,
+            affected_account_role: registry_types::AffectedAccountRole::Any,
+        };
+
+        let rule_preview = preview_rule(
+            &rule,
+            &ChainId::Mainnet,
+            107000000,
+            107503705,
+            10,
+            &mock_delta_lake_client,
+            crate::test_utils::create_mock_lake_s3_config(&[]),
+        )
+        .await
+        .unwrap();
+
+        assert!(rule_preview.blocks.is_empty());
+        assert_eq!(rule_preview.last_block_height, 107000999);
+    }
+}
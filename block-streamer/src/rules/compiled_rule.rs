@@ -0,0 +1,237 @@
+use std::collections::HashSet;
+
+use registry_types::{ActionKind, AffectedAccountRole, Rule, StateChangeKind, Status};
+use wildmatch::WildMatch;
+
+use crate::rules::data_predicate::CompiledDataPredicate;
+
+/// A single wildcard pattern, compiled up front so that patterns without wildcards are compared
+/// directly rather than through `WildMatch`.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Any,
+    Exact(String),
+    Wildcard(WildMatch),
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Self {
+        match pattern {
+            "*" => Self::Any,
+            pattern if is_wildcard(pattern) => Self::Wildcard(WildMatch::new(pattern)),
+            pattern => Self::Exact(pattern.to_string()),
+        }
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Exact(pattern) => pattern == value,
+            Self::Wildcard(wildcard) => wildcard.matches(value),
+        }
+    }
+}
+
+/// A comma separated list of account patterns. Exact account IDs are looked up in a set so that
+/// rules listing many accounts do not scale linearly per receipt.
+#[derive(Debug, Clone)]
+pub struct AccountPattern {
+    is_any: bool,
+    exact: HashSet<String>,
+    wildcards: Vec<WildMatch>,
+}
+
+impl AccountPattern {
+    pub fn new(account_ids: &str) -> Self {
+        let mut account_pattern = Self {
+            is_any: false,
+            exact: HashSet::new(),
+            wildcards: vec![],
+        };
+
+        for account_id in account_ids.split(',').map(str::trim) {
+            match account_id {
+                "*" => account_pattern.is_any = true,
+                account_id if is_wildcard(account_id) => {
+                    account_pattern.wildcards.push(WildMatch::new(account_id))
+                }
+                account_id => {
+                    account_pattern.exact.insert(account_id.to_string());
+                }
+            }
+        }
+
+        account_pattern
+    }
+
+    pub fn matches(&self, account_id: &str) -> bool {
+        self.is_any
+            || self.exact.contains(account_id)
+            || self
+                .wildcards
+                .iter()
+                .any(|wildcard| wildcard.matches(account_id))
+    }
+}
+
+pub fn is_wildcard(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// A `Rule` prepared for repeated evaluation: account lists are split, wildcards and data predicate
+/// patterns compiled and amounts parsed once, rather than for every receipt of every block.
+#[derive(Debug, Clone)]
+pub enum CompiledRule {
+    ActionAny {
+        affected_account: AccountPattern,
+        affected_account_role: AffectedAccountRole,
+        status: Status,
+    },
+    ActionFunctionCall {
+        affected_account: AccountPattern,
+        affected_account_role: AffectedAccountRole,
+        status: Status,
+        function: Pattern,
+        args_predicates: Vec<CompiledDataPredicate>,
+        min_deposit: Option<u128>,
+        min_gas: Option<u64>,
+    },
+    Event {
+        contract_account: AccountPattern,
+        event: Pattern,
+        standard: Pattern,
+        version: Pattern,
+        data_predicate: Option<CompiledDataPredicate>,
+    },
+    ActionKind {
+        affected_account: AccountPattern,
+        affected_account_role: AffectedAccountRole,
+        status: Status,
+        action_kinds: Vec<ActionKind>,
+        min_amount: Option<u128>,
+        max_amount: Option<u128>,
+    },
+    StateChange {
+        affected_account: AccountPattern,
+        change_kinds: Vec<StateChangeKind>,
+    },
+    All {
+        rules: Vec<CompiledRule>,
+    },
+    Any {
+        rules: Vec<CompiledRule>,
+    },
+    Not {
+        rule: Box<CompiledRule>,
+    },
+}
+
+impl From<&Rule> for CompiledRule {
+    fn from(rule: &Rule) -> Self {
+        match rule {
+            Rule::ActionAny {
+                affected_account_id,
+                status,
+                affected_account_role,
+            } => Self::ActionAny {
+                affected_account: AccountPattern::new(affected_account_id),
+                affected_account_role: affected_account_role.clone(),
+                status: status.clone(),
+            },
+            Rule::ActionFunctionCall {
+                affected_account_id,
+                status,
+                affected_account_role,
+                function,
+                args_predicates,
+                min_deposit,
+                min_gas,
+            } => Self::ActionFunctionCall {
+                affected_account: AccountPattern::new(affected_account_id),
+                affected_account_role: affected_account_role.clone(),
+                status: status.clone(),
+                function: Pattern::new(function),
+                args_predicates: args_predicates
+                    .iter()
+                    .map(CompiledDataPredicate::from)
+                    .collect(),
+                min_deposit: parse_amount(min_deposit.as_deref()),
+                min_gas: *min_gas,
+            },
+            Rule::Event {
+                contract_account_id,
+                standard,
+                version,
+                event,
+                data_predicate,
+            } => Self::Event {
+                contract_account: AccountPattern::new(contract_account_id),
+                event: Pattern::new(event),
+                standard: Pattern::new(standard),
+                version: Pattern::new(version),
+                data_predicate: data_predicate.as_ref().map(CompiledDataPredicate::from),
+            },
+            Rule::ActionKind {
+                affected_account_id,
+                status,
+                affected_account_role,
+                action_kinds,
+                min_amount,
+                max_amount,
+            } => Self::ActionKind {
+                affected_account: AccountPattern::new(affected_account_id),
+                affected_account_role: affected_account_role.clone(),
+                status: status.clone(),
+                action_kinds: action_kinds.clone(),
+                min_amount: parse_amount(min_amount.as_deref()),
+                max_amount: parse_amount(max_amount.as_deref()),
+            },
+            Rule::StateChange {
+                affected_account_id,
+                change_kinds,
+            } => Self::StateChange {
+                affected_account: AccountPattern::new(affected_account_id),
+                change_kinds: change_kinds.clone(),
+            },
+            Rule::All { rules } => Self::All {
+                rules: rules.iter().map(Self::from).collect(),
+            },
+            Rule::Any { rules } => Self::Any {
+                rules: rules.iter().map(Self::from).collect(),
+            },
+            Rule::Not { rule } => Self::Not {
+                rule: Box::new(Self::from(rule.as_ref())),
+            },
+        }
+    }
+}
+
+/// Amounts are validated on registration, so any which fail to parse are treated as absent.
+fn parse_amount(amount: Option<&str>) -> Option<u128> {
+    amount.and_then(|amount| amount.parse::<u128>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_patterns() {
+        assert!(Pattern::new("*").matches("ft_transfer"));
+        assert!(Pattern::new("ft_*").matches("ft_transfer"));
+        assert!(!Pattern::new("ft_*").matches("nft_transfer"));
+        assert!(Pattern::new("ft_transfer").matches("ft_transfer"));
+        assert!(!Pattern::new("ft_transfer").matches("ft_transfer_call"));
+    }
+
+    #[test]
+    fn matches_account_lists() {
+        let account_pattern = AccountPattern::new("token.sweat, *.kaiching ,app.nearcrowd.near");
+
+        assert!(account_pattern.matches("token.sweat"));
+        assert!(account_pattern.matches("app.nearcrowd.near"));
+        assert!(account_pattern.matches("earn.kaiching"));
+        assert!(!account_pattern.matches("sweat"));
+        assert!(!account_pattern.matches("nearcrowd.near"));
+    }
+}
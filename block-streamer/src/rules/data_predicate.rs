@@ -0,0 +1,228 @@
+use std::cmp::Ordering;
+
+use registry_types::{ComparisonOperator, DataPredicate};
+use serde_json::Value;
+
+use crate::rules::compiled_rule::Pattern;
+
+/// A `DataPredicate` prepared for repeated evaluation, so that the pattern of `Matches`
+/// comparisons is compiled once rather than for every value compared.
+#[derive(Debug, Clone)]
+pub struct CompiledDataPredicate {
+    path: String,
+    operator: ComparisonOperator,
+    value: String,
+    pattern: Pattern,
+}
+
+impl From<&DataPredicate> for CompiledDataPredicate {
+    fn from(data_predicate: &DataPredicate) -> Self {
+        Self {
+            path: data_predicate.path.clone(),
+            operator: data_predicate.operator.clone(),
+            value: data_predicate.value.clone(),
+            pattern: Pattern::new(&data_predicate.value),
+        }
+    }
+}
+
+/// Evaluates the predicate against the JSON document, matching when any of the values found at
+/// the predicate path satisfy the comparison.
+pub fn matches(data_predicate: &CompiledDataPredicate, document: &Value) -> bool {
+    resolve_path(&data_predicate.path, document)
+        .into_iter()
+        .any(|value| compare(data_predicate, value))
+}
+
+fn resolve_path<'a>(path: &str, document: &'a Value) -> Vec<&'a Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .fold(vec![document], |values, segment| {
+            let (key, indexes) = match segment.split_once('[') {
+                Some((key, indexes)) => (key, Some(indexes)),
+                None => (segment, None),
+            };
+
+            let mut values: Vec<&Value> = if key.is_empty() {
+                values
+            } else {
+                values
+                    .into_iter()
+                    .filter_map(|value| value.get(key))
+                    .collect()
+            };
+
+            // `indexes` is everything after the first `[`, e.g. `]` or `0][]`
+            for index in indexes.into_iter().flat_map(|indexes| indexes.split('[')) {
+                let index = index.trim_end_matches(']');
+
+                values = if index.is_empty() {
+                    values
+                        .into_iter()
+                        .filter_map(Value::as_array)
+                        .flatten()
+                        .collect()
+                } else {
+                    let Ok(index) = index.parse::<usize>() else {
+                        return vec![];
+                    };
+
+                    values
+                        .into_iter()
+                        .filter_map(|value| value.get(index))
+                        .collect()
+                };
+            }
+
+            values
+        })
+}
+
+fn compare(data_predicate: &CompiledDataPredicate, value: &Value) -> bool {
+    let actual = match value {
+        Value::String(string) => string.to_owned(),
+        value => value.to_string(),
+    };
+    let expected = data_predicate.value.as_str();
+
+    match data_predicate.operator {
+        ComparisonOperator::Equals => compare_values(&actual, expected) == Some(Ordering::Equal),
+        ComparisonOperator::NotEquals => compare_values(&actual, expected) != Some(Ordering::Equal),
+        ComparisonOperator::Matches => data_predicate.pattern.matches(&actual),
+        ComparisonOperator::GreaterThan => {
+            compare_numbers(&actual, expected) == Some(Ordering::Greater)
+        }
+        ComparisonOperator::GreaterThanOrEqual => matches!(
+            compare_numbers(&actual, expected),
+            Some(Ordering::Greater | Ordering::Equal)
+        ),
+        ComparisonOperator::LessThan => compare_numbers(&actual, expected) == Some(Ordering::Less),
+        ComparisonOperator::LessThanOrEqual => matches!(
+            compare_numbers(&actual, expected),
+            Some(Ordering::Less | Ordering::Equal)
+        ),
+    }
+}
+
+fn compare_values(actual: &str, expected: &str) -> Option<Ordering> {
+    compare_numbers(actual, expected).or(Some(actual.cmp(expected)))
+}
+
+/// Token amounts are commonly encoded as strings which exceed the precision of `f64`, so integers
+/// are compared exactly where possible.
+fn compare_numbers(actual: &str, expected: &str) -> Option<Ordering> {
+    if let (Ok(actual), Ok(expected)) = (actual.parse::<u128>(), expected.parse::<u128>()) {
+        return Some(actual.cmp(&expected));
+    }
+
+    let (Ok(actual), Ok(expected)) = (actual.parse::<f64>(), expected.parse::<f64>()) else {
+        return None;
+    };
+
+    actual.partial_cmp(&expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ft_transfer() -> Value {
+        serde_json::json!({
+            "standard": "nep141",
+            "version": "1.0.0",
+            "event": "ft_transfer",
+            "data": [
+                {
+                    "old_owner_id": "alice.near",
+                    "new_owner_id": "reward-optin.sweat",
+                    "amount": "100000000000000000"
+                },
+                {
+                    "old_owner_id": "bob.near",
+                    "new_owner_id": "carol.near",
+                    "amount": "5"
+                }
+            ]
+        })
+    }
+
+    fn predicate(path: &str, operator: ComparisonOperator, value: &str) -> CompiledDataPredicate {
+        CompiledDataPredicate::from(&DataPredicate {
+            path: path.to_string(),
+            operator,
+            value: value.to_string(),
+        })
+    }
+
+    #[test]
+    fn matches_wildcard_within_arrays() {
+        assert!(matches(
+            &predicate(
+                "data[].new_owner_id",
+                ComparisonOperator::Matches,
+                "*.sweat"
+            ),
+            &ft_transfer()
+        ));
+        assert!(!matches(
+            &predicate("data[].new_owner_id", ComparisonOperator::Matches, "*.tg"),
+            &ft_transfer()
+        ));
+    }
+
+    #[test]
+    fn matches_indexed_elements() {
+        assert!(matches(
+            &predicate(
+                "data[1].old_owner_id",
+                ComparisonOperator::Equals,
+                "bob.near"
+            ),
+            &ft_transfer()
+        ));
+        assert!(!matches(
+            &predicate(
+                "data[0].old_owner_id",
+                ComparisonOperator::Equals,
+                "bob.near"
+            ),
+            &ft_transfer()
+        ));
+    }
+
+    #[test]
+    fn compares_large_numeric_strings() {
+        assert!(matches(
+            &predicate(
+                "data[].amount",
+                ComparisonOperator::GreaterThan,
+                "99999999999999999"
+            ),
+            &ft_transfer()
+        ));
+        assert!(!matches(
+            &predicate(
+                "data[].amount",
+                ComparisonOperator::GreaterThanOrEqual,
+                "100000000000000001"
+            ),
+            &ft_transfer()
+        ));
+        assert!(matches(
+            &predicate("data[].amount", ComparisonOperator::LessThan, "10"),
+            &ft_transfer()
+        ));
+    }
+
+    #[test]
+    fn does_not_match_missing_paths() {
+        assert!(!matches(
+            &predicate("data[].memo", ComparisonOperator::NotEquals, "memo"),
+            &ft_transfer()
+        ));
+        assert!(!matches(
+            &predicate("data.amount", ComparisonOperator::Equals, "5"),
+            &ft_transfer()
+        ));
+    }
+}
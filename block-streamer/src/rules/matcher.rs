@@ -2,94 +2,218 @@ use near_lake_framework::near_indexer_primitives::{
     views::{ActionView, ExecutionStatusView, ReceiptEnumView},
     IndexerExecutionOutcomeWithReceipt,
 };
-use registry_types::{Rule, Status};
+use registry_types::{ActionKind, AffectedAccountRole, Status};
 
+use crate::rules::compiled_rule::{AccountPattern, CompiledRule, Pattern};
+use crate::rules::data_predicate::{self, CompiledDataPredicate};
 use crate::rules::types::Event;
 
 pub fn matches(
-    indexer_rule: &Rule,
+    indexer_rule: &CompiledRule,
     receipt_execution_outcome: &IndexerExecutionOutcomeWithReceipt,
 ) -> bool {
     match indexer_rule {
-        Rule::ActionAny {
-            affected_account_id,
+        CompiledRule::ActionAny {
+            affected_account,
             status,
-        } => match_action_any(affected_account_id, status, receipt_execution_outcome),
-        Rule::ActionFunctionCall {
-            affected_account_id,
-            status,
-            function,
-        } => match_action_function_call(
-            affected_account_id,
+            affected_account_role,
+        } => {
+            match_account(
+                affected_account,
+                affected_account_role,
+                receipt_execution_outcome,
+            ) && match_status(
+                status,
+                &receipt_execution_outcome.execution_outcome.outcome.status,
+            )
+        }
+        CompiledRule::ActionFunctionCall {
+            affected_account,
             status,
+            affected_account_role,
             function,
-            receipt_execution_outcome,
-        ),
-        Rule::Event {
-            contract_account_id,
+            args_predicates,
+            min_deposit,
+            min_gas,
+        } => {
+            match_account(
+                affected_account,
+                affected_account_role,
+                receipt_execution_outcome,
+            ) && match_action_function_call(
+                status,
+                function,
+                args_predicates,
+                *min_deposit,
+                *min_gas,
+                receipt_execution_outcome,
+            )
+        }
+        CompiledRule::Event {
+            contract_account,
             event,
             standard,
             version,
+            data_predicate,
         } => match_event(
-            contract_account_id,
+            contract_account,
             event,
             standard,
             version,
+            data_predicate.as_ref(),
             receipt_execution_outcome,
         ),
+        CompiledRule::ActionKind {
+            affected_account,
+            status,
+            affected_account_role,
+            action_kinds,
+            min_amount,
+            max_amount,
+        } => {
+            match_account(
+                affected_account,
+                affected_account_role,
+                receipt_execution_outcome,
+            ) && match_action_kind(
+                status,
+                action_kinds,
+                *min_amount,
+                *max_amount,
+                receipt_execution_outcome,
+            )
+        }
+        CompiledRule::All { rules } => rules
+            .iter()
+            .all(|rule| matches(rule, receipt_execution_outcome)),
+        CompiledRule::Any { rules } => rules
+            .iter()
+            .any(|rule| matches(rule, receipt_execution_outcome)),
+        CompiledRule::Not { rule } => !matches(rule, receipt_execution_outcome),
+        // State changes are not attributed to receipts, so are matched per shard instead
+        CompiledRule::StateChange { .. } => false,
     }
 }
 
-fn match_action_any(
-    account_id: &str,
+fn match_action_function_call(
     status: &Status,
+    function: &Pattern,
+    args_predicates: &[CompiledDataPredicate],
+    min_deposit: Option<u128>,
+    min_gas: Option<u64>,
     outcome_with_receipt: &IndexerExecutionOutcomeWithReceipt,
 ) -> bool {
-    if match_account(account_id, outcome_with_receipt) {
-        return match_status(
-            status,
-            &outcome_with_receipt.execution_outcome.outcome.status,
-        );
+    if let ReceiptEnumView::Action { actions, .. } = &outcome_with_receipt.receipt.receipt {
+        let is_any_matching_function_call = actions.iter().any(|action| {
+            if let ActionView::FunctionCall {
+                method_name,
+                args,
+                gas,
+                deposit,
+            } = action
+            {
+                function.matches(method_name)
+                    && match_minimum(min_deposit, *deposit)
+                    && match_minimum(min_gas, *gas)
+                    && match_function_call_args(args_predicates, args)
+            } else {
+                false
+            }
+        });
+        if is_any_matching_function_call {
+            return match_status(
+                status,
+                &outcome_with_receipt.execution_outcome.outcome.status,
+            );
+        }
     }
     false
 }
 
-fn match_action_function_call(
-    account_id: &str,
+fn match_action_kind(
     status: &Status,
-    function: &str,
+    action_kinds: &[ActionKind],
+    min_amount: Option<u128>,
+    max_amount: Option<u128>,
     outcome_with_receipt: &IndexerExecutionOutcomeWithReceipt,
 ) -> bool {
-    if match_account(account_id, outcome_with_receipt) {
-        if let ReceiptEnumView::Action { actions, .. } = &outcome_with_receipt.receipt.receipt {
-            let is_any_matching_function_call = actions.iter().any(|action| {
-                if let ActionView::FunctionCall { method_name, .. } = action {
-                    wildmatch::WildMatch::new(function).matches(method_name)
-                } else {
-                    false
-                }
-            });
-            if is_any_matching_function_call {
-                return match_status(
-                    status,
-                    &outcome_with_receipt.execution_outcome.outcome.status,
-                );
-            } else {
-                return false;
-            }
+    let ReceiptEnumView::Action { actions, .. } = &outcome_with_receipt.receipt.receipt else {
+        return false;
+    };
+
+    let is_any_matching_action = actions.iter().any(|action| {
+        let (action_kind, amount) = match action {
+            ActionView::Transfer { deposit } => (ActionKind::Transfer, Some(*deposit)),
+            ActionView::Stake { stake, .. } => (ActionKind::Stake, Some(*stake)),
+            ActionView::AddKey { .. } => (ActionKind::AddKey, None),
+            ActionView::DeleteKey { .. } => (ActionKind::DeleteKey, None),
+            ActionView::DeployContract { .. } => (ActionKind::DeployContract, None),
+            ActionView::CreateAccount => (ActionKind::CreateAccount, None),
+            ActionView::DeleteAccount { .. } => (ActionKind::DeleteAccount, None),
+            ActionView::FunctionCall { .. } | ActionView::Delegate { .. } => return false,
+        };
+
+        if !action_kinds.contains(&action_kind) {
+            return false;
+        }
+
+        if min_amount.is_none() && max_amount.is_none() {
+            return true;
         }
+
+        amount.is_some_and(|amount| {
+            match_minimum(min_amount, amount) && match_maximum(max_amount, amount)
+        })
+    });
+
+    is_any_matching_action
+        && match_status(
+            status,
+            &outcome_with_receipt.execution_outcome.outcome.status,
+        )
+}
+
+fn match_minimum<T: PartialOrd>(minimum: Option<T>, value: T) -> bool {
+    match minimum {
+        Some(minimum) => value >= minimum,
+        None => true,
+    }
+}
+
+fn match_maximum<T: PartialOrd>(maximum: Option<T>, value: T) -> bool {
+    match maximum {
+        Some(maximum) => value <= maximum,
+        None => true,
     }
-    false
+}
+
+/// Function call arguments are arbitrary bytes, so calls whose arguments are not valid JSON never
+/// satisfy predicates.
+fn match_function_call_args(args_predicates: &[CompiledDataPredicate], args: &[u8]) -> bool {
+    if args_predicates.is_empty() {
+        return true;
+    }
+
+    serde_json::from_slice::<serde_json::Value>(args).is_ok_and(|args| {
+        args_predicates
+            .iter()
+            .all(|args_predicate| data_predicate::matches(args_predicate, &args))
+    })
 }
 
 fn match_event(
-    account_id: &str,
-    event: &str,
-    standard: &str,
-    version: &str,
+    contract_account: &AccountPattern,
+    event: &Pattern,
+    standard: &Pattern,
+    version: &Pattern,
+    data_predicate: Option<&CompiledDataPredicate>,
     outcome_with_receipt: &IndexerExecutionOutcomeWithReceipt,
 ) -> bool {
-    if match_account(account_id, outcome_with_receipt) {
+    if match_account(
+        contract_account,
+        &AffectedAccountRole::Any,
+        outcome_with_receipt,
+    ) {
         outcome_with_receipt
             .execution_outcome
             .outcome
@@ -97,32 +221,57 @@ fn match_event(
             .iter()
             .filter_map(|log| Event::from_log(log).ok())
             .any(|near_event| {
-                vec![
-                    wildmatch::WildMatch::new(event).matches(&near_event.event),
-                    wildmatch::WildMatch::new(standard).matches(&near_event.standard),
-                    wildmatch::WildMatch::new(version).matches(&near_event.version),
-                ]
-                .into_iter()
-                .all(|val| val)
+                match_near_event(event, standard, version, data_predicate, &near_event)
             })
     } else {
         false
     }
 }
 
+pub fn match_near_event(
+    event: &Pattern,
+    standard: &Pattern,
+    version: &Pattern,
+    data_predicate: Option<&CompiledDataPredicate>,
+    near_event: &Event,
+) -> bool {
+    let is_matching_event = [
+        event.matches(&near_event.event),
+        standard.matches(&near_event.standard),
+        version.matches(&near_event.version),
+    ]
+    .into_iter()
+    .all(|val| val);
+
+    if !is_matching_event {
+        return false;
+    }
+
+    match data_predicate {
+        Some(data_predicate) => serde_json::to_value(near_event)
+            .is_ok_and(|near_event| data_predicate::matches(data_predicate, &near_event)),
+        None => true,
+    }
+}
+
 fn match_account(
-    account_id: &str,
+    affected_account: &AccountPattern,
+    affected_account_role: &AffectedAccountRole,
     outcome_with_receipt: &IndexerExecutionOutcomeWithReceipt,
 ) -> bool {
-    match account_id {
-        x if x.contains(',') => x
-            .split(',')
-            .any(|sub_account_id| match_account(sub_account_id.trim(), outcome_with_receipt)),
-        _ => {
-            wildmatch::WildMatch::new(account_id).matches(&outcome_with_receipt.receipt.receiver_id)
-                || wildmatch::WildMatch::new(account_id)
-                    .matches(&outcome_with_receipt.receipt.predecessor_id)
+    let receipt = &outcome_with_receipt.receipt;
+
+    match affected_account_role {
+        AffectedAccountRole::Any => {
+            affected_account.matches(&receipt.receiver_id)
+                || affected_account.matches(&receipt.predecessor_id)
         }
+        AffectedAccountRole::Receiver => affected_account.matches(&receipt.receiver_id),
+        AffectedAccountRole::Predecessor => affected_account.matches(&receipt.predecessor_id),
+        AffectedAccountRole::Signer => match &receipt.receipt {
+            ReceiptEnumView::Action { signer_id, .. } => affected_account.matches(signer_id),
+            ReceiptEnumView::Data { .. } => false,
+        },
     }
 }
 
@@ -1,22 +1,40 @@
+pub mod compiled_rule;
+pub mod data_predicate;
 pub mod matcher;
 pub mod outcomes_reducer;
+pub mod state_changes_reducer;
 pub mod types;
 
 use near_lake_framework::near_indexer_primitives::StreamerMessage;
-use registry_types::Rule;
 
+use crate::receipt_resolver::ReceiptResolver;
+use compiled_rule::CompiledRule;
 use types::{ChainId, IndexerRuleMatch};
 
 pub fn reduce_indexer_rule_matches(
-    indexer_rule: &Rule,
+    indexer_rule: &CompiledRule,
     streamer_message: &StreamerMessage,
+    receipt_resolver: &ReceiptResolver,
     chain_id: ChainId,
 ) -> Vec<IndexerRuleMatch> {
     match &indexer_rule {
-        Rule::ActionAny { .. } | Rule::ActionFunctionCall { .. } | Rule::Event { .. } => {
-            outcomes_reducer::reduce_indexer_rule_matches_from_outcomes(
+        CompiledRule::ActionAny { .. }
+        | CompiledRule::ActionFunctionCall { .. }
+        | CompiledRule::ActionKind { .. }
+        | CompiledRule::Event { .. }
+        | CompiledRule::All { .. }
+        | CompiledRule::Any { .. }
+        | CompiledRule::Not { .. } => outcomes_reducer::reduce_indexer_rule_matches_from_outcomes(
+            indexer_rule,
+            streamer_message,
+            receipt_resolver,
+            chain_id,
+        ),
+        CompiledRule::StateChange { .. } => {
+            state_changes_reducer::reduce_indexer_rule_matches_from_state_changes(
                 indexer_rule,
                 streamer_message,
+                receipt_resolver,
                 chain_id,
             )
         }
@@ -1,14 +1,16 @@
+use crate::receipt_resolver::ReceiptResolver;
+use crate::rules::compiled_rule::CompiledRule;
 use crate::rules::matcher;
 use crate::rules::types::Event;
 use crate::rules::types::{ChainId, IndexerRuleMatch, IndexerRuleMatchPayload};
-use crate::rules::Rule;
 use near_lake_framework::near_indexer_primitives::{
     IndexerExecutionOutcomeWithReceipt, StreamerMessage,
 };
 
 pub fn reduce_indexer_rule_matches_from_outcomes(
-    indexer_rule: &Rule,
+    indexer_rule: &CompiledRule,
     streamer_message: &StreamerMessage,
+    receipt_resolver: &ReceiptResolver,
     chain_id: ChainId,
 ) -> Vec<IndexerRuleMatch> {
     streamer_message
@@ -18,15 +20,15 @@ pub fn reduce_indexer_rule_matches_from_outcomes(
             shard
                 .receipt_execution_outcomes
                 .iter()
-                // future: when extracting Actions, Events, etc this will be a filter operation
-                .find(|receipt_execution_outcome| {
+                .filter(|receipt_execution_outcome| {
                     matcher::matches(indexer_rule, receipt_execution_outcome)
                 })
         })
-        .map(|receipt_execution_outcome| {
-            build_indexer_rule_match(
+        .flat_map(|receipt_execution_outcome| {
+            build_indexer_rule_matches(
                 indexer_rule,
                 receipt_execution_outcome,
+                receipt_resolver,
                 streamer_message.block.header.hash.to_string(),
                 streamer_message.block.header.height,
                 chain_id.clone(),
@@ -35,100 +37,133 @@ pub fn reduce_indexer_rule_matches_from_outcomes(
         .collect()
 }
 
-fn build_indexer_rule_match(
-    indexer_rule: &Rule,
+fn build_indexer_rule_matches(
+    indexer_rule: &CompiledRule,
     receipt_execution_outcome: &IndexerExecutionOutcomeWithReceipt,
+    receipt_resolver: &ReceiptResolver,
     block_header_hash: String,
     block_height: u64,
     chain_id: ChainId,
-) -> IndexerRuleMatch {
-    IndexerRuleMatch {
-        chain_id,
-        payload: build_indexer_rule_match_payload(
-            indexer_rule,
-            receipt_execution_outcome,
-            block_header_hash,
-        ),
+) -> Vec<IndexerRuleMatch> {
+    build_indexer_rule_match_payloads(
+        indexer_rule,
+        receipt_execution_outcome,
+        receipt_resolver,
+        block_header_hash,
+    )
+    .into_iter()
+    .map(|payload| IndexerRuleMatch {
+        chain_id: chain_id.clone(),
+        payload,
         block_height,
-    }
+    })
+    .collect()
 }
 
-fn build_indexer_rule_match_payload(
-    indexer_rule: &Rule,
+/// Builds a payload for the matched receipt, or for Event rules, one per matching event emitted
+/// by the receipt.
+fn build_indexer_rule_match_payloads(
+    indexer_rule: &CompiledRule,
     receipt_execution_outcome: &IndexerExecutionOutcomeWithReceipt,
+    receipt_resolver: &ReceiptResolver,
     block_header_hash: String,
-) -> IndexerRuleMatchPayload {
-    // future enhancement will extract and enrich fields from block & context as
-    //   specified in the indexer function config.
-    let transaction_hash = None;
+) -> Vec<IndexerRuleMatchPayload> {
+    let transaction_hash = receipt_resolver
+        .resolve(&receipt_execution_outcome.receipt.receipt_id)
+        .map(|transaction_hash| transaction_hash.to_string());
 
-    match &indexer_rule {
-        Rule::ActionAny { .. } | Rule::ActionFunctionCall { .. } => {
-            IndexerRuleMatchPayload::Actions {
-                block_hash: block_header_hash,
-                receipt_id: receipt_execution_outcome.receipt.receipt_id.to_string(),
-                transaction_hash,
-            }
+    // Composite rules take the payload shape of whichever nested rule matched, falling back to
+    // Actions when the match was only due to negation
+    let indexer_rule = match indexer_rule {
+        CompiledRule::All { .. } | CompiledRule::Any { .. } | CompiledRule::Not { .. } => {
+            find_matching_leaf_rule(indexer_rule, receipt_execution_outcome).unwrap_or(indexer_rule)
         }
-        Rule::Event {
+        _ => indexer_rule,
+    };
+
+    match &indexer_rule {
+        CompiledRule::ActionAny { .. }
+        | CompiledRule::ActionFunctionCall { .. }
+        | CompiledRule::ActionKind { .. }
+        | CompiledRule::All { .. }
+        | CompiledRule::Any { .. }
+        | CompiledRule::Not { .. } => vec![IndexerRuleMatchPayload::Actions {
+            block_hash: block_header_hash,
+            receipt_id: receipt_execution_outcome.receipt.receipt_id.to_string(),
+            transaction_hash,
+        }],
+        CompiledRule::Event {
             event,
             standard,
             version,
+            data_predicate,
             ..
-        } => {
-            let event = receipt_execution_outcome
-                .execution_outcome
-                .outcome
-                .logs
-                .iter()
-                .filter_map(|log| Event::from_log(log).ok())
-                .filter_map(|near_event| {
-                    if vec![
-                        wildmatch::WildMatch::new(event).matches(&near_event.event),
-                        wildmatch::WildMatch::new(standard).matches(&near_event.standard),
-                        wildmatch::WildMatch::new(version).matches(&near_event.version),
-                    ].into_iter().all(|val| val) {
-                        Some(near_event)
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<Event>>()
-                .first()
-                .expect("Failed to get the matched Event itself while building the IndexerRuleMatchPayload")
-                .clone();
-
-            IndexerRuleMatchPayload::Events {
-                block_hash: block_header_hash,
+        } => receipt_execution_outcome
+            .execution_outcome
+            .outcome
+            .logs
+            .iter()
+            .filter_map(|log| Event::from_log(log).ok())
+            .filter(|near_event| {
+                matcher::match_near_event(
+                    event,
+                    standard,
+                    version,
+                    data_predicate.as_ref(),
+                    near_event,
+                )
+            })
+            .map(|near_event| IndexerRuleMatchPayload::Events {
+                block_hash: block_header_hash.clone(),
                 receipt_id: receipt_execution_outcome.receipt.receipt_id.to_string(),
-                transaction_hash,
-                event: event.event.clone(),
-                standard: event.standard.clone(),
-                version: event.version.clone(),
-                data: event.data.as_ref().map(|data| data.to_string()),
-            }
-        }
+                transaction_hash: transaction_hash.clone(),
+                event: near_event.event,
+                standard: near_event.standard,
+                version: near_event.version,
+                data: near_event.data.as_ref().map(|data| data.to_string()),
+            })
+            .collect(),
+        CompiledRule::StateChange { .. } => vec![],
+    }
+}
+
+fn find_matching_leaf_rule<'a>(
+    indexer_rule: &'a CompiledRule,
+    receipt_execution_outcome: &IndexerExecutionOutcomeWithReceipt,
+) -> Option<&'a CompiledRule> {
+    match indexer_rule {
+        CompiledRule::All { rules } | CompiledRule::Any { rules } => rules
+            .iter()
+            .find_map(|rule| find_matching_leaf_rule(rule, receipt_execution_outcome)),
+        CompiledRule::Not { .. } => None,
+        leaf_rule => matcher::matches(leaf_rule, receipt_execution_outcome).then_some(leaf_rule),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use registry_types::{Rule, Status};
+    use registry_types::{
+        ActionKind, AffectedAccountRole, ComparisonOperator, DataPredicate, Rule, Status,
+    };
 
+    use crate::receipt_resolver::ReceiptResolver;
+    use crate::rules::compiled_rule::CompiledRule;
     use crate::rules::outcomes_reducer::reduce_indexer_rule_matches_from_outcomes;
-    use crate::rules::types::{ChainId, IndexerRuleMatch};
+    use crate::rules::types::{ChainId, IndexerRuleMatch, IndexerRuleMatchPayload};
 
     #[tokio::test]
     async fn match_wildcard_no_match() {
         let wildcard_rule = Rule::ActionAny {
             affected_account_id: "*.nearcrow.near".to_string(),
             status: Status::Success,
+            affected_account_role: AffectedAccountRole::Any,
         };
 
         let streamer_message = crate::test_utils::get_streamer_message(93085141);
         let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
-            &wildcard_rule,
+            &CompiledRule::from(&wildcard_rule),
             &streamer_message,
+            &ReceiptResolver::default(),
             ChainId::Testnet,
         );
 
@@ -140,16 +175,18 @@ mod tests {
         let wildcard_rule = Rule::ActionAny {
             affected_account_id: "*.nearcrowd.near".to_string(),
             status: Status::Success,
+            affected_account_role: AffectedAccountRole::Any,
         };
 
         let streamer_message = crate::test_utils::get_streamer_message(93085141);
         let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
-            &wildcard_rule,
+            &CompiledRule::from(&wildcard_rule),
             &streamer_message,
+            &ReceiptResolver::default(),
             ChainId::Testnet,
         );
 
-        assert_eq!(result.len(), 1); // There are two matches, until we add Extraction we are just matching the first one (block matching)
+        assert_eq!(result.len(), 2);
     }
 
     #[tokio::test]
@@ -157,29 +194,33 @@ mod tests {
         let wildcard_rule = Rule::ActionAny {
             affected_account_id: "*crowd.near".to_string(),
             status: Status::Success,
+            affected_account_role: AffectedAccountRole::Any,
         };
 
         let streamer_message = crate::test_utils::get_streamer_message(93085141);
         let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
-            &wildcard_rule,
+            &CompiledRule::from(&wildcard_rule),
             &streamer_message,
+            &ReceiptResolver::default(),
             ChainId::Testnet,
         );
 
-        assert_eq!(result.len(), 1); // see Extraction note in previous test
+        assert_eq!(result.len(), 2);
 
         let wildcard_rule = Rule::ActionAny {
             affected_account_id: "app.nea*owd.near".to_string(),
             status: Status::Success,
+            affected_account_role: AffectedAccountRole::Any,
         };
 
         let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
-            &wildcard_rule,
+            &CompiledRule::from(&wildcard_rule),
             &streamer_message,
+            &ReceiptResolver::default(),
             ChainId::Testnet,
         );
 
-        assert_eq!(result.len(), 1); // see Extraction note in previous test
+        assert_eq!(result.len(), 2);
     }
 
     #[tokio::test]
@@ -187,16 +228,18 @@ mod tests {
         let wildcard_rule = Rule::ActionAny {
             affected_account_id: "notintheblockaccount.near, app.nearcrowd.near".to_string(),
             status: Status::Success,
+            affected_account_role: AffectedAccountRole::Any,
         };
 
         let streamer_message = crate::test_utils::get_streamer_message(93085141);
         let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
-            &wildcard_rule,
+            &CompiledRule::from(&wildcard_rule),
             &streamer_message,
+            &ReceiptResolver::default(),
             ChainId::Testnet,
         );
 
-        assert_eq!(result.len(), 1); // There are two matches, until we add Extraction we are just matching the first one (block matching)
+        assert_eq!(result.len(), 2);
     }
 
     #[tokio::test]
@@ -204,15 +247,336 @@ mod tests {
         let wildcard_rule = Rule::ActionAny {
             affected_account_id: "notintheblockaccount.near, *.nearcrowd.near".to_string(),
             status: Status::Success,
+            affected_account_role: AffectedAccountRole::Any,
+        };
+
+        let streamer_message = crate::test_utils::get_streamer_message(93085141);
+        let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
+            &CompiledRule::from(&wildcard_rule),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Testnet,
+        );
+
+        assert_eq!(result.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn match_any_composite_rule() {
+        let composite_rule = Rule::Any {
+            rules: vec![
+                Rule::ActionFunctionCall {
+                    affected_account_id: "app.nearcrowd.near".to_string(),
+                    status: Status::Success,
+                    affected_account_role: AffectedAccountRole::Any,
+                    function: "claim_assignment".to_string(),
+                    args_predicates: vec![],
+                    min_deposit: None,
+                    min_gas: None,
+                },
+                Rule::Event {
+                    contract_account_id: "token.sweat".to_string(),
+                    standard: "nep141".to_string(),
+                    version: "*".to_string(),
+                    event: "ft_transfer".to_string(),
+                    data_predicate: None,
+                },
+            ],
         };
 
         let streamer_message = crate::test_utils::get_streamer_message(93085141);
         let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
-            &wildcard_rule,
+            &CompiledRule::from(&composite_rule),
             &streamer_message,
+            &ReceiptResolver::default(),
             ChainId::Testnet,
         );
 
-        assert_eq!(result.len(), 1); // There are two matches, until we add Extraction we are just matching the first one (block matching)
+        assert_eq!(result.len(), 3);
+        assert!(matches!(
+            result[0].payload,
+            IndexerRuleMatchPayload::Actions { .. }
+        ));
+        assert!(matches!(
+            result[1].payload,
+            IndexerRuleMatchPayload::Events { .. }
+        ));
+        assert!(matches!(
+            result[2].payload,
+            IndexerRuleMatchPayload::Events { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn extracts_every_matching_event() {
+        let event_rule = Rule::Event {
+            contract_account_id: "token.sweat".to_string(),
+            standard: "nep141".to_string(),
+            version: "*".to_string(),
+            event: "ft_transfer".to_string(),
+            data_predicate: None,
+        };
+
+        let streamer_message = crate::test_utils::get_streamer_message(93085141);
+        let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
+            &CompiledRule::from(&event_rule),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Mainnet,
+        );
+
+        assert_eq!(result.len(), 2);
+        for indexer_rule_match in result {
+            match indexer_rule_match.payload {
+                IndexerRuleMatchPayload::Events {
+                    event,
+                    standard,
+                    data,
+                    ..
+                } => {
+                    assert_eq!(event, "ft_transfer");
+                    assert_eq!(standard, "nep141");
+                    assert!(data.is_some());
+                }
+                payload => panic!("Unexpected payload: {:?}", payload),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn filters_function_calls_by_args() {
+        let function_call_rule = |operator| Rule::ActionFunctionCall {
+            affected_account_id: "app.nearcrowd.near".to_string(),
+            status: Status::Success,
+            affected_account_role: AffectedAccountRole::Any,
+            function: "claim_assignment".to_string(),
+            args_predicates: vec![
+                DataPredicate {
+                    path: "task_ordinal".to_string(),
+                    operator: ComparisonOperator::Equals,
+                    value: "1".to_string(),
+                },
+                DataPredicate {
+                    path: "bid".to_string(),
+                    operator,
+                    value: "50000000000000000000000".to_string(),
+                },
+            ],
+            min_deposit: None,
+            min_gas: None,
+        };
+
+        let streamer_message = crate::test_utils::get_streamer_message(93085141);
+
+        let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
+            &CompiledRule::from(&function_call_rule(ComparisonOperator::GreaterThanOrEqual)),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Mainnet,
+        );
+        assert_eq!(result.len(), 1);
+
+        let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
+            &CompiledRule::from(&function_call_rule(ComparisonOperator::GreaterThan)),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Mainnet,
+        );
+        assert_eq!(result.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn filters_function_calls_by_deposit_and_gas() {
+        let function_call_rule = |min_deposit: &str, min_gas| Rule::ActionFunctionCall {
+            affected_account_id: "token.sweat".to_string(),
+            status: Status::Success,
+            affected_account_role: AffectedAccountRole::Any,
+            function: "ft_transfer".to_string(),
+            args_predicates: vec![],
+            min_deposit: Some(min_deposit.to_string()),
+            min_gas: Some(min_gas),
+        };
+
+        let streamer_message = crate::test_utils::get_streamer_message(93085141);
+
+        let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
+            &CompiledRule::from(&function_call_rule("1", 14_000_000_000_000)),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Mainnet,
+        );
+        assert_eq!(result.len(), 2);
+
+        let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
+            &CompiledRule::from(&function_call_rule("2", 14_000_000_000_000)),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Mainnet,
+        );
+        assert_eq!(result.len(), 0);
+
+        let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
+            &CompiledRule::from(&function_call_rule("1", 30_000_000_000_000)),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Mainnet,
+        );
+        assert_eq!(result.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn match_affected_account_role() {
+        let action_any_rule = |affected_account_id: &str, affected_account_role| Rule::ActionAny {
+            affected_account_id: affected_account_id.to_string(),
+            status: Status::Success,
+            affected_account_role,
+        };
+
+        let streamer_message = crate::test_utils::get_streamer_message(93085141);
+
+        for (affected_account_id, affected_account_role, expected_matches) in [
+            ("*.nearcrowd.near", AffectedAccountRole::Any, 2),
+            ("*.nearcrowd.near", AffectedAccountRole::Receiver, 2),
+            ("*.nearcrowd.near", AffectedAccountRole::Predecessor, 1),
+            ("*.nearcrowd.near", AffectedAccountRole::Signer, 1),
+            ("gareva.near", AffectedAccountRole::Receiver, 0),
+            ("gareva.near", AffectedAccountRole::Signer, 1),
+        ] {
+            let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
+                &CompiledRule::from(&action_any_rule(
+                    affected_account_id,
+                    affected_account_role.clone(),
+                )),
+                &streamer_message,
+                &ReceiptResolver::default(),
+                ChainId::Mainnet,
+            );
+
+            assert_eq!(
+                result.len(),
+                expected_matches,
+                "{} as {:?}",
+                affected_account_id,
+                affected_account_role
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn match_action_kinds() {
+        let action_kind_rule = Rule::ActionKind {
+            affected_account_id: "*.users.kaiching".to_string(),
+            status: Status::Success,
+            affected_account_role: AffectedAccountRole::Any,
+            action_kinds: vec![ActionKind::CreateAccount, ActionKind::AddKey],
+            min_amount: None,
+            max_amount: None,
+        };
+
+        let streamer_message = crate::test_utils::get_streamer_message(107503704);
+        let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
+            &CompiledRule::from(&action_kind_rule),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Mainnet,
+        );
+
+        assert_eq!(result.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn filters_action_kinds_by_amount() {
+        let transfer_rule = |min_amount: &str| Rule::ActionKind {
+            affected_account_id: "*.thelittles.near".to_string(),
+            status: Status::Any,
+            affected_account_role: AffectedAccountRole::Any,
+            action_kinds: vec![ActionKind::Transfer, ActionKind::CreateAccount],
+            min_amount: Some(min_amount.to_string()),
+            max_amount: None,
+        };
+
+        let streamer_message = crate::test_utils::get_streamer_message(107503705);
+
+        let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
+            &CompiledRule::from(&transfer_rule("1000000000000000000000000")),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Mainnet,
+        );
+        assert_eq!(result.len(), 1);
+
+        let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
+            &CompiledRule::from(&transfer_rule("1000000000000000000000001")),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Mainnet,
+        );
+        assert_eq!(result.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn filters_events_by_data_predicate() {
+        let event_rule = Rule::Event {
+            contract_account_id: "token.sweat".to_string(),
+            standard: "nep141".to_string(),
+            version: "*".to_string(),
+            event: "ft_transfer".to_string(),
+            data_predicate: Some(DataPredicate {
+                path: "data[].old_owner_id".to_string(),
+                operator: ComparisonOperator::Equals,
+                value: "7e887c69ca5edd6eadfe7b87905a1c12d0ea5c8e6e6ae1a9659b74a43490d497"
+                    .to_string(),
+            }),
+        };
+
+        let streamer_message = crate::test_utils::get_streamer_message(93085141);
+        let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
+            &CompiledRule::from(&event_rule),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Mainnet,
+        );
+
+        assert_eq!(result.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn match_all_composite_rule_with_negation() {
+        let composite_rule = Rule::All {
+            rules: vec![
+                Rule::ActionAny {
+                    affected_account_id: "*.nearcrowd.near".to_string(),
+                    status: Status::Success,
+                    affected_account_role: AffectedAccountRole::Any,
+                },
+                Rule::Not {
+                    rule: Box::new(Rule::ActionFunctionCall {
+                        affected_account_id: "*".to_string(),
+                        status: Status::Any,
+                        affected_account_role: AffectedAccountRole::Any,
+                        function: "approve_solution".to_string(),
+                        args_predicates: vec![],
+                        min_deposit: None,
+                        min_gas: None,
+                    }),
+                },
+            ],
+        };
+
+        let streamer_message = crate::test_utils::get_streamer_message(93085141);
+        let result: Vec<IndexerRuleMatch> = reduce_indexer_rule_matches_from_outcomes(
+            &CompiledRule::from(&composite_rule),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Testnet,
+        );
+
+        assert_eq!(result.len(), 1);
+        match &result[0].payload {
+            IndexerRuleMatchPayload::Actions { receipt_id, .. } => {
+                assert_ne!(receipt_id, "DwVQmRqaeHiMwRESakJhp2jdYbq5ATLwa5BdKQtaek8n")
+            }
+            payload => panic!("Unexpected payload: {:?}", payload),
+        }
     }
 }
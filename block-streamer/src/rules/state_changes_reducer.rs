@@ -0,0 +1,202 @@
+use near_lake_framework::near_indexer_primitives::{
+    views::{StateChangeCauseView, StateChangeValueView, StateChangeWithCauseView},
+    StreamerMessage,
+};
+use registry_types::StateChangeKind;
+
+use crate::receipt_resolver::ReceiptResolver;
+use crate::rules::compiled_rule::CompiledRule;
+use crate::rules::types::{ChainId, IndexerRuleMatch, IndexerRuleMatchPayload};
+
+pub fn reduce_indexer_rule_matches_from_state_changes(
+    indexer_rule: &CompiledRule,
+    streamer_message: &StreamerMessage,
+    receipt_resolver: &ReceiptResolver,
+    chain_id: ChainId,
+) -> Vec<IndexerRuleMatch> {
+    let CompiledRule::StateChange {
+        affected_account,
+        change_kinds,
+    } = indexer_rule
+    else {
+        return vec![];
+    };
+
+    streamer_message
+        .shards
+        .iter()
+        .flat_map(|shard| shard.state_changes.iter())
+        .filter_map(|state_change| {
+            let (account_id, change_kind) = classify_state_change(state_change)?;
+
+            if !change_kinds.contains(&change_kind) || !affected_account.matches(account_id) {
+                return None;
+            }
+
+            let (receipt_id, transaction_hash) = match &state_change.cause {
+                StateChangeCauseView::TransactionProcessing { tx_hash } => {
+                    (None, Some(tx_hash.to_string()))
+                }
+                StateChangeCauseView::ActionReceiptProcessingStarted { receipt_hash }
+                | StateChangeCauseView::ActionReceiptGasReward { receipt_hash }
+                | StateChangeCauseView::ReceiptProcessing { receipt_hash }
+                | StateChangeCauseView::PostponedReceipt { receipt_hash } => (
+                    Some(receipt_hash.to_string()),
+                    receipt_resolver
+                        .resolve(receipt_hash)
+                        .map(|transaction_hash| transaction_hash.to_string()),
+                ),
+                _ => (None, None),
+            };
+
+            Some(IndexerRuleMatch {
+                chain_id: chain_id.clone(),
+                payload: IndexerRuleMatchPayload::StateChanges {
+                    block_hash: streamer_message.block.header.hash.to_string(),
+                    receipt_id,
+                    transaction_hash,
+                    account_id: account_id.to_string(),
+                    change_kind: change_kind_name(&change_kind).to_string(),
+                },
+                block_height: streamer_message.block.header.height,
+            })
+        })
+        .collect()
+}
+
+/// Maps the state change to its affected account and kind, or `None` for changes which cannot be
+/// matched against. Access key updates caused directly by transactions are nonce increments
+/// rather than newly added keys.
+pub fn classify_state_change(
+    state_change: &StateChangeWithCauseView,
+) -> Option<(&str, StateChangeKind)> {
+    match &state_change.value {
+        StateChangeValueView::AccountUpdate { account_id, .. } => {
+            Some((account_id.as_str(), StateChangeKind::AccountUpdate))
+        }
+        StateChangeValueView::AccessKeyUpdate { account_id, .. } => match state_change.cause {
+            StateChangeCauseView::TransactionProcessing { .. } => None,
+            _ => Some((account_id.as_str(), StateChangeKind::AccessKeyAdd)),
+        },
+        StateChangeValueView::AccessKeyDeletion { account_id, .. } => {
+            Some((account_id.as_str(), StateChangeKind::AccessKeyDelete))
+        }
+        StateChangeValueView::DataUpdate { account_id, .. }
+        | StateChangeValueView::DataDeletion { account_id, .. } => {
+            Some((account_id.as_str(), StateChangeKind::ContractDataUpdate))
+        }
+        StateChangeValueView::ContractCodeUpdate { account_id, .. } => {
+            Some((account_id.as_str(), StateChangeKind::CodeDeploy))
+        }
+        StateChangeValueView::AccountDeletion { .. }
+        | StateChangeValueView::ContractCodeDeletion { .. } => None,
+    }
+}
+
+fn change_kind_name(change_kind: &StateChangeKind) -> &'static str {
+    match change_kind {
+        StateChangeKind::AccountUpdate => "ACCOUNT_UPDATE",
+        StateChangeKind::AccessKeyAdd => "ACCESS_KEY_ADD",
+        StateChangeKind::AccessKeyDelete => "ACCESS_KEY_DELETE",
+        StateChangeKind::ContractDataUpdate => "CONTRACT_DATA_UPDATE",
+        StateChangeKind::CodeDeploy => "CODE_DEPLOY",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use registry_types::{Rule, StateChangeKind};
+
+    use crate::receipt_resolver::ReceiptResolver;
+    use crate::rules::compiled_rule::CompiledRule;
+    use crate::rules::state_changes_reducer::reduce_indexer_rule_matches_from_state_changes;
+    use crate::rules::types::{ChainId, IndexerRuleMatchPayload};
+
+    #[test]
+    fn match_contract_data_updates() {
+        let rule = Rule::StateChange {
+            affected_account_id: "app.nearcrowd.near".to_string(),
+            change_kinds: vec![StateChangeKind::ContractDataUpdate],
+        };
+
+        let streamer_message = crate::test_utils::get_streamer_message(93085141);
+        let result = reduce_indexer_rule_matches_from_state_changes(
+            &CompiledRule::from(&rule),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Mainnet,
+        );
+
+        assert!(!result.is_empty());
+        assert!(result.iter().all(|indexer_rule_match| matches!(
+            &indexer_rule_match.payload,
+            IndexerRuleMatchPayload::StateChanges {
+                account_id,
+                change_kind,
+                receipt_id: Some(_),
+                ..
+            } if account_id == "app.nearcrowd.near" && change_kind == "CONTRACT_DATA_UPDATE"
+        )));
+    }
+
+    #[test]
+    fn match_access_key_add() {
+        let rule = Rule::StateChange {
+            affected_account_id: "7f2a4588f275fa492661c9f8894dfe8b9c949fb58b5c799898ff780b6f6790f2"
+                .to_string(),
+            change_kinds: vec![StateChangeKind::AccessKeyAdd],
+        };
+
+        let streamer_message = crate::test_utils::get_streamer_message(93085141);
+        let mut receipt_resolver = ReceiptResolver::default();
+        receipt_resolver.track(&streamer_message);
+
+        let result = reduce_indexer_rule_matches_from_state_changes(
+            &CompiledRule::from(&rule),
+            &streamer_message,
+            &receipt_resolver,
+            ChainId::Mainnet,
+        );
+
+        assert_eq!(result.len(), 1);
+    }
+
+    #[test]
+    fn ignores_unselected_change_kinds() {
+        let rule = Rule::StateChange {
+            affected_account_id: "*".to_string(),
+            change_kinds: vec![StateChangeKind::CodeDeploy],
+        };
+
+        let streamer_message = crate::test_utils::get_streamer_message(93085141);
+        let result = reduce_indexer_rule_matches_from_state_changes(
+            &CompiledRule::from(&rule),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Mainnet,
+        );
+
+        assert_eq!(result.len(), 0);
+    }
+
+    #[test]
+    fn match_account_updates_by_wildcard() {
+        let rule = Rule::StateChange {
+            affected_account_id: "*.near, token.sweat".to_string(),
+            change_kinds: vec![StateChangeKind::AccountUpdate],
+        };
+
+        let streamer_message = crate::test_utils::get_streamer_message(93085141);
+        let result = reduce_indexer_rule_matches_from_state_changes(
+            &CompiledRule::from(&rule),
+            &streamer_message,
+            &ReceiptResolver::default(),
+            ChainId::Mainnet,
+        );
+
+        assert!(result.iter().any(|indexer_rule_match| matches!(
+            &indexer_rule_match.payload,
+            IndexerRuleMatchPayload::StateChanges { account_id, .. } if account_id == "token.sweat"
+        )));
+    }
+}
@@ -45,9 +45,21 @@ pub enum IndexerRuleMatchPayload {
         block_hash: BlockHashString,
         receipt_id: Option<ReceiptIdString>,
         transaction_hash: Option<TransactionHashString>,
+        account_id: String,
+        change_kind: String,
     },
 }
 
+impl IndexerRuleMatchPayload {
+    pub fn receipt_id(&self) -> Option<&ReceiptIdString> {
+        match self {
+            IndexerRuleMatchPayload::Actions { receipt_id, .. }
+            | IndexerRuleMatchPayload::Events { receipt_id, .. } => Some(receipt_id),
+            IndexerRuleMatchPayload::StateChanges { receipt_id, .. } => receipt_id.as_ref(),
+        }
+    }
+}
+
 #[derive(
     borsh::BorshSerialize,
     borsh::BorshDeserialize,
@@ -28,12 +28,17 @@ impl S3ClientImpl {
         aws_sdk_s3::operation::get_object::GetObjectOutput,
         aws_sdk_s3::error::SdkError<aws_sdk_s3::operation::get_object::GetObjectError>,
     > {
-        self.client
+        let result = self
+            .client
             .get_object()
             .bucket(bucket)
             .key(prefix)
             .send()
-            .await
+            .await;
+
+        crate::metrics::record_s3_request(crate::metrics::S3_GET_OBJECT, result.is_err());
+
+        result
     }
 
     pub async fn list_objects(
@@ -56,7 +61,11 @@ impl S3ClientImpl {
             builder = builder.continuation_token(token);
         }
 
-        builder.send().await
+        let result = builder.send().await;
+
+        crate::metrics::record_s3_request(crate::metrics::S3_LIST_OBJECTS, result.is_err());
+
+        result
     }
 
     pub async fn get_text_file(&self, bucket: &str, prefix: &str) -> anyhow::Result<String> {
@@ -114,3 +123,52 @@ impl S3ClientImpl {
         Ok(results)
     }
 }
+
+/// Records the S3 requests made by `near-lake-framework` against a block stream. The framework
+/// creates its own client from the config and fetches blocks in a separate task, so requests are
+/// recorded by an interceptor on a per stream config rather than by `S3Client`.
+#[derive(Debug)]
+pub struct LakeS3RequestMetrics {
+    account_id: String,
+    function_name: String,
+}
+
+impl LakeS3RequestMetrics {
+    pub fn new(account_id: &str, function_name: &str) -> Self {
+        Self {
+            account_id: account_id.to_string(),
+            function_name: function_name.to_string(),
+        }
+    }
+}
+
+impl aws_sdk_s3::config::Intercept for LakeS3RequestMetrics {
+    fn name(&self) -> &'static str {
+        "LakeS3RequestMetrics"
+    }
+
+    fn read_after_attempt(
+        &self,
+        context: &aws_sdk_s3::config::interceptors::FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &aws_sdk_s3::config::RuntimeComponents,
+        _cfg: &mut aws_sdk_s3::config::ConfigBag,
+    ) -> Result<(), aws_sdk_s3::error::BoxError> {
+        // `near-lake-framework` only lists block prefixes via ListObjectsV2 and gets block and
+        // shard files
+        let operation = match context.request() {
+            Some(request) if request.uri().contains("list-type=2") => {
+                crate::metrics::S3_LIST_OBJECTS
+            }
+            _ => crate::metrics::S3_GET_OBJECT,
+        };
+
+        crate::metrics::record_block_stream_s3_request(
+            &self.account_id,
+            &self.function_name,
+            operation,
+            matches!(context.output_or_error(), Some(Err(_))),
+        );
+
+        Ok(())
+    }
+}
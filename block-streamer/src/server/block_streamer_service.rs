@@ -1,7 +1,11 @@
 use std::collections::HashMap;
+use std::sync::atomic::Ordering;
 use std::sync::Mutex;
 
+use anyhow::Context;
+use futures::StreamExt;
 use near_lake_framework::near_indexer_primitives;
+use prost::Message;
 use tonic::{Request, Response, Status};
 
 use crate::indexer_config::IndexerConfig;
@@ -12,25 +16,40 @@ use crate::server::blockstreamer;
 
 use blockstreamer::*;
 
+/// Redis hash of the `StartStreamRequest` of each active block stream, keyed by stream ID
+const BLOCK_STREAMS_KEY: &str = "block_streamer:block_streams";
+
+const DEFAULT_PREVIEW_LIMIT: u32 = 100;
+const MAX_PREVIEW_LIMIT: u32 = 1_000;
+/// The maximum number of blocks a preview may span, which bounds the index files listed for it
+const MAX_PREVIEW_RANGE_BLOCKS: u64 = 1_000_000;
+
 pub struct BlockStreamerService {
     redis_client: std::sync::Arc<crate::redis::RedisClient>,
-    delta_lake_client: std::sync::Arc<crate::delta_lake_client::DeltaLakeClient>,
+    mainnet_delta_lake_client: std::sync::Arc<crate::delta_lake_client::DeltaLakeClient>,
+    testnet_delta_lake_client: std::sync::Arc<crate::delta_lake_client::DeltaLakeClient>,
     lake_s3_config: aws_sdk_s3::Config,
-    chain_id: ChainId,
+    redis_stream_watermarks: block_stream::RedisStreamWatermarks,
+    block_sink_policy: crate::block_sink::BlockSinkPolicy,
     block_streams: Mutex<HashMap<String, block_stream::BlockStream>>,
 }
 
 impl BlockStreamerService {
     pub fn new(
         redis_client: std::sync::Arc<crate::redis::RedisClient>,
-        delta_lake_client: std::sync::Arc<crate::delta_lake_client::DeltaLakeClient>,
+        mainnet_delta_lake_client: std::sync::Arc<crate::delta_lake_client::DeltaLakeClient>,
+        testnet_delta_lake_client: std::sync::Arc<crate::delta_lake_client::DeltaLakeClient>,
         lake_s3_config: aws_sdk_s3::Config,
+        redis_stream_watermarks: block_stream::RedisStreamWatermarks,
+        block_sink_policy: crate::block_sink::BlockSinkPolicy,
     ) -> Self {
         Self {
             redis_client,
-            delta_lake_client,
+            mainnet_delta_lake_client,
+            testnet_delta_lake_client,
             lake_s3_config,
-            chain_id: ChainId::Mainnet,
+            redis_stream_watermarks,
+            block_sink_policy,
             block_streams: Mutex::new(HashMap::new()),
         }
     }
@@ -43,6 +62,27 @@ impl BlockStreamerService {
             .map_err(|err| Status::internal(format!("Failed to acquire lock: {}", err)))
     }
 
+    fn get_delta_lake_client(
+        &self,
+        chain_id: &ChainId,
+    ) -> std::sync::Arc<crate::delta_lake_client::DeltaLakeClient> {
+        match chain_id {
+            ChainId::Mainnet => self.mainnet_delta_lake_client.clone(),
+            ChainId::Testnet => self.testnet_delta_lake_client.clone(),
+        }
+    }
+
+    fn match_chain_id(grpc_chain: i32) -> Result<ChainId, Status> {
+        match grpc_chain {
+            0 => Ok(ChainId::Mainnet),
+            1 => Ok(ChainId::Testnet),
+            chain => Err(Status::invalid_argument(format!(
+                "Invalid Chain provided: {}",
+                chain
+            ))),
+        }
+    }
+
     fn match_status(grpc_status: i32) -> Result<registry_types::Status, Status> {
         match grpc_status {
             1 => Ok(registry_types::Status::Success),
@@ -54,35 +94,406 @@ impl BlockStreamerService {
             ))),
         }
     }
-}
 
-#[tonic::async_trait]
-impl blockstreamer::block_streamer_server::BlockStreamer for BlockStreamerService {
-    async fn start_stream(
+    fn match_rule(rule: nested_rule::Rule) -> Result<registry_types::Rule, Status> {
+        let rule = match rule {
+            nested_rule::Rule::ActionAnyRule(action_any) => registry_types::Rule::ActionAny {
+                affected_account_id: action_any.affected_account_id,
+                status: Self::match_status(action_any.status)?,
+                affected_account_role: Self::match_affected_account_role(
+                    action_any.affected_account_role,
+                )?,
+            },
+            nested_rule::Rule::ActionFunctionCallRule(action_function_call) => {
+                registry_types::Rule::ActionFunctionCall {
+                    affected_account_id: action_function_call.affected_account_id,
+                    status: Self::match_status(action_function_call.status)?,
+                    affected_account_role: Self::match_affected_account_role(
+                        action_function_call.affected_account_role,
+                    )?,
+                    function: action_function_call.function_name,
+                    args_predicates: action_function_call
+                        .args_predicates
+                        .into_iter()
+                        .map(Self::match_data_predicate)
+                        .collect::<Result<_, _>>()?,
+                    min_deposit: Self::match_amount(action_function_call.min_deposit)?,
+                    min_gas: action_function_call.min_gas,
+                }
+            }
+            nested_rule::Rule::EventRule(event) => registry_types::Rule::Event {
+                contract_account_id: event.contract_account_id,
+                standard: event.standard,
+                version: event.version,
+                event: event.event,
+                data_predicate: event
+                    .data_predicate
+                    .map(Self::match_data_predicate)
+                    .transpose()?,
+            },
+            nested_rule::Rule::ActionKindRule(action_kind) => {
+                if action_kind.action_kinds.is_empty() {
+                    return Err(Status::invalid_argument(
+                        "Action kind rule must specify at least one action kind",
+                    ));
+                }
+
+                registry_types::Rule::ActionKind {
+                    affected_account_id: action_kind.affected_account_id,
+                    status: Self::match_status(action_kind.status)?,
+                    affected_account_role: Self::match_affected_account_role(
+                        action_kind.affected_account_role,
+                    )?,
+                    action_kinds: action_kind
+                        .action_kinds
+                        .into_iter()
+                        .map(Self::match_action_kind)
+                        .collect::<Result<_, _>>()?,
+                    min_amount: Self::match_amount(action_kind.min_amount)?,
+                    max_amount: Self::match_amount(action_kind.max_amount)?,
+                }
+            }
+            nested_rule::Rule::StateChangeRule(state_change) => {
+                if state_change.change_kinds.is_empty() {
+                    return Err(Status::invalid_argument(
+                        "State change rule must specify at least one change kind",
+                    ));
+                }
+
+                registry_types::Rule::StateChange {
+                    affected_account_id: state_change.affected_account_id,
+                    change_kinds: state_change
+                        .change_kinds
+                        .into_iter()
+                        .map(Self::match_state_change_kind)
+                        .collect::<Result<_, _>>()?,
+                }
+            }
+            nested_rule::Rule::AllRule(all) => registry_types::Rule::All {
+                rules: Self::match_nested_rules(all.rules)?,
+            },
+            nested_rule::Rule::AnyRule(any) => registry_types::Rule::Any {
+                rules: Self::match_nested_rules(any.rules)?,
+            },
+            nested_rule::Rule::NotRule(not) => registry_types::Rule::Not {
+                rule: Box::new(Self::match_nested_rule(
+                    not.rule
+                        .map(|rule| *rule)
+                        .ok_or(Status::invalid_argument("Not rule must contain a rule"))?,
+                )?),
+            },
+        };
+
+        Ok(rule)
+    }
+
+    fn match_data_predicate(
+        data_predicate: DataPredicate,
+    ) -> Result<registry_types::DataPredicate, Status> {
+        let operator = match data_predicate.operator {
+            1 => registry_types::ComparisonOperator::Equals,
+            2 => registry_types::ComparisonOperator::NotEquals,
+            3 => registry_types::ComparisonOperator::Matches,
+            4 => registry_types::ComparisonOperator::GreaterThan,
+            5 => registry_types::ComparisonOperator::GreaterThanOrEqual,
+            6 => registry_types::ComparisonOperator::LessThan,
+            7 => registry_types::ComparisonOperator::LessThanOrEqual,
+            operator => {
+                return Err(Status::invalid_argument(format!(
+                    "Invalid ComparisonOperator provided: {}",
+                    operator
+                )))
+            }
+        };
+
+        if data_predicate.path.is_empty() {
+            return Err(Status::invalid_argument(
+                "Data predicate path must be provided",
+            ));
+        }
+
+        if operator.is_numeric() && data_predicate.value.parse::<f64>().is_err() {
+            return Err(Status::invalid_argument(format!(
+                "Data predicate value {} must be numeric for {:?} comparisons",
+                data_predicate.value, operator
+            )));
+        }
+
+        Ok(registry_types::DataPredicate {
+            path: data_predicate.path,
+            operator,
+            value: data_predicate.value,
+        })
+    }
+
+    fn match_affected_account_role(
+        grpc_affected_account_role: i32,
+    ) -> Result<registry_types::AffectedAccountRole, Status> {
+        match grpc_affected_account_role {
+            0 | 4 => Ok(registry_types::AffectedAccountRole::Any),
+            1 => Ok(registry_types::AffectedAccountRole::Receiver),
+            2 => Ok(registry_types::AffectedAccountRole::Predecessor),
+            3 => Ok(registry_types::AffectedAccountRole::Signer),
+            affected_account_role => Err(Status::invalid_argument(format!(
+                "Invalid AffectedAccountRole provided: {}",
+                affected_account_role
+            ))),
+        }
+    }
+
+    fn match_action_kind(grpc_action_kind: i32) -> Result<registry_types::ActionKind, Status> {
+        match grpc_action_kind {
+            1 => Ok(registry_types::ActionKind::Transfer),
+            2 => Ok(registry_types::ActionKind::AddKey),
+            3 => Ok(registry_types::ActionKind::DeleteKey),
+            4 => Ok(registry_types::ActionKind::DeployContract),
+            5 => Ok(registry_types::ActionKind::Stake),
+            6 => Ok(registry_types::ActionKind::CreateAccount),
+            7 => Ok(registry_types::ActionKind::DeleteAccount),
+            action_kind => Err(Status::invalid_argument(format!(
+                "Invalid ActionKind provided: {}",
+                action_kind
+            ))),
+        }
+    }
+
+    fn match_amount(amount: Option<String>) -> Result<Option<String>, Status> {
+        match amount {
+            Some(amount) if amount.parse::<u128>().is_err() => Err(Status::invalid_argument(
+                format!("Invalid yoctoNEAR amount provided: {}", amount),
+            )),
+            amount => Ok(amount),
+        }
+    }
+
+    fn match_block_delivery(
+        block_delivery: Option<BlockDelivery>,
+    ) -> Result<crate::block_delivery::BlockDelivery, Status> {
+        let Some(block_delivery) = block_delivery else {
+            return Ok(crate::block_delivery::BlockDelivery::default());
+        };
+
+        let mode = match block_delivery.mode {
+            0 => crate::block_delivery::BlockDeliveryMode::None,
+            1 => crate::block_delivery::BlockDeliveryMode::Inline,
+            2 => crate::block_delivery::BlockDeliveryMode::Cache {
+                ttl_seconds: match block_delivery.cache_ttl_seconds {
+                    0 => crate::block_delivery::DEFAULT_CACHE_TTL_SECONDS,
+                    ttl_seconds => ttl_seconds,
+                },
+            },
+            mode => {
+                return Err(Status::invalid_argument(format!(
+                    "Invalid BlockDeliveryMode provided: {}",
+                    mode
+                )))
+            }
+        };
+
+        Ok(crate::block_delivery::BlockDelivery {
+            mode,
+            compress: block_delivery.compress,
+            matching_shards_only: block_delivery.matching_shards_only,
+        })
+    }
+
+    fn match_block_sink(
         &self,
-        request: Request<blockstreamer::StartStreamRequest>,
-    ) -> Result<Response<blockstreamer::StartStreamResponse>, Status> {
-        let request = request.into_inner();
+        sink: Option<start_stream_request::Sink>,
+    ) -> Result<crate::block_sink::BlockSinkConfig, Status> {
+        match sink {
+            None => Ok(crate::block_sink::BlockSinkConfig::RedisStream),
+            Some(start_stream_request::Sink::FileSink(file_sink)) => {
+                if file_sink.path.is_empty() {
+                    return Err(Status::invalid_argument("File sink path must be provided"));
+                }
+
+                let path = self
+                    .block_sink_policy
+                    .resolve_file_path(&file_sink.path)
+                    .map_err(|err| Status::invalid_argument(err.to_string()))?;
+
+                Ok(crate::block_sink::BlockSinkConfig::File { path })
+            }
+            Some(start_stream_request::Sink::JetStreamSink(jet_stream_sink)) => {
+                if jet_stream_sink.url.is_empty() || jet_stream_sink.subject.is_empty() {
+                    return Err(Status::invalid_argument(
+                        "JetStream sink url and subject must be provided",
+                    ));
+                }
+
+                self.block_sink_policy
+                    .check_nats_url(&jet_stream_sink.url)
+                    .map_err(|err| Status::invalid_argument(err.to_string()))?;
+
+                Ok(crate::block_sink::BlockSinkConfig::JetStream {
+                    url: jet_stream_sink.url,
+                    subject: jet_stream_sink.subject,
+                })
+            }
+        }
+    }
+
+    fn match_stream_state(block_stream: &block_stream::BlockStream) -> StreamState {
+        match block_stream.status.state() {
+            block_stream::StreamState::Failed => StreamState::Failed,
+            block_stream::StreamState::Restarting => StreamState::Restarting,
+            _ if block_stream.back_pressure.is_paused() => StreamState::Paused,
+            block_stream::StreamState::Starting => StreamState::Starting,
+            block_stream::StreamState::BackfillingFromDeltaLake => StreamState::Backfilling,
+            block_stream::StreamState::StreamingFromLake => StreamState::Streaming,
+        }
+    }
+
+    fn match_state_change_kind(
+        grpc_state_change_kind: i32,
+    ) -> Result<registry_types::StateChangeKind, Status> {
+        match grpc_state_change_kind {
+            1 => Ok(registry_types::StateChangeKind::AccountUpdate),
+            2 => Ok(registry_types::StateChangeKind::AccessKeyAdd),
+            3 => Ok(registry_types::StateChangeKind::AccessKeyDelete),
+            4 => Ok(registry_types::StateChangeKind::ContractDataUpdate),
+            5 => Ok(registry_types::StateChangeKind::CodeDeploy),
+            state_change_kind => Err(Status::invalid_argument(format!(
+                "Invalid StateChangeKind provided: {}",
+                state_change_kind
+            ))),
+        }
+    }
+
+    fn match_nested_rule(nested_rule: NestedRule) -> Result<registry_types::Rule, Status> {
+        let rule = Self::match_rule(
+            nested_rule
+                .rule
+                .ok_or(Status::invalid_argument("Nested rule must be provided"))?,
+        )?;
+
+        if matches!(rule, registry_types::Rule::StateChange { .. }) {
+            return Err(Status::invalid_argument(
+                "State change rules cannot be nested within composite rules",
+            ));
+        }
+
+        Ok(rule)
+    }
+
+    fn match_nested_rules(
+        nested_rules: Vec<NestedRule>,
+    ) -> Result<Vec<registry_types::Rule>, Status> {
+        if nested_rules.is_empty() {
+            return Err(Status::invalid_argument(
+                "Composite rules must contain at least one rule",
+            ));
+        }
+
+        nested_rules
+            .into_iter()
+            .map(Self::match_nested_rule)
+            .collect()
+    }
+
+    fn match_preview_limit(limit: u32) -> Result<usize, Status> {
+        match limit {
+            0 => Ok(DEFAULT_PREVIEW_LIMIT as usize),
+            limit if limit > MAX_PREVIEW_LIMIT => Err(Status::invalid_argument(format!(
+                "Limit may not exceed {}",
+                MAX_PREVIEW_LIMIT
+            ))),
+            limit => Ok(limit as usize),
+        }
+    }
 
+    async fn match_preview_range(
+        &self,
+        range: Option<preview_rule_request::Range>,
+        chain_id: &ChainId,
+    ) -> Result<(u64, u64), Status> {
+        match range.ok_or(Status::invalid_argument("Range must be provided"))? {
+            preview_rule_request::Range::BlockRange(block_range) => {
+                if block_range.start_block_height > block_range.end_block_height {
+                    return Err(Status::invalid_argument(
+                        "Start block height must not exceed end block height",
+                    ));
+                }
+
+                if block_range.end_block_height - block_range.start_block_height
+                    >= MAX_PREVIEW_RANGE_BLOCKS
+                {
+                    return Err(Status::invalid_argument(format!(
+                        "Block range may not exceed {} blocks",
+                        MAX_PREVIEW_RANGE_BLOCKS
+                    )));
+                }
+
+                Ok((block_range.start_block_height, block_range.end_block_height))
+            }
+            preview_rule_request::Range::LastBlocks(0) => Err(Status::invalid_argument(
+                "Last blocks must be greater than 0",
+            )),
+            preview_rule_request::Range::LastBlocks(last_blocks)
+                if last_blocks > MAX_PREVIEW_RANGE_BLOCKS =>
+            {
+                Err(Status::invalid_argument(format!(
+                    "Last blocks may not exceed {}",
+                    MAX_PREVIEW_RANGE_BLOCKS
+                )))
+            }
+            preview_rule_request::Range::LastBlocks(last_blocks) => {
+                let last_indexed_block = self
+                    .get_delta_lake_client(chain_id)
+                    .get_latest_block_metadata()
+                    .await
+                    .and_then(|metadata| {
+                        metadata
+                            .last_indexed_block
+                            .parse::<u64>()
+                            .context("Failed to parse Delta Lake metadata")
+                    })
+                    .map_err(|err| {
+                        Status::internal(format!("Failed to get last indexed block: {:?}", err))
+                    })?;
+
+                Ok((
+                    last_indexed_block.saturating_sub(last_blocks - 1),
+                    last_indexed_block,
+                ))
+            }
+        }
+    }
+
+    /// Starts a block stream for the request, without persisting it. Restored streams resume from
+    /// the block after their last published block, rather than the requested start block.
+    async fn start_block_stream(
+        &self,
+        request: StartStreamRequest,
+        resume_from_last_published_block: bool,
+    ) -> Result<String, Status> {
         let rule = request
             .rule
             .ok_or(Status::invalid_argument("Rule must be provided"))?;
 
-        let rule = match rule {
+        let rule = Self::match_rule(match rule {
             start_stream_request::Rule::ActionAnyRule(action_any) => {
-                registry_types::Rule::ActionAny {
-                    affected_account_id: action_any.affected_account_id,
-                    status: Self::match_status(action_any.status)?,
-                }
+                nested_rule::Rule::ActionAnyRule(action_any)
             }
             start_stream_request::Rule::ActionFunctionCallRule(action_function_call) => {
-                registry_types::Rule::ActionFunctionCall {
-                    affected_account_id: action_function_call.affected_account_id,
-                    status: Self::match_status(action_function_call.status)?,
-                    function: action_function_call.function_name,
-                }
+                nested_rule::Rule::ActionFunctionCallRule(action_function_call)
             }
-        };
+            start_stream_request::Rule::EventRule(event) => nested_rule::Rule::EventRule(event),
+            start_stream_request::Rule::AllRule(all) => nested_rule::Rule::AllRule(all),
+            start_stream_request::Rule::AnyRule(any) => nested_rule::Rule::AnyRule(any),
+            start_stream_request::Rule::NotRule(not) => nested_rule::Rule::NotRule(Box::new(not)),
+            start_stream_request::Rule::ActionKindRule(action_kind) => {
+                nested_rule::Rule::ActionKindRule(action_kind)
+            }
+            start_stream_request::Rule::StateChangeRule(state_change) => {
+                nested_rule::Rule::StateChangeRule(state_change)
+            }
+        })?;
+
+        let chain_id = Self::match_chain_id(request.chain)?;
+        let block_delivery = Self::match_block_delivery(request.block_delivery)?;
+        let block_sink_config = self.match_block_sink(request.sink)?;
 
         let account_id = near_indexer_primitives::types::AccountId::try_from(request.account_id)
             .map_err(|err| {
@@ -97,33 +508,227 @@ impl blockstreamer::block_streamer_server::BlockStreamer for BlockStreamerServic
             function_name: request.function_name,
         };
 
-        let lock = self.get_block_streams_lock()?;
-        match lock.get(&indexer_config.get_hash_id()) {
-            Some(_) => return Err(Status::already_exists("Block stream already exists")),
-            None => drop(lock),
+        let mut start_block_height = request.start_block_height;
+
+        if resume_from_last_published_block {
+            let last_published_block = self
+                .redis_client
+                .get::<String, u64>(indexer_config.get_last_published_block_key())
+                .await
+                .map_err(|err| {
+                    Status::internal(format!("Failed to get last_published_block: {}", err))
+                })?;
+
+            if let Some(last_published_block) = last_published_block {
+                start_block_height = last_published_block + 1;
+            }
+        }
+
+        if self
+            .get_block_streams_lock()?
+            .contains_key(&indexer_config.get_hash_id())
+        {
+            return Err(Status::already_exists("Block stream already exists"));
+        }
+
+        let block_sink = crate::block_sink::build(
+            &block_sink_config,
+            self.redis_client.clone(),
+            &request.redis_stream,
+            &indexer_config.get_last_published_block_key(),
+        )
+        .await
+        .map_err(|err| Status::internal(format!("Failed to create block sink: {:?}", err)))?;
+
+        // Another request for the same stream may have started it while the sink was built, so
+        // check again, holding the lock until the stream is inserted
+        let mut lock = self.get_block_streams_lock()?;
+
+        if lock.contains_key(&indexer_config.get_hash_id()) {
+            return Err(Status::already_exists("Block stream already exists"));
         }
 
         let mut block_stream = block_stream::BlockStream::new(
             indexer_config.clone(),
-            self.chain_id.clone(),
+            chain_id.clone(),
             request.version,
             request.redis_stream,
+            self.redis_stream_watermarks,
+            block_delivery,
         );
 
         block_stream
             .start(
-                request.start_block_height,
+                start_block_height,
                 self.redis_client.clone(),
-                self.delta_lake_client.clone(),
+                self.get_delta_lake_client(&chain_id),
                 self.lake_s3_config.clone(),
+                block_sink,
             )
             .map_err(|_| Status::internal("Failed to start block stream"))?;
 
-        let mut lock = self.get_block_streams_lock()?;
         lock.insert(indexer_config.get_hash_id(), block_stream);
 
+        Ok(indexer_config.get_hash_id())
+    }
+
+    /// Cancels every block stream, waiting for blocks part way through publishing to finish.
+    /// Streams remain persisted, so that they are restored once the block streamer restarts.
+    pub async fn stop_all_block_streams(&self) -> anyhow::Result<()> {
+        let block_streams: Vec<block_stream::BlockStream> = self
+            .block_streams
+            .lock()
+            .map_err(|err| anyhow::anyhow!("Failed to acquire lock: {}", err))?
+            .drain()
+            .map(|(_, block_stream)| block_stream)
+            .collect();
+
+        tracing::info!("Stopping {} block streams", block_streams.len());
+
+        futures::future::join_all(
+            block_streams
+                .into_iter()
+                .map(|mut block_stream| async move {
+                    if let Err(err) = block_stream.cancel().await {
+                        tracing::error!(
+                            account_id = block_stream.indexer_config.account_id.as_str(),
+                            function_name = block_stream.indexer_config.function_name,
+                            "Failed to stop block stream: {:?}",
+                            err
+                        );
+                    }
+                }),
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// Restarts every persisted block stream from the block after its last published block, so
+    /// that streams survive restarts of the block streamer.
+    pub async fn restore_block_streams(&self) -> anyhow::Result<()> {
+        let persisted_block_streams = self
+            .redis_client
+            .hgetall(BLOCK_STREAMS_KEY.to_string())
+            .await
+            .context("Failed to get persisted block streams")?;
+
+        tracing::info!("Restoring {} block streams", persisted_block_streams.len());
+
+        for (stream_id, encoded_request) in persisted_block_streams {
+            let request = match StartStreamRequest::decode(encoded_request.as_slice()) {
+                Ok(request) => request,
+                Err(err) => {
+                    tracing::error!(
+                        stream_id,
+                        "Failed to decode persisted block stream: {:?}",
+                        err
+                    );
+                    self.remove_persisted_block_stream(stream_id).await;
+                    continue;
+                }
+            };
+
+            if let Err(status) = self.start_block_stream(request, true).await {
+                tracing::error!(
+                    stream_id,
+                    "Failed to restore block stream: {}",
+                    status.message()
+                );
+                self.remove_persisted_block_stream(stream_id).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes a block stream which could not be restored, so that it is absent from
+    /// `ListStreams` rather than silently missing, and is started again by the coordinator.
+    async fn remove_persisted_block_stream(&self, stream_id: String) {
+        if let Err(err) = self
+            .redis_client
+            .hdel(BLOCK_STREAMS_KEY.to_string(), stream_id.clone())
+            .await
+        {
+            tracing::error!(
+                stream_id,
+                "Failed to remove persisted block stream: {:?}",
+                err
+            );
+        }
+    }
+
+    /// Maps published blocks to `TailStream` responses. Clients which lag behind are sent a
+    /// `DataLoss` error and the responses end, rather than continuing past the missed blocks.
+    fn tail_responses(
+        published_blocks: tokio::sync::broadcast::Receiver<crate::block_sink::PublishedBlock>,
+    ) -> impl futures::Stream<Item = Result<TailStreamResponse, Status>> {
+        tokio_stream::wrappers::BroadcastStream::new(published_blocks)
+            .map(|published_block| match published_block {
+                Ok(published_block) => Ok(TailStreamResponse {
+                    block_height: published_block.block_height,
+                    matches: published_block
+                        .fields
+                        .into_iter()
+                        .find_map(|(field, value)| (field == "matches").then_some(value))
+                        .unwrap_or_default(),
+                }),
+                Err(tokio_stream::wrappers::errors::BroadcastStreamRecvError::Lagged(skipped)) => {
+                    Err(Status::data_loss(format!(
+                        "Tailing client lagged behind by {} blocks",
+                        skipped
+                    )))
+                }
+            })
+            .scan(false, |has_lagged, response| {
+                if *has_lagged {
+                    return futures::future::ready(None);
+                }
+
+                *has_lagged = response.is_err();
+
+                futures::future::ready(Some(response))
+            })
+    }
+}
+
+#[tonic::async_trait]
+impl blockstreamer::block_streamer_server::BlockStreamer for BlockStreamerService {
+    async fn start_stream(
+        &self,
+        request: Request<blockstreamer::StartStreamRequest>,
+    ) -> Result<Response<blockstreamer::StartStreamResponse>, Status> {
+        let request = request.into_inner();
+
+        let stream_id = self.start_block_stream(request.clone(), false).await?;
+
+        if let Err(err) = self
+            .redis_client
+            .hset(
+                BLOCK_STREAMS_KEY.to_string(),
+                stream_id.clone(),
+                request.encode_to_vec(),
+            )
+            .await
+        {
+            // Streams which are not persisted would not be restored, so are not left running
+            let block_stream = self.get_block_streams_lock()?.remove(&stream_id);
+
+            if let Some(mut block_stream) = block_stream {
+                block_stream
+                    .cancel()
+                    .await
+                    .map_err(|_| Status::internal("Failed to cancel block stream"))?;
+            }
+
+            return Err(Status::internal(format!(
+                "Failed to persist block stream: {}",
+                err
+            )));
+        }
+
         Ok(Response::new(blockstreamer::StartStreamResponse {
-            stream_id: indexer_config.get_hash_id(),
+            stream_id,
         }))
     }
 
@@ -140,19 +745,30 @@ impl blockstreamer::block_streamer_server::BlockStreamer for BlockStreamerServic
             lock.remove(&stream_id)
         };
 
-        match exising_block_stream {
-            None => {
-                return Err(Status::not_found(format!(
-                    "Block stream with id {} not found",
-                    stream_id
-                )))
-            }
-            Some(mut block_stream) => {
-                block_stream
-                    .cancel()
-                    .await
-                    .map_err(|_| Status::internal("Failed to cancel block stream"))?;
-            }
+        let is_running = exising_block_stream.is_some();
+
+        if let Some(mut block_stream) = exising_block_stream {
+            block_stream
+                .cancel()
+                .await
+                .map_err(|_| Status::internal("Failed to cancel block stream"))?;
+        }
+
+        // Persisted streams are removed even when not running, e.g. when a previous stop failed
+        // to remove them, so that they are not restored again
+        let was_persisted = self
+            .redis_client
+            .hdel(BLOCK_STREAMS_KEY.to_string(), stream_id.clone())
+            .await
+            .map_err(|err| {
+                Status::internal(format!("Failed to remove persisted block stream: {}", err))
+            })?;
+
+        if !is_running && !was_persisted {
+            return Err(Status::not_found(format!(
+                "Block stream with id {} not found",
+                stream_id
+            )));
         }
 
         Ok(Response::new(blockstreamer::StopStreamResponse {
@@ -160,6 +776,83 @@ impl blockstreamer::block_streamer_server::BlockStreamer for BlockStreamerServic
         }))
     }
 
+    type TailStreamStream = std::pin::Pin<
+        Box<dyn futures::Stream<Item = Result<TailStreamResponse, Status>> + Send + 'static>,
+    >;
+
+    async fn tail_stream(
+        &self,
+        request: Request<blockstreamer::TailStreamRequest>,
+    ) -> Result<Response<Self::TailStreamStream>, Status> {
+        let stream_id = request.into_inner().stream_id;
+
+        let published_blocks = self
+            .get_block_streams_lock()?
+            .get(&stream_id)
+            .ok_or_else(|| {
+                Status::not_found(format!("Block stream with id {} not found", stream_id))
+            })?
+            .subscribe();
+
+        let responses = Self::tail_responses(published_blocks);
+
+        Ok(Response::new(Box::pin(responses)))
+    }
+
+    async fn preview_rule(
+        &self,
+        request: Request<blockstreamer::PreviewRuleRequest>,
+    ) -> Result<Response<blockstreamer::PreviewRuleResponse>, Status> {
+        let request = request.into_inner();
+
+        let rule = Self::match_rule(
+            request
+                .rule
+                .and_then(|rule| rule.rule)
+                .ok_or(Status::invalid_argument("Rule must be provided"))?,
+        )?;
+        let chain_id = Self::match_chain_id(request.chain)?;
+        let limit = Self::match_preview_limit(request.limit)?;
+        let (start_block_height, end_block_height) =
+            self.match_preview_range(request.range, &chain_id).await?;
+
+        let rule_preview = crate::rule_preview::preview_rule(
+            &rule,
+            &chain_id,
+            start_block_height,
+            end_block_height,
+            limit,
+            &self.get_delta_lake_client(&chain_id),
+            self.lake_s3_config.clone(),
+        )
+        .await
+        .map_err(|err| Status::internal(format!("Failed to preview rule: {:?}", err)))?;
+
+        let blocks = rule_preview
+            .blocks
+            .into_iter()
+            .map(|previewed_block| {
+                let payloads: Vec<_> = previewed_block
+                    .matches
+                    .iter()
+                    .map(|indexer_rule_match| &indexer_rule_match.payload)
+                    .collect();
+
+                Ok(PreviewedBlock {
+                    block_height: previewed_block.block_height,
+                    matches: serde_json::to_string(&payloads).map_err(|err| {
+                        Status::internal(format!("Failed to serialize matches: {}", err))
+                    })?,
+                })
+            })
+            .collect::<Result<Vec<_>, Status>>()?;
+
+        Ok(Response::new(blockstreamer::PreviewRuleResponse {
+            blocks,
+            last_block_height: rule_preview.last_block_height,
+        }))
+    }
+
     async fn list_streams(
         &self,
         _request: Request<blockstreamer::ListStreamsRequest>,
@@ -172,6 +865,43 @@ impl blockstreamer::block_streamer_server::BlockStreamer for BlockStreamerServic
                 account_id: block_stream.indexer_config.account_id.to_string(),
                 function_name: block_stream.indexer_config.function_name.clone(),
                 version: block_stream.version,
+                chain: match block_stream.chain_id {
+                    ChainId::Mainnet => Chain::Mainnet,
+                    ChainId::Testnet => Chain::Testnet,
+                }
+                .into(),
+                backfill_progress: Some(BackfillProgress {
+                    index_files_total: block_stream
+                        .backfill_progress
+                        .index_files_total
+                        .load(Ordering::Relaxed),
+                    index_files_processed: block_stream
+                        .backfill_progress
+                        .index_files_processed
+                        .load(Ordering::Relaxed),
+                    blocks_published: block_stream
+                        .backfill_progress
+                        .blocks_published
+                        .load(Ordering::Relaxed),
+                }),
+                state: Self::match_stream_state(block_stream).into(),
+                last_published_block_height: block_stream
+                    .status
+                    .last_published_block
+                    .load(Ordering::Relaxed),
+                blocks_scanned: block_stream.status.blocks_scanned.load(Ordering::Relaxed),
+                matches_published: block_stream
+                    .status
+                    .matches_published
+                    .load(Ordering::Relaxed),
+                last_error: block_stream
+                    .status
+                    .last_error()
+                    .map(|stream_error| StreamError {
+                        message: stream_error.message,
+                        timestamp_ms: stream_error.occurred_at.timestamp_millis(),
+                    }),
+                restarts: block_stream.status.restarts.load(Ordering::Relaxed),
             })
             .collect();
 
@@ -189,7 +919,7 @@ mod tests {
 
     use blockstreamer::block_streamer_server::BlockStreamer;
 
-    fn create_block_streamer_service() -> BlockStreamerService {
+    fn create_mock_delta_lake_client() -> crate::delta_lake_client::DeltaLakeClient {
         let mut mock_delta_lake_client = crate::delta_lake_client::DeltaLakeClient::default();
         mock_delta_lake_client
             .expect_get_latest_block_metadata()
@@ -203,20 +933,52 @@ mod tests {
                 })
             });
         mock_delta_lake_client
-            .expect_list_matching_block_heights()
-            .returning(|_, _| Ok(vec![]));
+            .expect_list_index_files()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_delta_lake_client
+            .expect_stream_matching_block_heights()
+            .returning(|_, _| futures::stream::empty().boxed());
+
+        mock_delta_lake_client
+    }
 
+    fn create_mock_redis_client() -> crate::redis::RedisClient {
         let mut mock_redis_client = crate::redis::RedisClient::default();
         mock_redis_client
-            .expect_xadd::<String, u64>()
-            .returning(|_, _| Ok(()));
+            .expect_xadd_block()
+            .returning(|_, _, _, _| Ok(true));
+        mock_redis_client
+            .expect_xlen::<String>()
+            .returning(|_| Ok(0));
+        mock_redis_client
+            .expect_hset::<String, Vec<u8>>()
+            .returning(|_, _, _| Ok(()));
+        mock_redis_client
+            .expect_hdel::<String>()
+            .returning(|_, _| Ok(true));
+        mock_redis_client
+            .expect_get::<String, String>()
+            .returning(|_| Ok(None));
+
+        mock_redis_client
+    }
 
+    fn create_block_streamer_service() -> BlockStreamerService {
+        create_block_streamer_service_with_redis_client(create_mock_redis_client())
+    }
+
+    fn create_block_streamer_service_with_redis_client(
+        mock_redis_client: crate::redis::RedisClient,
+    ) -> BlockStreamerService {
         let lake_s3_config = crate::test_utils::create_mock_lake_s3_config(&[107503704]);
 
         BlockStreamerService::new(
             std::sync::Arc::new(mock_redis_client),
-            std::sync::Arc::new(mock_delta_lake_client),
+            std::sync::Arc::new(create_mock_delta_lake_client()),
+            std::sync::Arc::new(create_mock_delta_lake_client()),
             lake_s3_config,
+            block_stream::RedisStreamWatermarks::default(),
+            crate::block_sink::BlockSinkPolicy::default(),
         )
     }
 
@@ -236,9 +998,13 @@ mod tests {
                 function_name: "test".to_string(),
                 version: 0,
                 redis_stream: "stream".to_string(),
+                chain: Chain::Mainnet.into(),
+                block_delivery: None,
+                sink: None,
                 rule: Some(start_stream_request::Rule::ActionAnyRule(ActionAnyRule {
                     affected_account_id: "queryapi.dataplatform.near".to_string(),
                     status: 1,
+                    affected_account_role: AffectedAccountRole::Any.into(),
                 })),
             }))
             .await
@@ -249,30 +1015,488 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn stops_a_block_stream() {
+    async fn starts_block_streams_on_each_chain() {
         let block_streamer_service = create_block_streamer_service();
 
-        assert_eq!(
+        for (function_name, chain) in [("mainnet", Chain::Mainnet), ("testnet", Chain::Testnet)] {
             block_streamer_service
-                .list_streams(Request::new(ListStreamsRequest {}))
+                .start_stream(Request::new(StartStreamRequest {
+                    start_block_height: 0,
+                    account_id: "morgs.near".to_string(),
+                    function_name: function_name.to_string(),
+                    version: 0,
+                    redis_stream: "stream".to_string(),
+                    chain: chain.into(),
+                    block_delivery: None,
+                    sink: None,
+                    rule: Some(start_stream_request::Rule::ActionAnyRule(ActionAnyRule {
+                        affected_account_id: "queryapi.dataplatform.near".to_string(),
+                        status: 1,
+                        affected_account_role: AffectedAccountRole::Any.into(),
+                    })),
+                }))
                 .await
-                .unwrap()
-                .into_inner()
-                .streams
-                .len(),
-            0
-        );
+                .unwrap();
+        }
 
-        block_streamer_service
-            .start_stream(Request::new(StartStreamRequest {
-                start_block_height: 0,
+        let mut streams = block_streamer_service
+            .list_streams(Request::new(ListStreamsRequest {}))
+            .await
+            .unwrap()
+            .into_inner()
+            .streams;
+        streams.sort_by(|a, b| a.function_name.cmp(&b.function_name));
+
+        assert_eq!(
+            streams
+                .iter()
+                .map(|stream| (stream.function_name.as_str(), stream.chain()))
+                .collect::<Vec<_>>(),
+            vec![("mainnet", Chain::Mainnet), ("testnet", Chain::Testnet)]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_chains() {
+        let block_streamer_service = create_block_streamer_service();
+
+        let result = block_streamer_service
+            .start_stream(Request::new(StartStreamRequest {
+                start_block_height: 0,
+                account_id: "morgs.near".to_string(),
+                function_name: "test".to_string(),
+                version: 0,
+                redis_stream: "stream".to_string(),
+                chain: 2,
+                block_delivery: None,
+                sink: None,
+                rule: Some(start_stream_request::Rule::ActionAnyRule(ActionAnyRule {
+                    affected_account_id: "queryapi.dataplatform.near".to_string(),
+                    status: 1,
+                    affected_account_role: AffectedAccountRole::Any.into(),
+                })),
+            }))
+            .await;
+
+        assert_eq!(result.unwrap_err().code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn rejects_sinks_not_allowed_by_policy() {
+        let block_streamer_service = create_block_streamer_service();
+
+        for sink in [
+            start_stream_request::Sink::FileSink(FileSink {
+                path: "../../etc/passwd".to_string(),
+            }),
+            start_stream_request::Sink::JetStreamSink(JetStreamSink {
+                url: "nats://internal:4222".to_string(),
+                subject: "blocks".to_string(),
+            }),
+        ] {
+            let result = block_streamer_service
+                .start_stream(Request::new(StartStreamRequest {
+                    start_block_height: 0,
+                    account_id: "morgs.near".to_string(),
+                    function_name: "test".to_string(),
+                    version: 0,
+                    redis_stream: "stream".to_string(),
+                    chain: Chain::Mainnet.into(),
+                    block_delivery: None,
+                    sink: Some(sink),
+                    rule: Some(start_stream_request::Rule::ActionAnyRule(ActionAnyRule {
+                        affected_account_id: "queryapi.dataplatform.near".to_string(),
+                        status: 1,
+                        affected_account_role: AffectedAccountRole::Any.into(),
+                    })),
+                }))
+                .await;
+
+            assert_eq!(result.unwrap_err().code(), tonic::Code::InvalidArgument);
+        }
+
+        let lock = block_streamer_service.get_block_streams_lock().unwrap();
+        assert_eq!(lock.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn starts_a_block_stream_with_event_rule() {
+        let block_streamer_service = create_block_streamer_service();
+
+        block_streamer_service
+            .start_stream(Request::new(StartStreamRequest {
+                start_block_height: 0,
+                account_id: "morgs.near".to_string(),
+                function_name: "test".to_string(),
+                version: 0,
+                redis_stream: "stream".to_string(),
+                chain: Chain::Mainnet.into(),
+                block_delivery: None,
+                sink: None,
+                rule: Some(start_stream_request::Rule::EventRule(EventRule {
+                    contract_account_id: "nft.near".to_string(),
+                    standard: "nep171".to_string(),
+                    version: "*".to_string(),
+                    event: "nft_mint".to_string(),
+                    data_predicate: Some(DataPredicate {
+                        path: "data[].owner_id".to_string(),
+                        operator: ComparisonOperator::Matches.into(),
+                        value: "*.near".to_string(),
+                    }),
+                })),
+            }))
+            .await
+            .unwrap();
+
+        let lock = block_streamer_service.get_block_streams_lock().unwrap();
+        let block_stream = lock.get("16210176318434468568").unwrap();
+        assert_eq!(
+            block_stream.indexer_config.rule,
+            registry_types::Rule::Event {
+                contract_account_id: "nft.near".to_string(),
+                standard: "nep171".to_string(),
+                version: "*".to_string(),
+                event: "nft_mint".to_string(),
+                data_predicate: Some(registry_types::DataPredicate {
+                    path: "data[].owner_id".to_string(),
+                    operator: registry_types::ComparisonOperator::Matches,
+                    value: "*.near".to_string(),
+                }),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_non_numeric_values_for_numeric_comparisons() {
+        let block_streamer_service = create_block_streamer_service();
+
+        let result = block_streamer_service
+            .start_stream(Request::new(StartStreamRequest {
+                start_block_height: 0,
+                account_id: "morgs.near".to_string(),
+                function_name: "test".to_string(),
+                version: 0,
+                redis_stream: "stream".to_string(),
+                chain: Chain::Mainnet.into(),
+                block_delivery: None,
+                sink: None,
+                rule: Some(start_stream_request::Rule::EventRule(EventRule {
+                    contract_account_id: "token.sweat".to_string(),
+                    standard: "nep141".to_string(),
+                    version: "*".to_string(),
+                    event: "ft_transfer".to_string(),
+                    data_predicate: Some(DataPredicate {
+                        path: "data[].amount".to_string(),
+                        operator: ComparisonOperator::GreaterThan.into(),
+                        value: "1 SWEAT".to_string(),
+                    }),
+                })),
+            }))
+            .await;
+
+        assert_eq!(result.unwrap_err().code(), tonic::Code::InvalidArgument);
+
+        let lock = block_streamer_service.get_block_streams_lock().unwrap();
+        assert!(lock.is_empty());
+    }
+
+    #[tokio::test]
+    async fn starts_a_block_stream_with_function_call_filters() {
+        let block_streamer_service = create_block_streamer_service();
+
+        block_streamer_service
+            .start_stream(Request::new(StartStreamRequest {
+                start_block_height: 0,
+                account_id: "morgs.near".to_string(),
+                function_name: "test".to_string(),
+                version: 0,
+                redis_stream: "stream".to_string(),
+                chain: Chain::Mainnet.into(),
+                block_delivery: None,
+                sink: None,
+                rule: Some(start_stream_request::Rule::ActionFunctionCallRule(
+                    ActionFunctionCallRule {
+                        affected_account_id: "nft.near".to_string(),
+                        function_name: "nft_transfer".to_string(),
+                        status: 1,
+                        affected_account_role: AffectedAccountRole::Any.into(),
+                        args_predicates: vec![DataPredicate {
+                            path: "token_id".to_string(),
+                            operator: ComparisonOperator::LessThan.into(),
+                            value: "1000".to_string(),
+                        }],
+                        min_deposit: Some("1".to_string()),
+                        min_gas: None,
+                    },
+                )),
+            }))
+            .await
+            .unwrap();
+
+        let lock = block_streamer_service.get_block_streams_lock().unwrap();
+        let block_stream = lock.get("16210176318434468568").unwrap();
+        assert_eq!(
+            block_stream.indexer_config.rule,
+            registry_types::Rule::ActionFunctionCall {
+                affected_account_id: "nft.near".to_string(),
+                status: registry_types::Status::Success,
+                affected_account_role: registry_types::AffectedAccountRole::Any,
+                function: "nft_transfer".to_string(),
+                args_predicates: vec![registry_types::DataPredicate {
+                    path: "token_id".to_string(),
+                    operator: registry_types::ComparisonOperator::LessThan,
+                    value: "1000".to_string(),
+                }],
+                min_deposit: Some("1".to_string()),
+                min_gas: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn starts_a_block_stream_with_action_kind_rule() {
+        let block_streamer_service = create_block_streamer_service();
+
+        block_streamer_service
+            .start_stream(Request::new(StartStreamRequest {
+                start_block_height: 0,
+                account_id: "morgs.near".to_string(),
+                function_name: "test".to_string(),
+                version: 0,
+                redis_stream: "stream".to_string(),
+                chain: Chain::Mainnet.into(),
+                block_delivery: None,
+                sink: None,
+                rule: Some(start_stream_request::Rule::ActionKindRule(ActionKindRule {
+                    affected_account_id: "*.exchange.near".to_string(),
+                    status: 1,
+                    affected_account_role: AffectedAccountRole::Any.into(),
+                    action_kinds: vec![ActionKind::Transfer.into(), ActionKind::Stake.into()],
+                    min_amount: Some("1000000000000000000000000000".to_string()),
+                    max_amount: None,
+                })),
+            }))
+            .await
+            .unwrap();
+
+        let lock = block_streamer_service.get_block_streams_lock().unwrap();
+        let block_stream = lock.get("16210176318434468568").unwrap();
+        assert_eq!(
+            block_stream.indexer_config.rule,
+            registry_types::Rule::ActionKind {
+                affected_account_id: "*.exchange.near".to_string(),
+                status: registry_types::Status::Success,
+                affected_account_role: registry_types::AffectedAccountRole::Any,
+                action_kinds: vec![
+                    registry_types::ActionKind::Transfer,
+                    registry_types::ActionKind::Stake
+                ],
+                min_amount: Some("1000000000000000000000000000".to_string()),
+                max_amount: None,
+            }
+        );
+    }
+
+    #[test]
+    fn matches_unspecified_affected_account_role_as_any() {
+        assert_eq!(
+            BlockStreamerService::match_affected_account_role(
+                AffectedAccountRole::Unspecified.into()
+            )
+            .unwrap(),
+            registry_types::AffectedAccountRole::Any
+        );
+        assert_eq!(
+            BlockStreamerService::match_affected_account_role(AffectedAccountRole::Any.into())
+                .unwrap(),
+            registry_types::AffectedAccountRole::Any
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_min_deposit() {
+        let block_streamer_service = create_block_streamer_service();
+
+        let result = block_streamer_service
+            .start_stream(Request::new(StartStreamRequest {
+                start_block_height: 0,
+                account_id: "morgs.near".to_string(),
+                function_name: "test".to_string(),
+                version: 0,
+                redis_stream: "stream".to_string(),
+                chain: Chain::Mainnet.into(),
+                block_delivery: None,
+                sink: None,
+                rule: Some(start_stream_request::Rule::ActionFunctionCallRule(
+                    ActionFunctionCallRule {
+                        affected_account_id: "nft.near".to_string(),
+                        function_name: "nft_transfer".to_string(),
+                        status: 1,
+                        affected_account_role: AffectedAccountRole::Any.into(),
+                        args_predicates: vec![],
+                        min_deposit: Some("1 NEAR".to_string()),
+                        min_gas: None,
+                    },
+                )),
+            }))
+            .await;
+
+        assert_eq!(result.unwrap_err().code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn starts_a_block_stream_with_composite_rule() {
+        let block_streamer_service = create_block_streamer_service();
+
+        block_streamer_service
+            .start_stream(Request::new(StartStreamRequest {
+                start_block_height: 0,
+                account_id: "morgs.near".to_string(),
+                function_name: "test".to_string(),
+                version: 0,
+                redis_stream: "stream".to_string(),
+                chain: Chain::Mainnet.into(),
+                block_delivery: None,
+                sink: None,
+                rule: Some(start_stream_request::Rule::AllRule(AllRule {
+                    rules: vec![
+                        NestedRule {
+                            rule: Some(nested_rule::Rule::ActionAnyRule(ActionAnyRule {
+                                affected_account_id: "queryapi.dataplatform.near".to_string(),
+                                status: 1,
+                                affected_account_role: AffectedAccountRole::Any.into(),
+                            })),
+                        },
+                        NestedRule {
+                            rule: Some(nested_rule::Rule::NotRule(Box::new(NotRule {
+                                rule: Some(Box::new(NestedRule {
+                                    rule: Some(nested_rule::Rule::ActionFunctionCallRule(
+                                        ActionFunctionCallRule {
+                                            affected_account_id: "*".to_string(),
+                                            function_name: "register".to_string(),
+                                            status: 3,
+                                            affected_account_role: AffectedAccountRole::Any.into(),
+                                            args_predicates: vec![],
+                                            min_deposit: None,
+                                            min_gas: None,
+                                        },
+                                    )),
+                                })),
+                            }))),
+                        },
+                    ],
+                })),
+            }))
+            .await
+            .unwrap();
+
+        let lock = block_streamer_service.get_block_streams_lock().unwrap();
+        let block_stream = lock.get("16210176318434468568").unwrap();
+        assert_eq!(
+            block_stream.indexer_config.rule,
+            registry_types::Rule::All {
+                rules: vec![
+                    registry_types::Rule::ActionAny {
+                        affected_account_id: "queryapi.dataplatform.near".to_string(),
+                        status: registry_types::Status::Success,
+                        affected_account_role: registry_types::AffectedAccountRole::Any,
+                    },
+                    registry_types::Rule::Not {
+                        rule: Box::new(registry_types::Rule::ActionFunctionCall {
+                            affected_account_id: "*".to_string(),
+                            function: "register".to_string(),
+                            status: registry_types::Status::Any,
+                            affected_account_role: registry_types::AffectedAccountRole::Any,
+                            args_predicates: vec![],
+                            min_deposit: None,
+                            min_gas: None,
+                        }),
+                    },
+                ],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_empty_composite_rule() {
+        let block_streamer_service = create_block_streamer_service();
+
+        let result = block_streamer_service
+            .start_stream(Request::new(StartStreamRequest {
+                start_block_height: 0,
+                account_id: "morgs.near".to_string(),
+                function_name: "test".to_string(),
+                version: 0,
+                redis_stream: "stream".to_string(),
+                chain: Chain::Mainnet.into(),
+                block_delivery: None,
+                sink: None,
+                rule: Some(start_stream_request::Rule::AnyRule(AnyRule {
+                    rules: vec![],
+                })),
+            }))
+            .await;
+
+        assert_eq!(result.unwrap_err().code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn rejects_nested_state_change_rule() {
+        let block_streamer_service = create_block_streamer_service();
+
+        let result = block_streamer_service
+            .start_stream(Request::new(StartStreamRequest {
+                start_block_height: 0,
                 account_id: "morgs.near".to_string(),
                 function_name: "test".to_string(),
                 version: 0,
                 redis_stream: "stream".to_string(),
+                chain: Chain::Mainnet.into(),
+                block_delivery: None,
+                sink: None,
+                rule: Some(start_stream_request::Rule::AnyRule(AnyRule {
+                    rules: vec![NestedRule {
+                        rule: Some(nested_rule::Rule::StateChangeRule(StateChangeRule {
+                            affected_account_id: "morgs.near".to_string(),
+                            change_kinds: vec![StateChangeKind::AccessKeyAdd.into()],
+                        })),
+                    }],
+                })),
+            }))
+            .await;
+
+        assert_eq!(result.unwrap_err().code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn stops_a_block_stream() {
+        let block_streamer_service = create_block_streamer_service();
+
+        assert_eq!(
+            block_streamer_service
+                .list_streams(Request::new(ListStreamsRequest {}))
+                .await
+                .unwrap()
+                .into_inner()
+                .streams
+                .len(),
+            0
+        );
+
+        block_streamer_service
+            .start_stream(Request::new(StartStreamRequest {
+                start_block_height: 0,
+                account_id: "morgs.near".to_string(),
+                function_name: "test".to_string(),
+                version: 0,
+                redis_stream: "stream".to_string(),
+                chain: Chain::Mainnet.into(),
+                block_delivery: None,
+                sink: None,
                 rule: Some(start_stream_request::Rule::ActionAnyRule(ActionAnyRule {
                     affected_account_id: "queryapi.dataplatform.near".to_string(),
                     status: 1,
+                    affected_account_role: AffectedAccountRole::Any.into(),
                 })),
             }))
             .await
@@ -308,4 +1532,346 @@ mod tests {
             0
         );
     }
+
+    #[tokio::test]
+    async fn restores_persisted_block_streams() {
+        let request = StartStreamRequest {
+            start_block_height: 0,
+            account_id: "morgs.near".to_string(),
+            function_name: "test".to_string(),
+            version: 0,
+            redis_stream: "stream".to_string(),
+            chain: Chain::Mainnet.into(),
+            block_delivery: None,
+            sink: None,
+            rule: Some(start_stream_request::Rule::ActionAnyRule(ActionAnyRule {
+                affected_account_id: "queryapi.dataplatform.near".to_string(),
+                status: 1,
+                affected_account_role: AffectedAccountRole::Any.into(),
+            })),
+        };
+
+        let mut mock_redis_client = create_mock_redis_client();
+        mock_redis_client
+            .expect_hgetall::<String>()
+            .with(mockall::predicate::eq(BLOCK_STREAMS_KEY.to_string()))
+            .returning(move |_| {
+                Ok(HashMap::from([(
+                    "16210176318434468568".to_string(),
+                    request.encode_to_vec(),
+                )]))
+            });
+        mock_redis_client
+            .expect_get::<String, u64>()
+            .with(mockall::predicate::eq(
+                "morgs.near/test:last_published_block".to_string(),
+            ))
+            .returning(|_| Ok(Some(107503703)))
+            .once();
+
+        let block_streamer_service =
+            create_block_streamer_service_with_redis_client(mock_redis_client);
+
+        block_streamer_service
+            .restore_block_streams()
+            .await
+            .unwrap();
+
+        let streams = block_streamer_service
+            .list_streams(Request::new(ListStreamsRequest {}))
+            .await
+            .unwrap()
+            .into_inner()
+            .streams;
+
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].stream_id, "16210176318434468568");
+        assert_eq!(streams[0].function_name, "test");
+    }
+
+    #[tokio::test]
+    async fn removes_persisted_block_streams_which_cannot_be_restored() {
+        let mut mock_redis_client = crate::redis::RedisClient::default();
+        mock_redis_client.expect_hgetall::<String>().returning(|_| {
+            Ok(HashMap::from([(
+                "16210176318434468568".to_string(),
+                vec![0xff, 0xff, 0xff],
+            )]))
+        });
+        mock_redis_client
+            .expect_hdel::<String>()
+            .with(
+                mockall::predicate::eq(BLOCK_STREAMS_KEY.to_string()),
+                mockall::predicate::eq("16210176318434468568".to_string()),
+            )
+            .returning(|_, _| Ok(true))
+            .once();
+
+        let block_streamer_service =
+            create_block_streamer_service_with_redis_client(mock_redis_client);
+
+        block_streamer_service
+            .restore_block_streams()
+            .await
+            .unwrap();
+
+        assert!(block_streamer_service
+            .list_streams(Request::new(ListStreamsRequest {}))
+            .await
+            .unwrap()
+            .into_inner()
+            .streams
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn removes_persisted_block_streams_which_are_not_running() {
+        let mut mock_redis_client = crate::redis::RedisClient::default();
+        mock_redis_client
+            .expect_hdel::<String>()
+            .with(
+                mockall::predicate::eq(BLOCK_STREAMS_KEY.to_string()),
+                mockall::predicate::eq("16210176318434468568".to_string()),
+            )
+            .returning(|_, _| Ok(true))
+            .once();
+
+        let block_streamer_service =
+            create_block_streamer_service_with_redis_client(mock_redis_client);
+
+        block_streamer_service
+            .stop_stream(Request::new(StopStreamRequest {
+                stream_id: "16210176318434468568".to_string(),
+            }))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_stopping_unknown_streams() {
+        let mut mock_redis_client = crate::redis::RedisClient::default();
+        mock_redis_client
+            .expect_hdel::<String>()
+            .returning(|_, _| Ok(false));
+
+        let block_streamer_service =
+            create_block_streamer_service_with_redis_client(mock_redis_client);
+
+        let result = block_streamer_service
+            .stop_stream(Request::new(StopStreamRequest {
+                stream_id: "unknown".to_string(),
+            }))
+            .await;
+
+        assert_eq!(result.unwrap_err().code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn stops_block_streams_which_cannot_be_persisted() {
+        let mut mock_redis_client = crate::redis::RedisClient::default();
+        mock_redis_client
+            .expect_xadd_block()
+            .returning(|_, _, _, _| Ok(true));
+        mock_redis_client
+            .expect_xlen::<String>()
+            .returning(|_| Ok(0));
+        mock_redis_client
+            .expect_hset::<String, Vec<u8>>()
+            .returning(|_, _, _| {
+                Err(::redis::RedisError::from((
+                    ::redis::ErrorKind::IoError,
+                    "Connection refused",
+                )))
+            });
+
+        let block_streamer_service =
+            create_block_streamer_service_with_redis_client(mock_redis_client);
+
+        let result = block_streamer_service
+            .start_stream(Request::new(StartStreamRequest {
+                start_block_height: 0,
+                account_id: "morgs.near".to_string(),
+                function_name: "test".to_string(),
+                version: 0,
+                redis_stream: "stream".to_string(),
+                chain: Chain::Mainnet.into(),
+                block_delivery: None,
+                sink: None,
+                rule: Some(start_stream_request::Rule::ActionAnyRule(ActionAnyRule {
+                    affected_account_id: "queryapi.dataplatform.near".to_string(),
+                    status: 1,
+                    affected_account_role: AffectedAccountRole::Any.into(),
+                })),
+            }))
+            .await;
+
+        assert_eq!(result.unwrap_err().code(), tonic::Code::Internal);
+
+        let lock = block_streamer_service.get_block_streams_lock().unwrap();
+        assert_eq!(lock.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn ends_tail_responses_once_block_streams_stop() {
+        let block_streamer_service = create_block_streamer_service();
+
+        let stream_id = block_streamer_service
+            .start_stream(Request::new(StartStreamRequest {
+                start_block_height: 0,
+                account_id: "morgs.near".to_string(),
+                function_name: "test".to_string(),
+                version: 0,
+                redis_stream: "stream".to_string(),
+                chain: Chain::Mainnet.into(),
+                block_delivery: None,
+                sink: None,
+                rule: Some(start_stream_request::Rule::ActionAnyRule(ActionAnyRule {
+                    affected_account_id: "queryapi.dataplatform.near".to_string(),
+                    status: 1,
+                    affected_account_role: AffectedAccountRole::Any.into(),
+                })),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .stream_id;
+
+        let mut responses = block_streamer_service
+            .tail_stream(Request::new(TailStreamRequest { stream_id }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        block_streamer_service
+            .stop_all_block_streams()
+            .await
+            .unwrap();
+
+        let remaining_responses = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            responses.by_ref().collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+
+        assert!(remaining_responses.is_empty());
+    }
+
+    #[tokio::test]
+    async fn ends_tail_responses_with_data_loss_when_lagging() {
+        let (sender, receiver) = tokio::sync::broadcast::channel(1);
+
+        for block_height in [1, 2] {
+            sender
+                .send(crate::block_sink::PublishedBlock {
+                    block_height,
+                    fields: vec![],
+                })
+                .unwrap();
+        }
+        drop(sender);
+
+        let responses = BlockStreamerService::tail_responses(receiver)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(responses.len(), 1);
+        assert_eq!(
+            responses[0].as_ref().unwrap_err().code(),
+            tonic::Code::DataLoss
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_tailing_unknown_streams() {
+        let block_streamer_service = create_block_streamer_service();
+
+        let result = block_streamer_service
+            .tail_stream(Request::new(TailStreamRequest {
+                stream_id: "unknown".to_string(),
+            }))
+            .await;
+
+        assert_eq!(result.err().unwrap().code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn rejects_preview_of_reversed_range() {
+        let block_streamer_service = create_block_streamer_service();
+
+        let result = block_streamer_service
+            .preview_rule(Request::new(PreviewRuleRequest {
+                rule: Some(NestedRule {
+                    rule: Some(nested_rule::Rule::ActionAnyRule(ActionAnyRule {
+                        affected_account_id: "queryapi.dataplatform.near".to_string(),
+                        status: 1,
+                        affected_account_role: AffectedAccountRole::Any.into(),
+                    })),
+                }),
+                chain: Chain::Mainnet.into(),
+                range: Some(preview_rule_request::Range::BlockRange(BlockRange {
+                    start_block_height: 107503705,
+                    end_block_height: 107503704,
+                })),
+                limit: 0,
+            }))
+            .await;
+
+        assert_eq!(result.err().unwrap().code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn rejects_preview_of_oversized_ranges() {
+        let block_streamer_service = create_block_streamer_service();
+
+        for range in [
+            preview_rule_request::Range::BlockRange(BlockRange {
+                start_block_height: 100000000,
+                end_block_height: 100000000 + MAX_PREVIEW_RANGE_BLOCKS,
+            }),
+            preview_rule_request::Range::LastBlocks(MAX_PREVIEW_RANGE_BLOCKS + 1),
+        ] {
+            let result = block_streamer_service
+                .preview_rule(Request::new(PreviewRuleRequest {
+                    rule: Some(NestedRule {
+                        rule: Some(nested_rule::Rule::ActionAnyRule(ActionAnyRule {
+                            affected_account_id: "queryapi.dataplatform.near".to_string(),
+                            status: 1,
+                            affected_account_role: AffectedAccountRole::Any.into(),
+                        })),
+                    }),
+                    chain: Chain::Mainnet.into(),
+                    range: Some(range),
+                    limit: 0,
+                }))
+                .await;
+
+            assert_eq!(result.err().unwrap().code(), tonic::Code::InvalidArgument);
+        }
+    }
+
+    #[tokio::test]
+    async fn previews_last_blocks_of_delta_lake() {
+        let block_streamer_service = create_block_streamer_service();
+
+        let response = block_streamer_service
+            .preview_rule(Request::new(PreviewRuleRequest {
+                rule: Some(NestedRule {
+                    rule: Some(nested_rule::Rule::ActionAnyRule(ActionAnyRule {
+                        affected_account_id: "queryapi.dataplatform.near".to_string(),
+                        status: 1,
+                        affected_account_role: AffectedAccountRole::Any.into(),
+                    })),
+                }),
+                chain: Chain::Mainnet.into(),
+                range: Some(preview_rule_request::Range::LastBlocks(10)),
+                limit: 0,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert!(response.blocks.is_empty());
+        assert_eq!(response.last_block_height, 107503703);
+    }
 }
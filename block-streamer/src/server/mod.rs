@@ -1,31 +1,85 @@
 mod block_streamer_service;
 
+#[allow(clippy::enum_variant_names)]
 pub mod blockstreamer {
     tonic::include_proto!("blockstreamer");
+
+    pub(crate) const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("blockstreamer_descriptor");
 }
 
+type BlockStreamerServer = blockstreamer::block_streamer_server::BlockStreamerServer<
+    block_streamer_service::BlockStreamerService,
+>;
+
+#[allow(clippy::too_many_arguments)]
 pub async fn init(
     port: &str,
     redis_client: std::sync::Arc<crate::redis::RedisClient>,
-    delta_lake_client: std::sync::Arc<crate::delta_lake_client::DeltaLakeClient>,
+    mainnet_delta_lake_client: std::sync::Arc<crate::delta_lake_client::DeltaLakeClient>,
+    testnet_delta_lake_client: std::sync::Arc<crate::delta_lake_client::DeltaLakeClient>,
     lake_s3_config: aws_sdk_s3::Config,
+    redis_stream_watermarks: crate::block_stream::RedisStreamWatermarks,
+    block_sink_policy: crate::block_sink::BlockSinkPolicy,
+    restore_block_streams: bool,
 ) -> anyhow::Result<()> {
     let addr = format!("0.0.0.0:{}", port).parse()?;
 
     tracing::info!("Starting RPC server at {}", addr);
 
-    let block_streamer_service = block_streamer_service::BlockStreamerService::new(
-        redis_client,
-        delta_lake_client,
-        lake_s3_config,
-    );
+    let block_streamer_service =
+        std::sync::Arc::new(block_streamer_service::BlockStreamerService::new(
+            redis_client,
+            mainnet_delta_lake_client,
+            testnet_delta_lake_client,
+            lake_s3_config,
+            redis_stream_watermarks,
+            block_sink_policy,
+        ));
+
+    if restore_block_streams {
+        block_streamer_service.restore_block_streams().await?;
+    }
 
-    let block_streamer_server =
-        blockstreamer::block_streamer_server::BlockStreamerServer::new(block_streamer_service);
+    let block_streamer_server = BlockStreamerServer::from_arc(block_streamer_service.clone());
+
+    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+    health_reporter.set_serving::<BlockStreamerServer>().await;
+
+    let reflection_service = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(blockstreamer::FILE_DESCRIPTOR_SET)
+        .build()?;
 
     tonic::transport::Server::builder()
+        .add_service(health_service)
+        .add_service(reflection_service)
         .add_service(block_streamer_server)
-        .serve(addr)
-        .await
-        .map_err(|err| err.into())
+        .serve_with_shutdown(addr, async move {
+            shutdown_signal().await;
+
+            health_reporter
+                .set_not_serving::<BlockStreamerServer>()
+                .await;
+
+            // TailStream responses only end once their block stream stops, so streams are
+            // stopped before the server waits for connections to drain
+            if let Err(err) = block_streamer_service.stop_all_block_streams().await {
+                tracing::error!("Failed to stop block streams: {:?}", err);
+            }
+        })
+        .await?;
+
+    Ok(())
+}
+
+async fn shutdown_signal() {
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        .expect("Failed to listen for SIGTERM");
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {},
+        _ = sigterm.recv() => {},
+    }
+
+    tracing::info!("Shutting down RPC server");
 }
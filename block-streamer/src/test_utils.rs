@@ -1,3 +1,6 @@
+// Shared by the library and binary test suites, each of which only uses some of these helpers
+#![allow(dead_code)]
+
 use aws_smithy_runtime::client::http::test_util::{ReplayEvent, StaticReplayClient};
 use aws_smithy_types::body::SdkBody;
 use near_lake_framework::near_indexer_primitives;
@@ -106,9 +109,9 @@ fn generate_replay_events_for_blocks(block_heights: &[u64]) -> Vec<ReplayEvent>
     for block_height in block_heights {
         events.extend(generate_replay_events_for_block(*block_height));
     }
-    events.extend(generate_stop_replay_event_for_block(
-        *block_heights.last().unwrap() + 1,
-    ));
+    if let Some(last_block_height) = block_heights.last() {
+        events.extend(generate_stop_replay_event_for_block(last_block_height + 1));
+    }
     events
 }
 
@@ -127,6 +130,18 @@ pub fn create_mock_lake_s3_config(block_heights: &[u64]) -> aws_sdk_s3::Config {
         .build()
 }
 
+/// Creates `S3Config` with a mock HTTP client which responds with an invalid block at
+/// `block_height`, so that `near_lake_framework` fails as soon as it starts.
+pub fn create_failing_mock_lake_s3_config(block_height: u64) -> aws_sdk_s3::Config {
+    let replay_client = StaticReplayClient::new(generate_stop_replay_event_for_block(block_height));
+
+    aws_sdk_s3::Config::builder()
+        .behavior_version_latest()
+        .region(aws_sdk_s3::config::Region::new("eu-central-1"))
+        .http_client(replay_client)
+        .build()
+}
+
 pub fn get_streamer_message(block_height: u64) -> near_indexer_primitives::StreamerMessage {
     let block: near_indexer_primitives::views::BlockView = serde_json::from_slice(
         &std::fs::read(format!(
@@ -5,8 +5,10 @@ pub use block_streamer::StreamInfo;
 use anyhow::Context;
 use block_streamer::block_streamer_client::BlockStreamerClient;
 use block_streamer::{
-    start_stream_request::Rule, ActionAnyRule, ActionFunctionCallRule, ListStreamsRequest,
-    StartStreamRequest, Status, StopStreamRequest,
+    nested_rule, start_stream_request::Rule, ActionAnyRule, ActionFunctionCallRule, ActionKind,
+    ActionKindRule, AffectedAccountRole, AllRule, AnyRule, Chain, ComparisonOperator,
+    DataPredicate, EventRule, ListStreamsRequest, NestedRule, NotRule, StartStreamRequest,
+    StateChangeKind, StateChangeRule, Status, StopStreamRequest,
 };
 use tonic::transport::channel::Channel;
 use tonic::Request;
@@ -80,35 +82,168 @@ impl BlockStreamsHandlerImpl {
         .into()
     }
 
-    pub async fn start(
-        &self,
-        start_block_height: u64,
-        indexer_config: &IndexerConfig,
-    ) -> anyhow::Result<()> {
-        let rule = match &indexer_config.rule {
+    fn match_rule(rule: &registry_types::Rule) -> nested_rule::Rule {
+        match rule {
             registry_types::Rule::ActionAny {
                 affected_account_id,
                 status,
-            } => Rule::ActionAnyRule(ActionAnyRule {
+                affected_account_role,
+            } => nested_rule::Rule::ActionAnyRule(ActionAnyRule {
                 affected_account_id: affected_account_id.to_owned(),
                 status: Self::match_status(status),
+                affected_account_role: Self::match_affected_account_role(affected_account_role),
             }),
             registry_types::Rule::ActionFunctionCall {
                 affected_account_id,
                 status,
+                affected_account_role,
                 function,
-            } => Rule::ActionFunctionCallRule(ActionFunctionCallRule {
+                args_predicates,
+                min_deposit,
+                min_gas,
+            } => nested_rule::Rule::ActionFunctionCallRule(ActionFunctionCallRule {
                 affected_account_id: affected_account_id.to_owned(),
                 function_name: function.to_owned(),
                 status: Self::match_status(status),
+                affected_account_role: Self::match_affected_account_role(affected_account_role),
+                args_predicates: args_predicates
+                    .iter()
+                    .map(Self::match_data_predicate)
+                    .collect(),
+                min_deposit: min_deposit.to_owned(),
+                min_gas: min_gas.to_owned(),
             }),
-            unsupported_rule => {
-                tracing::error!(
-                    "Encountered unsupported indexer rule: {:?}",
-                    unsupported_rule
-                );
-                return Ok(());
+            registry_types::Rule::Event {
+                contract_account_id,
+                standard,
+                version,
+                event,
+                data_predicate,
+            } => nested_rule::Rule::EventRule(EventRule {
+                contract_account_id: contract_account_id.to_owned(),
+                standard: standard.to_owned(),
+                version: version.to_owned(),
+                event: event.to_owned(),
+                data_predicate: data_predicate.as_ref().map(Self::match_data_predicate),
+            }),
+            registry_types::Rule::ActionKind {
+                affected_account_id,
+                status,
+                affected_account_role,
+                action_kinds,
+                min_amount,
+                max_amount,
+            } => nested_rule::Rule::ActionKindRule(ActionKindRule {
+                affected_account_id: affected_account_id.to_owned(),
+                status: Self::match_status(status),
+                affected_account_role: Self::match_affected_account_role(affected_account_role),
+                action_kinds: action_kinds.iter().map(Self::match_action_kind).collect(),
+                min_amount: min_amount.to_owned(),
+                max_amount: max_amount.to_owned(),
+            }),
+            registry_types::Rule::StateChange {
+                affected_account_id,
+                change_kinds,
+            } => nested_rule::Rule::StateChangeRule(StateChangeRule {
+                affected_account_id: affected_account_id.to_owned(),
+                change_kinds: change_kinds
+                    .iter()
+                    .map(Self::match_state_change_kind)
+                    .collect(),
+            }),
+            registry_types::Rule::All { rules } => nested_rule::Rule::AllRule(AllRule {
+                rules: rules.iter().map(Self::match_nested_rule).collect(),
+            }),
+            registry_types::Rule::Any { rules } => nested_rule::Rule::AnyRule(AnyRule {
+                rules: rules.iter().map(Self::match_nested_rule).collect(),
+            }),
+            registry_types::Rule::Not { rule } => nested_rule::Rule::NotRule(Box::new(NotRule {
+                rule: Some(Box::new(Self::match_nested_rule(rule))),
+            })),
+        }
+    }
+
+    fn match_data_predicate(data_predicate: &registry_types::DataPredicate) -> DataPredicate {
+        DataPredicate {
+            path: data_predicate.path.to_owned(),
+            operator: match data_predicate.operator {
+                registry_types::ComparisonOperator::Equals => ComparisonOperator::Equals,
+                registry_types::ComparisonOperator::NotEquals => ComparisonOperator::NotEquals,
+                registry_types::ComparisonOperator::Matches => ComparisonOperator::Matches,
+                registry_types::ComparisonOperator::GreaterThan => ComparisonOperator::GreaterThan,
+                registry_types::ComparisonOperator::GreaterThanOrEqual => {
+                    ComparisonOperator::GreaterThanOrEqual
+                }
+                registry_types::ComparisonOperator::LessThan => ComparisonOperator::LessThan,
+                registry_types::ComparisonOperator::LessThanOrEqual => {
+                    ComparisonOperator::LessThanOrEqual
+                }
+            }
+            .into(),
+            value: data_predicate.value.to_owned(),
+        }
+    }
+
+    fn match_affected_account_role(
+        affected_account_role: &registry_types::AffectedAccountRole,
+    ) -> i32 {
+        match affected_account_role {
+            registry_types::AffectedAccountRole::Any => AffectedAccountRole::Any,
+            registry_types::AffectedAccountRole::Receiver => AffectedAccountRole::Receiver,
+            registry_types::AffectedAccountRole::Predecessor => AffectedAccountRole::Predecessor,
+            registry_types::AffectedAccountRole::Signer => AffectedAccountRole::Signer,
+        }
+        .into()
+    }
+
+    fn match_action_kind(action_kind: &registry_types::ActionKind) -> i32 {
+        match action_kind {
+            registry_types::ActionKind::Transfer => ActionKind::Transfer,
+            registry_types::ActionKind::AddKey => ActionKind::AddKey,
+            registry_types::ActionKind::DeleteKey => ActionKind::DeleteKey,
+            registry_types::ActionKind::DeployContract => ActionKind::DeployContract,
+            registry_types::ActionKind::Stake => ActionKind::Stake,
+            registry_types::ActionKind::CreateAccount => ActionKind::CreateAccount,
+            registry_types::ActionKind::DeleteAccount => ActionKind::DeleteAccount,
+        }
+        .into()
+    }
+
+    fn match_state_change_kind(change_kind: &registry_types::StateChangeKind) -> i32 {
+        match change_kind {
+            registry_types::StateChangeKind::AccountUpdate => StateChangeKind::AccountUpdate,
+            registry_types::StateChangeKind::AccessKeyAdd => StateChangeKind::AccessKeyAdd,
+            registry_types::StateChangeKind::AccessKeyDelete => StateChangeKind::AccessKeyDelete,
+            registry_types::StateChangeKind::ContractDataUpdate => {
+                StateChangeKind::ContractDataUpdate
+            }
+            registry_types::StateChangeKind::CodeDeploy => StateChangeKind::CodeDeploy,
+        }
+        .into()
+    }
+
+    fn match_nested_rule(rule: &registry_types::Rule) -> NestedRule {
+        NestedRule {
+            rule: Some(Self::match_rule(rule)),
+        }
+    }
+
+    pub async fn start(
+        &self,
+        start_block_height: u64,
+        indexer_config: &IndexerConfig,
+    ) -> anyhow::Result<()> {
+        let rule = match Self::match_rule(&indexer_config.rule) {
+            nested_rule::Rule::ActionAnyRule(action_any) => Rule::ActionAnyRule(action_any),
+            nested_rule::Rule::ActionFunctionCallRule(action_function_call) => {
+                Rule::ActionFunctionCallRule(action_function_call)
             }
+            nested_rule::Rule::EventRule(event) => Rule::EventRule(event),
+            nested_rule::Rule::AllRule(all) => Rule::AllRule(all),
+            nested_rule::Rule::AnyRule(any) => Rule::AnyRule(any),
+            nested_rule::Rule::NotRule(not) => Rule::NotRule(*not),
+            nested_rule::Rule::StateChangeRule(state_change) => Rule::StateChangeRule(state_change),
+            nested_rule::Rule::ActionKindRule(action_kind) => Rule::ActionKindRule(action_kind),
         };
 
         let request = StartStreamRequest {
@@ -118,6 +253,9 @@ impl BlockStreamsHandlerImpl {
             account_id: indexer_config.account_id.to_string(),
             function_name: indexer_config.function_name.clone(),
             rule: Some(rule),
+            chain: Chain::Mainnet.into(),
+            block_delivery: None,
+            sink: None,
         };
 
         let response = self
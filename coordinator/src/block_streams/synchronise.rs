@@ -76,6 +76,18 @@ async fn synchronise_block_stream(
 ) -> anyhow::Result<()> {
     if let Some(active_block_stream) = active_block_stream {
         if active_block_stream.version == indexer_config.get_registry_version() {
+            // Streams which are restarting are left to back off within block-streamer, but those
+            // which have exceeded their restart limit are only resumed by starting them again
+            if active_block_stream.state() == block_streamer::StreamState::Failed {
+                return restart_failed_block_stream(
+                    active_block_stream,
+                    indexer_config,
+                    redis_client,
+                    block_streams_handler,
+                )
+                .await;
+            }
+
             return Ok(());
         }
 
@@ -105,6 +117,32 @@ async fn synchronise_block_stream(
     Ok(())
 }
 
+async fn restart_failed_block_stream(
+    failed_block_stream: StreamInfo,
+    indexer_config: &IndexerConfig,
+    redis_client: &RedisClient,
+    block_streams_handler: &BlockStreamsHandler,
+) -> anyhow::Result<()> {
+    tracing::error!(
+        last_published_block_height = failed_block_stream.last_published_block_height,
+        restarts = failed_block_stream.restarts,
+        "Restarting failed block stream: {:?}",
+        failed_block_stream
+            .last_error
+            .map(|last_error| last_error.message)
+    );
+
+    let start_block_height = get_continuation_block_height(indexer_config, redis_client).await?;
+
+    block_streams_handler
+        .stop(failed_block_stream.stream_id)
+        .await?;
+
+    block_streams_handler
+        .start(start_block_height, indexer_config)
+        .await
+}
+
 #[derive(Debug)]
 enum StreamStatus {
     /// Stream has just been migrated to V2
@@ -200,7 +238,7 @@ mod tests {
     use std::collections::HashMap;
 
     use mockall::predicate;
-    use registry_types::{Rule, Status};
+    use registry_types::{AffectedAccountRole, Rule, Status};
 
     #[tokio::test]
     async fn resumes_stream_with_matching_redis_version() {
@@ -212,6 +250,7 @@ mod tests {
             rule: Rule::ActionAny {
                 affected_account_id: "queryapi.dataplatform.near".to_string(),
                 status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
             },
             created_at_block_height: 1,
             updated_at_block_height: Some(200),
@@ -264,6 +303,7 @@ mod tests {
             rule: Rule::ActionAny {
                 affected_account_id: "queryapi.dataplatform.near".to_string(),
                 status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
             },
             created_at_block_height: 1,
             updated_at_block_height: Some(200),
@@ -315,6 +355,7 @@ mod tests {
             rule: Rule::ActionAny {
                 affected_account_id: "queryapi.dataplatform.near".to_string(),
                 status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
             },
             created_at_block_height: 1,
             updated_at_block_height: Some(200),
@@ -366,6 +407,7 @@ mod tests {
             rule: Rule::ActionAny {
                 affected_account_id: "queryapi.dataplatform.near".to_string(),
                 status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
             },
             created_at_block_height: 1,
             updated_at_block_height: Some(200),
@@ -420,6 +462,14 @@ mod tests {
                 account_id: "morgs.near".to_string(),
                 function_name: "test".to_string(),
                 version: 1,
+                chain: block_streamer::Chain::Mainnet.into(),
+                backfill_progress: None,
+                state: block_streamer::StreamState::Streaming.into(),
+                last_published_block_height: 0,
+                blocks_scanned: 0,
+                matches_published: 0,
+                last_error: None,
+                restarts: 0,
             }])
         });
         block_stream_handler
@@ -447,6 +497,122 @@ mod tests {
                     rule: Rule::ActionAny {
                         affected_account_id: "queryapi.dataplatform.near".to_string(),
                         status: Status::Any,
+                        affected_account_role: AffectedAccountRole::Any,
+                    },
+                    created_at_block_height: 101,
+                    updated_at_block_height: None,
+                    start_block: StartBlock::Latest,
+                },
+            )]),
+        )]);
+
+        let redis_client = RedisClient::default();
+
+        let mut block_stream_handler = BlockStreamsHandler::default();
+        block_stream_handler.expect_list().returning(|| {
+            Ok(vec![block_streamer::StreamInfo {
+                stream_id: "stream_id".to_string(),
+                account_id: "morgs.near".to_string(),
+                function_name: "test".to_string(),
+                version: 101,
+                chain: block_streamer::Chain::Mainnet.into(),
+                backfill_progress: None,
+                state: block_streamer::StreamState::Streaming.into(),
+                last_published_block_height: 0,
+                blocks_scanned: 0,
+                matches_published: 0,
+                last_error: None,
+                restarts: 0,
+            }])
+        });
+        block_stream_handler.expect_stop().never();
+        block_stream_handler.expect_start().never();
+
+        synchronise_block_streams(&indexer_registry, &redis_client, &block_stream_handler)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn restarts_failed_stream_from_last_published_block() {
+        let indexer_config = IndexerConfig {
+            account_id: "morgs.near".parse().unwrap(),
+            function_name: "test".to_string(),
+            code: String::new(),
+            schema: String::new(),
+            rule: Rule::ActionAny {
+                affected_account_id: "queryapi.dataplatform.near".to_string(),
+                status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
+            },
+            created_at_block_height: 101,
+            updated_at_block_height: None,
+            start_block: StartBlock::Height(50),
+        };
+        let indexer_registry = HashMap::from([(
+            "morgs.near".parse().unwrap(),
+            HashMap::from([("test".to_string(), indexer_config.clone())]),
+        )]);
+
+        let mut redis_client = RedisClient::default();
+        redis_client
+            .expect_get_last_published_block()
+            .with(predicate::eq(indexer_config.clone()))
+            .returning(|_| Ok(Some(500)))
+            .once();
+        redis_client.expect_clear_block_stream().never();
+
+        let mut block_stream_handler = BlockStreamsHandler::default();
+        block_stream_handler.expect_list().returning(|| {
+            Ok(vec![block_streamer::StreamInfo {
+                stream_id: "stream_id".to_string(),
+                account_id: "morgs.near".to_string(),
+                function_name: "test".to_string(),
+                version: 101,
+                chain: block_streamer::Chain::Mainnet.into(),
+                backfill_progress: None,
+                state: block_streamer::StreamState::Failed.into(),
+                last_published_block_height: 500,
+                blocks_scanned: 0,
+                matches_published: 0,
+                last_error: Some(block_streamer::StreamError {
+                    message: "Delta Lake unavailable".to_string(),
+                    timestamp_ms: 0,
+                }),
+                restarts: 5,
+            }])
+        });
+        block_stream_handler
+            .expect_stop()
+            .with(predicate::eq("stream_id".to_string()))
+            .returning(|_| Ok(()))
+            .once();
+        block_stream_handler
+            .expect_start()
+            .with(predicate::eq(501), predicate::eq(indexer_config))
+            .returning(|_, _| Ok(()))
+            .once();
+
+        synchronise_block_streams(&indexer_registry, &redis_client, &block_stream_handler)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn leaves_restarting_stream_to_back_off() {
+        let indexer_registry = HashMap::from([(
+            "morgs.near".parse().unwrap(),
+            HashMap::from([(
+                "test".to_string(),
+                IndexerConfig {
+                    account_id: "morgs.near".parse().unwrap(),
+                    function_name: "test".to_string(),
+                    code: String::new(),
+                    schema: String::new(),
+                    rule: Rule::ActionAny {
+                        affected_account_id: "queryapi.dataplatform.near".to_string(),
+                        status: Status::Any,
+                        affected_account_role: AffectedAccountRole::Any,
                     },
                     created_at_block_height: 101,
                     updated_at_block_height: None,
@@ -464,6 +630,14 @@ mod tests {
                 account_id: "morgs.near".to_string(),
                 function_name: "test".to_string(),
                 version: 101,
+                chain: block_streamer::Chain::Mainnet.into(),
+                backfill_progress: None,
+                state: block_streamer::StreamState::Restarting.into(),
+                last_published_block_height: 500,
+                blocks_scanned: 0,
+                matches_published: 0,
+                last_error: None,
+                restarts: 1,
             }])
         });
         block_stream_handler.expect_stop().never();
@@ -484,6 +658,7 @@ mod tests {
             rule: Rule::ActionAny {
                 affected_account_id: "queryapi.dataplatform.near".to_string(),
                 status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
             },
             created_at_block_height: 101,
             updated_at_block_height: Some(199),
@@ -518,6 +693,14 @@ mod tests {
                 account_id: "morgs.near".to_string(),
                 function_name: "test".to_string(),
                 version: 101,
+                chain: block_streamer::Chain::Mainnet.into(),
+                backfill_progress: None,
+                state: block_streamer::StreamState::Streaming.into(),
+                last_published_block_height: 0,
+                blocks_scanned: 0,
+                matches_published: 0,
+                last_error: None,
+                restarts: 0,
             }])
         });
         block_stream_handler
@@ -546,6 +729,7 @@ mod tests {
             rule: Rule::ActionAny {
                 affected_account_id: "queryapi.dataplatform.near".to_string(),
                 status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
             },
             created_at_block_height: 101,
             updated_at_block_height: Some(200),
@@ -597,6 +781,7 @@ mod tests {
             rule: Rule::ActionAny {
                 affected_account_id: "queryapi.dataplatform.near".to_string(),
                 status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
             },
             created_at_block_height: 101,
             updated_at_block_height: Some(200),
@@ -639,6 +824,7 @@ mod tests {
             rule: Rule::ActionAny {
                 affected_account_id: "queryapi.dataplatform.near".to_string(),
                 status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
             },
             created_at_block_height: 101,
             updated_at_block_height: None,
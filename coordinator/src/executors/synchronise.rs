@@ -95,7 +95,7 @@ mod tests {
     use std::collections::HashMap;
 
     use mockall::predicate;
-    use registry_types::{Rule, StartBlock, Status};
+    use registry_types::{AffectedAccountRole, Rule, StartBlock, Status};
 
     use crate::indexer_config::IndexerConfig;
 
@@ -109,6 +109,7 @@ mod tests {
             rule: Rule::ActionAny {
                 affected_account_id: "queryapi.dataplatform.near".to_string(),
                 status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
             },
             created_at_block_height: 1,
             updated_at_block_height: None,
@@ -142,6 +143,7 @@ mod tests {
             rule: Rule::ActionAny {
                 affected_account_id: "queryapi.dataplatform.near".to_string(),
                 status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
             },
             created_at_block_height: 1,
             updated_at_block_height: Some(2),
@@ -193,6 +195,7 @@ mod tests {
                     rule: Rule::ActionAny {
                         affected_account_id: "queryapi.dataplatform.near".to_string(),
                         status: Status::Any,
+                        affected_account_role: AffectedAccountRole::Any,
                     },
                     created_at_block_height: 1,
                     updated_at_block_height: Some(2),
@@ -304,7 +304,7 @@ mod tests {
     use std::collections::HashMap;
 
     use mockall::predicate;
-    use registry_types::{Rule, StartBlock, Status};
+    use registry_types::{AffectedAccountRole, Rule, StartBlock, Status};
 
     use crate::indexer_config::IndexerConfig;
 
@@ -322,6 +322,7 @@ mod tests {
                     rule: Rule::ActionAny {
                         affected_account_id: "queryapi.dataplatform.near".to_string(),
                         status: Status::Any,
+                        affected_account_role: AffectedAccountRole::Any,
                     },
                     created_at_block_height: 101,
                     updated_at_block_height: Some(200),
@@ -390,6 +391,7 @@ mod tests {
                     rule: Rule::ActionAny {
                         affected_account_id: "queryapi.dataplatform.near".to_string(),
                         status: Status::Any,
+                        affected_account_role: AffectedAccountRole::Any,
                     },
                     created_at_block_height: 101,
                     updated_at_block_height: Some(200),
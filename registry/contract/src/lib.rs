@@ -5,8 +5,9 @@ use near_sdk::store::UnorderedMap;
 use near_sdk::{env, log, near_bindgen, serde_json, AccountId, BorshStorageKey, CryptoHash};
 
 use registry_types::{
-    AccountIndexers, AllIndexers, IndexerConfig, IndexerRuleKind, MatchingRule,
-    OldAccountOrAllIndexers, OldIndexerConfig, OldIndexerRule, Rule, StartBlock, Status,
+    AccountIndexers, AffectedAccountRole, AllIndexers, DataPredicate, IndexerConfig,
+    IndexerConfigV3, IndexerRuleKind, MatchingRule, OldAccountOrAllIndexers, OldIndexerConfig,
+    OldIndexerRule, Rule, StartBlock, Status,
 };
 
 type FunctionName = String;
@@ -19,7 +20,7 @@ pub struct OldContract {
 
 pub type OldIndexersByAccount = UnorderedMap<AccountId, OldIndexerConfigByFunctionName>;
 
-pub type OldIndexerConfigByFunctionName = UnorderedMap<FunctionName, OldIndexerConfig>;
+pub type OldIndexerConfigByFunctionName = UnorderedMap<FunctionName, IndexerConfigV3>;
 
 // Define the contract structure
 #[near_bindgen]
@@ -44,6 +45,8 @@ enum StorageKeys {
     AccountV2(CryptoHash),
     RegistryV3,
     AccountV3(CryptoHash),
+    RegistryV4,
+    AccountV4(CryptoHash),
 }
 
 /// These roles are used to control access across the various contract methods.
@@ -72,7 +75,7 @@ pub struct AccountRole {
 impl Default for Contract {
     fn default() -> Self {
         Self {
-            registry: IndexersByAccount::new(StorageKeys::RegistryV3),
+            registry: IndexersByAccount::new(StorageKeys::RegistryV4),
             account_roles: vec![
                 AccountRole {
                     account_id: "morgs.near".parse().unwrap(),
@@ -111,11 +114,11 @@ impl Contract {
     pub fn migrate() -> Self {
         let state: OldContract = env::state_read().expect("failed to parse existing state");
 
-        let mut registry = IndexersByAccount::new(StorageKeys::RegistryV3);
+        let mut registry = IndexersByAccount::new(StorageKeys::RegistryV4);
 
         for (account_id, indexers) in state.registry.iter() {
             let mut new_indexers: IndexerConfigByFunctionName = IndexerConfigByFunctionName::new(
-                StorageKeys::AccountV3(env::sha256_array(account_id.as_bytes())),
+                StorageKeys::AccountV4(env::sha256_array(account_id.as_bytes())),
             );
 
             for (function_name, indexer_config) in indexers.iter() {
@@ -138,6 +141,10 @@ impl Contract {
             affected_account_id: contract.to_string(),
             function: method.to_string(),
             status: Status::Any,
+            affected_account_role: AffectedAccountRole::Any,
+            args_predicates: vec![],
+            min_deposit: None,
+            min_gas: None,
         };
         OldIndexerRule {
             indexer_rule_kind: IndexerRuleKind::Action,
@@ -200,6 +207,125 @@ impl Contract {
         }
     }
 
+    /// Composite rules must be non-empty and cannot contain state change rules, data predicates
+    /// must be well formed, and rules which could match receipts from any account are restricted
+    /// to Owners.
+    fn assert_rule_is_permitted(&self, rule: &Rule) {
+        fn assert_data_predicate_is_valid(data_predicate: &DataPredicate) {
+            if data_predicate.path.is_empty() {
+                env::panic_str("Data predicate path cannot be empty");
+            }
+
+            if data_predicate.operator.is_numeric() && data_predicate.value.parse::<f64>().is_err()
+            {
+                env::panic_str(&format!(
+                    "Data predicate value {} must be numeric for {:?} comparisons",
+                    data_predicate.value, data_predicate.operator
+                ));
+            }
+        }
+
+        fn assert_nested_rule_is_valid(rule: &Rule) {
+            if matches!(rule, Rule::StateChange { .. }) {
+                env::panic_str("State change rules cannot be nested within composite rules");
+            }
+
+            assert_rule_is_valid(rule);
+        }
+
+        fn assert_rule_is_valid(rule: &Rule) {
+            match rule {
+                Rule::ActionFunctionCall {
+                    args_predicates,
+                    min_deposit,
+                    ..
+                } => {
+                    args_predicates
+                        .iter()
+                        .for_each(assert_data_predicate_is_valid);
+
+                    if let Some(min_deposit) = min_deposit {
+                        if min_deposit.parse::<u128>().is_err() {
+                            env::panic_str(&format!(
+                                "Minimum deposit {} must be an amount of yoctoNEAR",
+                                min_deposit
+                            ));
+                        }
+                    }
+                }
+                Rule::ActionKind {
+                    action_kinds,
+                    min_amount,
+                    max_amount,
+                    ..
+                } => {
+                    if action_kinds.is_empty() {
+                        env::panic_str("Action kind rules must contain at least one action kind");
+                    }
+
+                    for amount in [min_amount, max_amount].into_iter().flatten() {
+                        if amount.parse::<u128>().is_err() {
+                            env::panic_str(&format!(
+                                "Amount {} must be an amount of yoctoNEAR",
+                                amount
+                            ));
+                        }
+                    }
+                }
+                Rule::Event {
+                    data_predicate: Some(data_predicate),
+                    ..
+                } => assert_data_predicate_is_valid(data_predicate),
+                Rule::StateChange { change_kinds, .. } => {
+                    if change_kinds.is_empty() {
+                        env::panic_str("State change rules must contain at least one change kind");
+                    }
+                }
+                Rule::All { rules } | Rule::Any { rules } => {
+                    if rules.is_empty() {
+                        env::panic_str("Composite rules must contain at least one rule");
+                    }
+
+                    rules.iter().for_each(assert_nested_rule_is_valid);
+                }
+                Rule::Not { rule } => assert_nested_rule_is_valid(rule),
+                _ => {}
+            }
+        }
+
+        fn is_unbounded(rule: &Rule) -> bool {
+            match rule {
+                Rule::ActionAny {
+                    affected_account_id,
+                    ..
+                }
+                | Rule::ActionFunctionCall {
+                    affected_account_id,
+                    ..
+                }
+                | Rule::ActionKind {
+                    affected_account_id,
+                    ..
+                }
+                | Rule::StateChange {
+                    affected_account_id,
+                    ..
+                } => affected_account_id == "*",
+                Rule::Event { .. } => false,
+                Rule::All { rules } => rules.iter().all(is_unbounded),
+                Rule::Any { rules } => rules.iter().any(is_unbounded),
+                // A negated rule matches everything else, unless narrowed by a sibling in `All`
+                Rule::Not { .. } => true,
+            }
+        }
+
+        assert_rule_is_valid(rule);
+
+        if is_unbounded(rule) {
+            self.assert_roles(vec![Role::Owner]);
+        }
+    }
+
     pub fn list_account_roles(&self) -> Vec<AccountRole> {
         self.account_roles.clone()
     }
@@ -268,26 +394,12 @@ impl Contract {
             &account_id
         );
 
-        match &rule {
-            Rule::ActionAny {
-                affected_account_id,
-                ..
-            }
-            | Rule::ActionFunctionCall {
-                affected_account_id,
-                ..
-            } => {
-                if affected_account_id == "*" {
-                    self.assert_roles(vec![Role::Owner]);
-                }
-            }
-            _ => {}
-        }
+        self.assert_rule_is_permitted(&rule);
 
         let account_indexers =
             self.registry
                 .entry(account_id.clone())
-                .or_insert(IndexerConfigByFunctionName::new(StorageKeys::AccountV3(
+                .or_insert(IndexerConfigByFunctionName::new(StorageKeys::AccountV4(
                     env::sha256_array(account_id.as_bytes()),
                 )));
 
@@ -347,21 +459,7 @@ impl Contract {
                         env::panic_str(&format!("Invalid filter JSON {}", e));
                     });
 
-                match &filter_rule.matching_rule {
-                    MatchingRule::ActionAny {
-                        affected_account_id,
-                        ..
-                    }
-                    | MatchingRule::ActionFunctionCall {
-                        affected_account_id,
-                        ..
-                    } => {
-                        if affected_account_id == "*" {
-                            self.assert_roles(vec![Role::Owner]);
-                        }
-                    }
-                    _ => {}
-                }
+                self.assert_rule_is_permitted(&filter_rule.matching_rule.clone().into());
 
                 filter_rule
             }
@@ -377,7 +475,7 @@ impl Contract {
         let account_indexers =
             self.registry
                 .entry(account_id.clone())
-                .or_insert(IndexerConfigByFunctionName::new(StorageKeys::AccountV3(
+                .or_insert(IndexerConfigByFunctionName::new(StorageKeys::AccountV4(
                     env::sha256_array(account_id.as_bytes()),
                 )));
 
@@ -495,7 +593,7 @@ impl Contract {
     pub fn list_by_account(&self, account_id: AccountId) -> AccountIndexers {
         self.registry
             .get(&account_id)
-            .unwrap_or(&IndexerConfigByFunctionName::new(StorageKeys::AccountV3(
+            .unwrap_or(&IndexerConfigByFunctionName::new(StorageKeys::AccountV4(
                 env::sha256_array(account_id.as_bytes()),
             )))
             .iter()
@@ -525,39 +623,40 @@ mod tests {
 
     use std::collections::HashMap;
 
+    use registry_types::{ActionKind, ComparisonOperator, RuleV3, StateChangeKind};
+
     #[test]
     fn migrate() {
-        let mut registry = OldIndexersByAccount::new(StorageKeys::RegistryV2);
+        let mut registry = OldIndexersByAccount::new(StorageKeys::RegistryV3);
         let account_id = "morgs.near".parse::<AccountId>().unwrap();
-        let mut functions = OldIndexerConfigByFunctionName::new(StorageKeys::AccountV2(
+        let mut functions = OldIndexerConfigByFunctionName::new(StorageKeys::AccountV3(
             env::sha256_array(account_id.as_bytes()),
         ));
 
         functions.insert(
             "test".to_string(),
-            OldIndexerConfig {
+            IndexerConfigV3 {
                 code: "return block;".to_string(),
-                start_block_height: None,
-                schema: None,
-                filter: Contract::near_social_indexer_rule(),
+                start_block: StartBlock::Latest,
+                schema: String::new(),
+                rule: RuleV3::ActionFunctionCall {
+                    affected_account_id: String::from("social.near"),
+                    status: Status::Any,
+                    function: String::from("set"),
+                },
                 created_at_block_height: 10,
                 updated_at_block_height: None,
             },
         );
         functions.insert(
             "test2".to_string(),
-            OldIndexerConfig {
+            IndexerConfigV3 {
                 code: "return block2;".to_string(),
-                start_block_height: Some(100),
-                schema: Some(String::from("create table blah")),
-                filter: OldIndexerRule {
-                    id: None,
-                    name: None,
-                    indexer_rule_kind: IndexerRuleKind::Action,
-                    matching_rule: MatchingRule::ActionAny {
-                        affected_account_id: String::from("social.near"),
-                        status: Status::Success,
-                    },
+                start_block: StartBlock::Height(100),
+                schema: String::from("create table blah"),
+                rule: RuleV3::ActionAny {
+                    affected_account_id: String::from("social.near"),
+                    status: Status::Success,
                 },
                 created_at_block_height: 10,
                 updated_at_block_height: Some(20),
@@ -586,7 +685,11 @@ mod tests {
                 rule: Rule::ActionFunctionCall {
                     affected_account_id: String::from("social.near"),
                     status: Status::Any,
-                    function: String::from("set")
+                    affected_account_role: AffectedAccountRole::Any,
+                    function: String::from("set"),
+                    args_predicates: vec![],
+                    min_deposit: None,
+                    min_gas: None,
                 },
                 updated_at_block_height: None,
                 created_at_block_height: 10,
@@ -605,7 +708,8 @@ mod tests {
                 start_block: StartBlock::Height(100),
                 rule: Rule::ActionAny {
                     affected_account_id: String::from("social.near"),
-                    status: Status::Success
+                    status: Status::Success,
+                    affected_account_role: AffectedAccountRole::Any,
                 },
                 updated_at_block_height: Some(20),
                 created_at_block_height: 10,
@@ -614,6 +718,106 @@ mod tests {
         assert_eq!(contract.account_roles, Contract::default().account_roles);
     }
 
+    /// Encodes an `IndexerConfig` field by field as persisted under `StorageKeys::RegistryV3`, so
+    /// that the bytes do not depend on the current types.
+    fn encode_v3_indexer_config(rule: Vec<u8>) -> Vec<u8> {
+        [
+            borsh::to_vec("return block;").unwrap(),
+            // StartBlock::Height
+            vec![0],
+            borsh::to_vec(&100u64).unwrap(),
+            borsh::to_vec("create table blah").unwrap(),
+            rule,
+            borsh::to_vec(&Some(20u64)).unwrap(),
+            borsh::to_vec(&10u64).unwrap(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn decodes_v3_action_any_rule() {
+        let rule = [
+            // RuleV3::ActionAny
+            vec![0],
+            borsh::to_vec("social.near").unwrap(),
+            // Status::Success
+            vec![1],
+        ]
+        .concat();
+
+        let config = IndexerConfigV3::try_from_slice(&encode_v3_indexer_config(rule)).unwrap();
+
+        assert_eq!(
+            IndexerConfig::from(config),
+            IndexerConfig {
+                code: "return block;".to_string(),
+                start_block: StartBlock::Height(100),
+                schema: String::from("create table blah"),
+                rule: Rule::ActionAny {
+                    affected_account_id: String::from("social.near"),
+                    status: Status::Success,
+                    affected_account_role: AffectedAccountRole::Any,
+                },
+                updated_at_block_height: Some(20),
+                created_at_block_height: 10,
+            }
+        );
+    }
+
+    #[test]
+    fn decodes_v3_action_function_call_rule() {
+        let rule = [
+            // RuleV3::ActionFunctionCall
+            vec![1],
+            borsh::to_vec("social.near").unwrap(),
+            // Status::Any
+            vec![0],
+            borsh::to_vec("set").unwrap(),
+        ]
+        .concat();
+
+        let config = IndexerConfigV3::try_from_slice(&encode_v3_indexer_config(rule)).unwrap();
+
+        assert_eq!(
+            IndexerConfig::from(config).rule,
+            Rule::ActionFunctionCall {
+                affected_account_id: String::from("social.near"),
+                status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
+                function: String::from("set"),
+                args_predicates: vec![],
+                min_deposit: None,
+                min_gas: None,
+            }
+        );
+    }
+
+    #[test]
+    fn decodes_v3_event_rule() {
+        let rule = [
+            // RuleV3::Event
+            vec![2],
+            borsh::to_vec("nft.near").unwrap(),
+            borsh::to_vec("nep171").unwrap(),
+            borsh::to_vec("1.0.0").unwrap(),
+            borsh::to_vec("nft_mint").unwrap(),
+        ]
+        .concat();
+
+        let config = IndexerConfigV3::try_from_slice(&encode_v3_indexer_config(rule)).unwrap();
+
+        assert_eq!(
+            IndexerConfig::from(config).rule,
+            Rule::Event {
+                contract_account_id: String::from("nft.near"),
+                standard: String::from("nep171"),
+                version: String::from("1.0.0"),
+                event: String::from("nft_mint"),
+                data_predicate: None,
+            }
+        );
+    }
+
     #[test]
     fn list_account_roles() {
         let admins = vec![
@@ -1054,6 +1258,10 @@ mod tests {
                     affected_account_id: "test".to_string(),
                     function: "test".to_string(),
                     status: Status::Fail,
+                    affected_account_role: AffectedAccountRole::Any,
+                    args_predicates: vec![],
+                    min_deposit: None,
+                    min_gas: None,
                 },
                 id: None,
                 name: None,
@@ -1103,6 +1311,7 @@ mod tests {
                 matching_rule: MatchingRule::ActionAny {
                     affected_account_id: "test".to_string(),
                     status: Status::Success,
+                    affected_account_role: AffectedAccountRole::Any,
                 },
                 id: None,
                 name: None,
@@ -1134,6 +1343,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn register_indexer_function_with_affected_account_role() {
+        let mut contract = Contract {
+            registry: IndexersByAccount::new(StorageKeys::Registry),
+            account_roles: vec![AccountRole {
+                account_id: "bob.near".parse().unwrap(),
+                role: Role::User,
+            }],
+        };
+
+        contract.register_indexer_function(
+            "test".to_string(),
+            "var x= 1;".to_string(),
+            None,
+            None,
+            None,
+            Some(r#"{"indexer_rule_kind":"Action","matching_rule":{"rule":"ACTION_FUNCTION_CALL","affected_account_id":"test","function":"test","status":"ANY","affected_account_role":"RECEIVER"}}"#.to_string()),
+        );
+
+        assert_eq!(
+            contract
+                .registry
+                .get(&"bob.near".parse::<AccountId>().unwrap())
+                .unwrap()
+                .get("test")
+                .unwrap()
+                .rule,
+            Rule::ActionFunctionCall {
+                affected_account_id: "test".to_string(),
+                status: Status::Any,
+                affected_account_role: AffectedAccountRole::Receiver,
+                function: "test".to_string(),
+                args_predicates: vec![],
+                min_deposit: None,
+                min_gas: None,
+            }
+        );
+    }
+
     #[test]
     #[should_panic(expected = "Invalid filter JSON")]
     fn register_indexer_function_with_invalid_filter() {
@@ -1172,6 +1420,7 @@ mod tests {
                 rule: Rule::ActionAny {
                     affected_account_id: String::from("social.near"),
                     status: Status::Success,
+                    affected_account_role: AffectedAccountRole::Any,
                 },
                 updated_at_block_height: None,
                 created_at_block_height: 100,
@@ -1225,6 +1474,7 @@ mod tests {
                 rule: Rule::ActionAny {
                     affected_account_id: String::from("social.near"),
                     status: Status::Success,
+                    affected_account_role: AffectedAccountRole::Any,
                 },
                 updated_at_block_height: None,
                 created_at_block_height: 100,
@@ -1306,6 +1556,335 @@ mod tests {
         assert_eq!(contract.registry.len(), 1);
     }
 
+    #[test]
+    fn register_composite_rule() {
+        let mut contract = Contract::default();
+        let rule = Rule::All {
+            rules: vec![
+                Rule::Any {
+                    rules: vec![
+                        Rule::ActionFunctionCall {
+                            affected_account_id: String::from("a.near"),
+                            status: Status::Success,
+                            affected_account_role: AffectedAccountRole::Any,
+                            function: String::from("x"),
+                            args_predicates: vec![],
+                            min_deposit: None,
+                            min_gas: None,
+                        },
+                        Rule::Event {
+                            contract_account_id: String::from("b.near"),
+                            standard: String::from("nep171"),
+                            version: String::from("*"),
+                            event: String::from("y"),
+                            data_predicate: None,
+                        },
+                    ],
+                },
+                Rule::Not {
+                    rule: Box::new(Rule::ActionAny {
+                        affected_account_id: String::from("z.near"),
+                        status: Status::Any,
+                        affected_account_role: AffectedAccountRole::Any,
+                    }),
+                },
+            ],
+        };
+
+        contract.register(
+            String::from("test"),
+            String::from("code"),
+            String::from("schema"),
+            rule.clone(),
+            StartBlock::Latest,
+        );
+
+        assert_eq!(
+            contract
+                .list_by_account("bob.near".parse().unwrap())
+                .get("test")
+                .unwrap()
+                .rule,
+            rule
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Account bob.near does not have any roles")]
+    fn prevents_non_owners_from_using_unbounded_negation() {
+        let mut contract = Contract::default();
+
+        contract.register(
+            String::from("test"),
+            String::from("code"),
+            String::from("schema"),
+            Rule::Not {
+                rule: Box::new(Rule::ActionAny {
+                    affected_account_id: String::from("z.near"),
+                    status: Status::Any,
+                    affected_account_role: AffectedAccountRole::Any,
+                }),
+            },
+            StartBlock::Latest,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Account bob.near does not have any roles")]
+    fn prevents_non_owners_from_using_nested_wildcard() {
+        let mut contract = Contract::default();
+
+        contract.register(
+            String::from("test"),
+            String::from("code"),
+            String::from("schema"),
+            Rule::Any {
+                rules: vec![
+                    Rule::ActionAny {
+                        affected_account_id: String::from("social.near"),
+                        status: Status::Any,
+                        affected_account_role: AffectedAccountRole::Any,
+                    },
+                    Rule::ActionAny {
+                        affected_account_id: String::from("*"),
+                        status: Status::Any,
+                        affected_account_role: AffectedAccountRole::Any,
+                    },
+                ],
+            },
+            StartBlock::Latest,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Composite rules must contain at least one rule")]
+    fn rejects_empty_composite_rule() {
+        let mut contract = Contract::default();
+
+        contract.register(
+            String::from("test"),
+            String::from("code"),
+            String::from("schema"),
+            Rule::Any { rules: vec![] },
+            StartBlock::Latest,
+        );
+    }
+
+    #[test]
+    fn register_state_change_rule() {
+        let mut contract = Contract::default();
+        let rule = Rule::StateChange {
+            affected_account_id: String::from("*.sweat"),
+            change_kinds: vec![
+                StateChangeKind::AccountUpdate,
+                StateChangeKind::AccessKeyAdd,
+            ],
+        };
+
+        contract.register(
+            String::from("test"),
+            String::from("code"),
+            String::from("schema"),
+            rule.clone(),
+            StartBlock::Latest,
+        );
+
+        assert_eq!(
+            contract
+                .list_by_account("bob.near".parse().unwrap())
+                .get("test")
+                .unwrap()
+                .rule,
+            rule
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "State change rules cannot be nested within composite rules")]
+    fn rejects_nested_state_change_rule() {
+        let mut contract = Contract::default();
+
+        contract.register(
+            String::from("test"),
+            String::from("code"),
+            String::from("schema"),
+            Rule::Any {
+                rules: vec![Rule::StateChange {
+                    affected_account_id: String::from("social.near"),
+                    change_kinds: vec![StateChangeKind::ContractDataUpdate],
+                }],
+            },
+            StartBlock::Latest,
+        );
+    }
+
+    #[test]
+    fn register_function_call_rule_with_filters() {
+        let mut contract = Contract::default();
+        let rule = Rule::ActionFunctionCall {
+            affected_account_id: String::from("nft.near"),
+            status: Status::Success,
+            affected_account_role: AffectedAccountRole::Any,
+            function: String::from("nft_transfer"),
+            args_predicates: vec![DataPredicate {
+                path: String::from("token_id"),
+                operator: ComparisonOperator::LessThanOrEqual,
+                value: String::from("1000"),
+            }],
+            min_deposit: Some(String::from("1")),
+            min_gas: Some(30_000_000_000_000),
+        };
+
+        contract.register(
+            String::from("test"),
+            String::from("code"),
+            String::from("schema"),
+            rule.clone(),
+            StartBlock::Latest,
+        );
+
+        assert_eq!(
+            contract
+                .list_by_account("bob.near".parse().unwrap())
+                .get("test")
+                .unwrap()
+                .rule,
+            rule
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Minimum deposit 0.1 must be an amount of yoctoNEAR")]
+    fn rejects_invalid_minimum_deposit() {
+        let mut contract = Contract::default();
+
+        contract.register(
+            String::from("test"),
+            String::from("code"),
+            String::from("schema"),
+            Rule::ActionFunctionCall {
+                affected_account_id: String::from("nft.near"),
+                status: Status::Success,
+                affected_account_role: AffectedAccountRole::Any,
+                function: String::from("nft_transfer"),
+                args_predicates: vec![],
+                min_deposit: Some(String::from("0.1")),
+                min_gas: None,
+            },
+            StartBlock::Latest,
+        );
+    }
+
+    #[test]
+    fn register_action_kind_rule() {
+        let mut contract = Contract::default();
+        let rule = Rule::ActionKind {
+            affected_account_id: String::from("*.exchange.near"),
+            status: Status::Success,
+            affected_account_role: AffectedAccountRole::Any,
+            action_kinds: vec![ActionKind::Transfer],
+            min_amount: Some(String::from("1000000000000000000000000000")),
+            max_amount: None,
+        };
+
+        contract.register(
+            String::from("test"),
+            String::from("code"),
+            String::from("schema"),
+            rule.clone(),
+            StartBlock::Latest,
+        );
+
+        assert_eq!(
+            contract
+                .list_by_account("bob.near".parse().unwrap())
+                .get("test")
+                .unwrap()
+                .rule,
+            rule
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Action kind rules must contain at least one action kind")]
+    fn rejects_empty_action_kind_rule() {
+        let mut contract = Contract::default();
+
+        contract.register(
+            String::from("test"),
+            String::from("code"),
+            String::from("schema"),
+            Rule::ActionKind {
+                affected_account_id: String::from("*.exchange.near"),
+                status: Status::Success,
+                affected_account_role: AffectedAccountRole::Any,
+                action_kinds: vec![],
+                min_amount: None,
+                max_amount: None,
+            },
+            StartBlock::Latest,
+        );
+    }
+
+    #[test]
+    fn register_event_rule_with_data_predicate() {
+        let mut contract = Contract::default();
+        let rule = Rule::Event {
+            contract_account_id: String::from("token.sweat"),
+            standard: String::from("nep141"),
+            version: String::from("*"),
+            event: String::from("ft_transfer"),
+            data_predicate: Some(DataPredicate {
+                path: String::from("data[].new_owner_id"),
+                operator: ComparisonOperator::Matches,
+                value: String::from("*.sweat"),
+            }),
+        };
+
+        contract.register(
+            String::from("test"),
+            String::from("code"),
+            String::from("schema"),
+            rule.clone(),
+            StartBlock::Latest,
+        );
+
+        assert_eq!(
+            contract
+                .list_by_account("bob.near".parse().unwrap())
+                .get("test")
+                .unwrap()
+                .rule,
+            rule
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Data predicate value many must be numeric for GreaterThan comparisons"
+    )]
+    fn rejects_non_numeric_ordering_data_predicate() {
+        let mut contract = Contract::default();
+
+        contract.register(
+            String::from("test"),
+            String::from("code"),
+            String::from("schema"),
+            Rule::Event {
+                contract_account_id: String::from("token.sweat"),
+                standard: String::from("nep141"),
+                version: String::from("*"),
+                event: String::from("ft_transfer"),
+                data_predicate: Some(DataPredicate {
+                    path: String::from("data[].amount"),
+                    operator: ComparisonOperator::GreaterThan,
+                    value: String::from("many"),
+                }),
+            },
+            StartBlock::Latest,
+        );
+    }
+
     #[test]
     fn users_can_remove_their_own_functions() {
         let account_id = "bob.near".parse::<AccountId>().unwrap();
@@ -1321,6 +1900,7 @@ mod tests {
                 rule: Rule::ActionAny {
                     affected_account_id: String::from("social.near"),
                     status: Status::Success,
+                    affected_account_role: AffectedAccountRole::Any,
                 },
                 updated_at_block_height: None,
                 created_at_block_height: 100,
@@ -1359,6 +1939,7 @@ mod tests {
                 rule: Rule::ActionAny {
                     affected_account_id: String::from("social.near"),
                     status: Status::Success,
+                    affected_account_role: AffectedAccountRole::Any,
                 },
                 updated_at_block_height: None,
                 created_at_block_height: 100,
@@ -1398,6 +1979,7 @@ mod tests {
                 rule: Rule::ActionAny {
                     affected_account_id: String::from("social.near"),
                     status: Status::Success,
+                    affected_account_role: AffectedAccountRole::Any,
                 },
                 updated_at_block_height: None,
                 created_at_block_height: 100,
@@ -1431,6 +2013,7 @@ mod tests {
                 rule: Rule::ActionAny {
                     affected_account_id: String::from("social.near"),
                     status: Status::Success,
+                    affected_account_role: AffectedAccountRole::Any,
                 },
                 updated_at_block_height: None,
                 created_at_block_height: 100,
@@ -1470,6 +2053,7 @@ mod tests {
                 rule: Rule::ActionAny {
                     affected_account_id: String::from("social.near"),
                     status: Status::Success,
+                    affected_account_role: AffectedAccountRole::Any,
                 },
                 updated_at_block_height: None,
                 created_at_block_height: 100,
@@ -1500,6 +2084,7 @@ mod tests {
                 rule: Rule::ActionAny {
                     affected_account_id: String::from("social.near"),
                     status: Status::Success,
+                    affected_account_role: AffectedAccountRole::Any,
                 },
                 updated_at_block_height: None,
                 created_at_block_height: 100,
@@ -1514,6 +2099,7 @@ mod tests {
                 rule: Rule::ActionAny {
                     affected_account_id: String::from("social.near"),
                     status: Status::Success,
+                    affected_account_role: AffectedAccountRole::Any,
                 },
                 updated_at_block_height: None,
                 created_at_block_height: 100,
@@ -1732,6 +2318,7 @@ mod tests {
             Rule::ActionAny {
                 affected_account_id: String::from("social.near"),
                 status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
             },
             StartBlock::Latest,
         );
@@ -1748,6 +2335,7 @@ mod tests {
                         rule: Rule::ActionAny {
                             affected_account_id: String::from("social.near"),
                             status: Status::Any,
+                            affected_account_role: AffectedAccountRole::Any,
                         },
                         start_block: StartBlock::Latest,
                         updated_at_block_height: None,
@@ -1769,6 +2357,7 @@ mod tests {
             Rule::ActionAny {
                 affected_account_id: String::from("social.near"),
                 status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
             },
             StartBlock::Latest,
         );
@@ -1790,6 +2379,7 @@ mod tests {
             Rule::ActionAny {
                 affected_account_id: String::from("social.near"),
                 status: Status::Any,
+                affected_account_role: AffectedAccountRole::Any,
             },
             StartBlock::Latest,
         );
@@ -1804,6 +2394,7 @@ mod tests {
                     rule: Rule::ActionAny {
                         affected_account_id: String::from("social.near"),
                         status: Status::Any,
+                        affected_account_role: AffectedAccountRole::Any,
                     },
                     start_block: StartBlock::Latest,
                     updated_at_block_height: None,
@@ -22,17 +22,53 @@ pub enum MatchingRule {
     ActionAny {
         affected_account_id: String,
         status: Status,
+        #[serde(default)]
+        affected_account_role: AffectedAccountRole,
     },
     ActionFunctionCall {
         affected_account_id: String,
         status: Status,
+        #[serde(default)]
+        affected_account_role: AffectedAccountRole,
         function: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args_predicates: Vec<DataPredicate>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_deposit: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_gas: Option<u64>,
     },
     Event {
         contract_account_id: String,
         standard: String,
         version: String,
         event: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        data_predicate: Option<DataPredicate>,
+    },
+    ActionKind {
+        affected_account_id: String,
+        status: Status,
+        #[serde(default)]
+        affected_account_role: AffectedAccountRole,
+        action_kinds: Vec<ActionKind>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_amount: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_amount: Option<String>,
+    },
+    StateChange {
+        affected_account_id: String,
+        change_kinds: Vec<StateChangeKind>,
+    },
+    All {
+        matching_rules: Vec<MatchingRule>,
+    },
+    Any {
+        matching_rules: Vec<MatchingRule>,
+    },
+    Not {
+        matching_rule: Box<MatchingRule>,
     },
 }
 
@@ -42,29 +78,72 @@ impl From<Rule> for MatchingRule {
             Rule::ActionAny {
                 affected_account_id,
                 status,
+                affected_account_role,
             } => MatchingRule::ActionAny {
                 affected_account_id,
                 status,
+                affected_account_role,
             },
             Rule::Event {
                 contract_account_id,
                 standard,
                 version,
                 event,
+                data_predicate,
             } => MatchingRule::Event {
                 contract_account_id,
                 standard,
                 version,
                 event,
+                data_predicate,
             },
             Rule::ActionFunctionCall {
                 affected_account_id,
                 status,
+                affected_account_role,
                 function,
+                args_predicates,
+                min_deposit,
+                min_gas,
             } => MatchingRule::ActionFunctionCall {
                 affected_account_id,
                 status,
+                affected_account_role,
                 function,
+                args_predicates,
+                min_deposit,
+                min_gas,
+            },
+            Rule::ActionKind {
+                affected_account_id,
+                status,
+                affected_account_role,
+                action_kinds,
+                min_amount,
+                max_amount,
+            } => MatchingRule::ActionKind {
+                affected_account_id,
+                status,
+                affected_account_role,
+                action_kinds,
+                min_amount,
+                max_amount,
+            },
+            Rule::StateChange {
+                affected_account_id,
+                change_kinds,
+            } => MatchingRule::StateChange {
+                affected_account_id,
+                change_kinds,
+            },
+            Rule::All { rules } => MatchingRule::All {
+                matching_rules: rules.into_iter().map(Into::into).collect(),
+            },
+            Rule::Any { rules } => MatchingRule::Any {
+                matching_rules: rules.into_iter().map(Into::into).collect(),
+            },
+            Rule::Not { rule } => MatchingRule::Not {
+                matching_rule: Box::new((*rule).into()),
             },
         }
     }
@@ -141,24 +220,142 @@ pub enum Status {
     Fail,
 }
 
+/// Which account of a receipt must match the affected account pattern
+#[derive(
+    Clone, Debug, Default, Serialize, Deserialize, BorshSerialize, BorshDeserialize, PartialEq, Eq,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AffectedAccountRole {
+    /// Either the receiver or the predecessor
+    #[default]
+    Any,
+    Receiver,
+    Predecessor,
+    /// The signer of the transaction the receipt originated from
+    Signer,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ActionKind {
+    Transfer,
+    AddKey,
+    DeleteKey,
+    DeployContract,
+    Stake,
+    CreateAccount,
+    DeleteAccount,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StateChangeKind {
+    AccountUpdate,
+    AccessKeyAdd,
+    AccessKeyDelete,
+    ContractDataUpdate,
+    CodeDeploy,
+}
+
+/// Compares the values found at `path` within a JSON document against `value`, matching when any
+/// of them satisfy the comparison.
+///
+/// `path` is a dot separated list of object keys, where a key suffixed with `[]` matches every
+/// element of an array, and `[n]` the element at index `n`, e.g. `data[].new_owner_id`. Ordering
+/// comparisons treat both sides as numbers, and `Matches` treats `value` as a wildcard pattern.
+#[derive(Clone, Debug, Serialize, Deserialize, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub struct DataPredicate {
+    pub path: String,
+    pub operator: ComparisonOperator,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ComparisonOperator {
+    Equals,
+    NotEquals,
+    Matches,
+    GreaterThan,
+    GreaterThanOrEqual,
+    LessThan,
+    LessThanOrEqual,
+}
+
+impl ComparisonOperator {
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            ComparisonOperator::GreaterThan
+                | ComparisonOperator::GreaterThanOrEqual
+                | ComparisonOperator::LessThan
+                | ComparisonOperator::LessThanOrEqual
+        )
+    }
+}
+
+/// Rules are Borsh encoded within contract state, so fields cannot be added to existing variants
+/// without migrating the state, see `RuleV3`.
 #[derive(Clone, Debug, Serialize, Deserialize, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
 #[serde(tag = "kind", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum Rule {
     ActionAny {
         affected_account_id: String,
         status: Status,
+        #[serde(default)]
+        affected_account_role: AffectedAccountRole,
     },
     ActionFunctionCall {
         affected_account_id: String,
         status: Status,
+        #[serde(default)]
+        affected_account_role: AffectedAccountRole,
         function: String,
+        /// Predicates which the JSON decoded call arguments must all satisfy
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args_predicates: Vec<DataPredicate>,
+        /// Minimum attached deposit, in yoctoNEAR
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_deposit: Option<String>,
+        /// Minimum attached gas
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_gas: Option<u64>,
     },
     Event {
         contract_account_id: String,
         standard: String,
         version: String,
         event: String,
+        /// Predicate which the event `data` must satisfy
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        data_predicate: Option<DataPredicate>,
     },
+    /// Matches receipts containing any of the specified kinds of action. Amount thresholds, in
+    /// yoctoNEAR, apply to the deposit of transfers and the stake of staking actions, so exclude
+    /// all other kinds when set.
+    ActionKind {
+        affected_account_id: String,
+        status: Status,
+        #[serde(default)]
+        affected_account_role: AffectedAccountRole,
+        action_kinds: Vec<ActionKind>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_amount: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_amount: Option<String>,
+    },
+    /// Matches changes to the state of the affected account. Cannot be nested within composite
+    /// rules, as state changes are not matched per receipt.
+    StateChange {
+        affected_account_id: String,
+        change_kinds: Vec<StateChangeKind>,
+    },
+    /// Matches when every one of the nested rules matches
+    All { rules: Vec<Rule> },
+    /// Matches when at least one of the nested rules matches
+    Any { rules: Vec<Rule> },
+    /// Matches when the nested rule does not match
+    Not { rule: Box<Rule> },
 }
 
 impl From<MatchingRule> for Rule {
@@ -167,29 +364,72 @@ impl From<MatchingRule> for Rule {
             MatchingRule::ActionAny {
                 affected_account_id,
                 status,
+                affected_account_role,
             } => Rule::ActionAny {
                 affected_account_id,
                 status,
+                affected_account_role,
             },
             MatchingRule::Event {
                 contract_account_id,
                 standard,
                 version,
                 event,
+                data_predicate,
             } => Rule::Event {
                 contract_account_id,
                 standard,
                 version,
                 event,
+                data_predicate,
             },
             MatchingRule::ActionFunctionCall {
                 affected_account_id,
                 status,
+                affected_account_role,
                 function,
+                args_predicates,
+                min_deposit,
+                min_gas,
             } => Rule::ActionFunctionCall {
                 affected_account_id,
                 status,
+                affected_account_role,
                 function,
+                args_predicates,
+                min_deposit,
+                min_gas,
+            },
+            MatchingRule::ActionKind {
+                affected_account_id,
+                status,
+                affected_account_role,
+                action_kinds,
+                min_amount,
+                max_amount,
+            } => Rule::ActionKind {
+                affected_account_id,
+                status,
+                affected_account_role,
+                action_kinds,
+                min_amount,
+                max_amount,
+            },
+            MatchingRule::StateChange {
+                affected_account_id,
+                change_kinds,
+            } => Rule::StateChange {
+                affected_account_id,
+                change_kinds,
+            },
+            MatchingRule::All { matching_rules } => Rule::All {
+                rules: matching_rules.into_iter().map(Into::into).collect(),
+            },
+            MatchingRule::Any { matching_rules } => Rule::Any {
+                rules: matching_rules.into_iter().map(Into::into).collect(),
+            },
+            MatchingRule::Not { matching_rule } => Rule::Not {
+                rule: Box::new((*matching_rule).into()),
             },
         }
     }
@@ -236,3 +476,88 @@ impl From<OldIndexerConfig> for IndexerConfig {
 pub type AccountIndexers = HashMap<FunctionName, IndexerConfig>;
 
 pub type AllIndexers = HashMap<AccountId, AccountIndexers>;
+
+/// `Rule` as persisted under `StorageKeys::RegistryV3`, before fields were added to its existing
+/// variants. Only used to migrate state, so must not be modified.
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub enum RuleV3 {
+    ActionAny {
+        affected_account_id: String,
+        status: Status,
+    },
+    ActionFunctionCall {
+        affected_account_id: String,
+        status: Status,
+        function: String,
+    },
+    Event {
+        contract_account_id: String,
+        standard: String,
+        version: String,
+        event: String,
+    },
+}
+
+impl From<RuleV3> for Rule {
+    fn from(value: RuleV3) -> Self {
+        match value {
+            RuleV3::ActionAny {
+                affected_account_id,
+                status,
+            } => Rule::ActionAny {
+                affected_account_id,
+                status,
+                affected_account_role: AffectedAccountRole::Any,
+            },
+            RuleV3::ActionFunctionCall {
+                affected_account_id,
+                status,
+                function,
+            } => Rule::ActionFunctionCall {
+                affected_account_id,
+                status,
+                affected_account_role: AffectedAccountRole::Any,
+                function,
+                args_predicates: vec![],
+                min_deposit: None,
+                min_gas: None,
+            },
+            RuleV3::Event {
+                contract_account_id,
+                standard,
+                version,
+                event,
+            } => Rule::Event {
+                contract_account_id,
+                standard,
+                version,
+                event,
+                data_predicate: None,
+            },
+        }
+    }
+}
+
+/// `IndexerConfig` as persisted under `StorageKeys::RegistryV3`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct IndexerConfigV3 {
+    pub code: String,
+    pub start_block: StartBlock,
+    pub schema: String,
+    pub rule: RuleV3,
+    pub updated_at_block_height: Option<u64>,
+    pub created_at_block_height: u64,
+}
+
+impl From<IndexerConfigV3> for IndexerConfig {
+    fn from(config: IndexerConfigV3) -> Self {
+        Self {
+            code: config.code,
+            start_block: config.start_block,
+            schema: config.schema,
+            rule: config.rule.into(),
+            updated_at_block_height: config.updated_at_block_height,
+            created_at_block_height: config.created_at_block_height,
+        }
+    }
+}